@@ -0,0 +1,261 @@
+//! Lossless conversion between bencode and JSON.
+//!
+//! Byte strings that are valid UTF-8 become JSON strings. Anything else
+//! becomes `{"$hex": ".."}` or `{"$base64": ".."}`, and a dict key that is
+//! not UTF-8 becomes `"$hex:.."` or `"$base64:.."`. Dict keys that really
+//! start with `$` are escaped as `$$`, so these annotations cannot be
+//! confused with data.
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hanekawa_bencode::{Map, Value};
+use serde_json::{Map as JsonMap, Value as Json};
+
+/// How byte strings that are not UTF-8 are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binary {
+    Hex,
+    Base64,
+}
+
+impl Binary {
+    const ALL: [Binary; 2] = [Binary::Hex, Binary::Base64];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hex => "hex",
+            Self::Base64 => "base64",
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::Hex => "$hex",
+            Self::Base64 => "$base64",
+        }
+    }
+
+    fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Hex => hex::encode(bytes),
+            Self::Base64 => STANDARD.encode(bytes),
+        }
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Hex => hex::decode(s).map_err(|e| e.to_string()),
+            Self::Base64 => STANDARD.decode(s).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("invalid {} string {:?}: {}", self.as_str(), s, e))
+    }
+}
+
+impl FromStr for Binary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|b| b.as_str() == s)
+            .ok_or_else(|| format!("unknown binary format: {}", s))
+    }
+}
+
+fn key_to_json(key: &[u8], binary: Binary) -> String {
+    match std::str::from_utf8(key) {
+        Ok(key) if key.starts_with('$') => format!("${}", key),
+        Ok(key) => key.to_owned(),
+        Err(_) => format!("{}:{}", binary.tag(), binary.encode(key)),
+    }
+}
+
+pub fn to_json<B: AsRef<[u8]> + Ord>(value: &Value<B>, binary: Binary) -> Json {
+    match value {
+        Value::Int(i) => Json::from(*i),
+        Value::Bytes(b) => match std::str::from_utf8(b.as_ref()) {
+            Ok(s) => Json::String(s.to_owned()),
+            Err(_) => {
+                let mut annotated = JsonMap::new();
+                annotated.insert(
+                    binary.tag().to_owned(),
+                    Json::String(binary.encode(b.as_ref())),
+                );
+                Json::Object(annotated)
+            }
+        },
+        Value::List(vs) => Json::Array(vs.iter().map(|v| to_json(v, binary)).collect()),
+        Value::Dict(m) => Json::Object(
+            m.iter()
+                .map(|(k, v)| (key_to_json(k.as_ref(), binary), to_json(v, binary)))
+                .collect(),
+        ),
+    }
+}
+
+fn key_from_json(key: &str) -> Result<Vec<u8>, String> {
+    let annotated = match key.strip_prefix('$') {
+        Some(annotated) => annotated,
+        None => return Ok(key.as_bytes().to_vec()),
+    };
+    if annotated.starts_with('$') {
+        return Ok(annotated.as_bytes().to_vec());
+    }
+
+    Binary::ALL
+        .into_iter()
+        .find_map(|b| Some((b, annotated.strip_prefix(b.as_str())?.strip_prefix(':')?)))
+        .ok_or_else(|| format!("unknown dict key annotation: {:?}", key))
+        .and_then(|(b, encoded)| b.decode(encoded))
+}
+
+/// The byte string an annotated object like `{"$hex": ".."}` stands for.
+fn annotated_bytes(object: &JsonMap<String, Json>) -> Option<Result<Vec<u8>, String>> {
+    let (tag, encoded) = match object.iter().next() {
+        Some((tag, Json::String(encoded))) if object.len() == 1 => (tag, encoded),
+        _ => return None,
+    };
+    let binary = Binary::ALL.into_iter().find(|b| b.tag() == tag)?;
+
+    Some(binary.decode(encoded))
+}
+
+/// Convert JSON in the format [`to_json`] produces back to bencode, with
+/// dict keys sorted so that it encodes canonically.
+pub fn from_json(json: &Json) -> Result<Value<Vec<u8>>, String> {
+    match json {
+        Json::Null => Err("bencode has no null".to_owned()),
+        Json::Bool(_) => Err("bencode has no booleans".to_owned()),
+        Json::Number(n) => n
+            .as_i64()
+            .map(Value::Int)
+            .ok_or_else(|| format!("not a 64-bit integer: {}", n)),
+        Json::String(s) => Ok(Value::Bytes(s.as_bytes().to_vec())),
+        Json::Array(vs) => vs
+            .iter()
+            .map(from_json)
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        Json::Object(object) => {
+            if let Some(bytes) = annotated_bytes(object) {
+                return bytes.map(Value::Bytes);
+            }
+
+            let mut entries = object
+                .iter()
+                .map(|(k, v)| Ok((key_from_json(k)?, from_json(v)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            entries.sort_by(|x, y| x.0.cmp(&y.0));
+            if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(format!(
+                    "duplicate dict key: {}",
+                    key_to_json(&pair[0].0, Binary::Hex)
+                ));
+            }
+
+            Ok(Value::Dict(Map::from_raw(entries)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hanekawa_bencode::{bencode, encode, parse};
+    use serde_json::json;
+
+    fn round_trip(input: &[u8], binary: Binary) -> Vec<u8> {
+        let value = parse(input).unwrap().into_value();
+        let json = to_json(&value, binary);
+        let text = serde_json::to_string(&json).unwrap();
+        encode(&from_json(&serde_json::from_str(&text).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn annotates_binary_strings() {
+        let value = bencode!({
+            "name" => "test",
+            "pieces" => &[0xffu8, 0x00][..],
+            "$ref" => "x",
+            &[0xfeu8][..] => -1,
+        });
+        assert_eq!(
+            json!({
+                "name": "test",
+                "pieces": { "$hex": "ff00" },
+                "$$ref": "x",
+                "$hex:fe": -1,
+            }),
+            to_json(&value, Binary::Hex)
+        );
+        assert_eq!(
+            json!({
+                "name": "test",
+                "pieces": { "$base64": "/wA=" },
+                "$$ref": "x",
+                "$base64:/g==": -1,
+            }),
+            to_json(&value, Binary::Base64)
+        );
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        for input in [
+            &b"i-20e"[..],
+            b"0:",
+            b"le",
+            b"de",
+            b"d3:cowli1e2:abe4:spamd1:xi-20eee",
+            b"d4:$hexi1e1:\xffl2:\x00\xffee",
+            b"d4:$hex4:\xff\xff\xff\xffe",
+            b"d1:$d5:$$hex0:ee",
+        ] {
+            for binary in Binary::ALL {
+                assert_eq!(input, round_trip(input, binary), "{:?}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_torrents() {
+        static SAMPLES: include_dir::Dir =
+            include_dir::include_dir!("$CARGO_MANIFEST_DIR/../hanekawa-bencode/benches/samples");
+
+        for file in SAMPLES.files() {
+            for binary in Binary::ALL {
+                assert_eq!(
+                    file.contents(),
+                    round_trip(file.contents(), binary),
+                    "{:?}",
+                    file.path()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_json_canonically() {
+        let json = json!({ "b": [1, "x"], "$hex:61": {}, "c": { "$base64": "AP8=" } });
+        assert_eq!(
+            &b"d1:ade1:bli1e1:xe1:c2:\x00\xffe"[..],
+            encode(&from_json(&json).unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_json_without_bencode_equivalent() {
+        for json in [
+            json!(null),
+            json!(true),
+            json!(1.5),
+            json!(u64::MAX),
+            json!({ "$hex": "zz" }),
+            json!({ "$nope:00": 1 }),
+            json!({ "a": 1, "$hex:61": 2 }),
+        ] {
+            assert!(from_json(&json).is_err(), "{}", json);
+        }
+    }
+}
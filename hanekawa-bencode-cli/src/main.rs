@@ -0,0 +1,188 @@
+mod json;
+
+use std::io::{Read, Write};
+
+use hanekawa_bencode::{Elements, ParseOptions, Parser};
+use json::Binary;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+const USAGE: &str = "\
+usage: bencode <command> [options] [FILE]
+
+Reads FILE, or standard input if FILE is missing or `-`.
+
+commands:
+    json [--binary hex|base64]
+        Print bencode as JSON. Byte strings that are not UTF-8 are shown as
+        {\"$hex\": \"..\"} or {\"$base64\": \"..\"} (hex by default), dict keys that
+        are not UTF-8 as \"$hex:..\" or \"$base64:..\", and dict keys starting
+        with `$` are escaped as `$$`.
+    encode
+        Convert JSON in the format above to canonical bencode.
+    check
+        Check that the input is canonical bencode.
+    info-hash
+        Print the v1 and v2 info hashes of a .torrent file.
+";
+
+enum Command {
+    Json { binary: Binary },
+    Encode,
+    Check,
+    InfoHash,
+}
+
+struct Args {
+    command: Command,
+    file: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut command = match args.next().as_deref() {
+        None | Some("-h") | Some("--help") | Some("help") => return Ok(None),
+        Some("json") => Command::Json {
+            binary: Binary::Hex,
+        },
+        Some("encode") => Command::Encode,
+        Some("check") => Command::Check,
+        Some("info-hash") => Command::InfoHash,
+        Some(other) => return Err(format!("unknown command: {}", other)),
+    };
+    let mut file = None;
+
+    while let Some(arg) = args.next() {
+        match (&mut command, arg.as_str()) {
+            (_, "-h" | "--help") => return Ok(None),
+            (Command::Json { binary }, "--binary") => {
+                *binary = args
+                    .next()
+                    .ok_or_else(|| "--binary needs a value".to_owned())?
+                    .parse()?;
+            }
+            (Command::Json { binary }, arg) if arg.starts_with("--binary=") => {
+                *binary = arg["--binary=".len()..].parse()?;
+            }
+            (_, arg) if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option: {}", arg))
+            }
+            (_, _) if file.is_some() => return Err(format!("unexpected argument: {}", arg)),
+            (_, _) => file = Some(arg),
+        }
+    }
+
+    Ok(Some(Args { command, file }))
+}
+
+fn read_input(file: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut input = vec![];
+    match file {
+        None | Some("-") => std::io::stdin().lock().read_to_end(&mut input),
+        Some(path) => std::fs::File::open(path).and_then(|mut f| f.read_to_end(&mut input)),
+    }
+    .map_err(|e| format!("{}: {}", file.unwrap_or("<stdin>"), e))?;
+
+    Ok(input)
+}
+
+fn parse<'a>(input: &'a [u8], options: &ParseOptions) -> Result<Elements<&'a [u8]>, String> {
+    Parser::new(input, options)
+        .parse_with_offset()
+        .map_err(|(e, offset)| format!("invalid bencode at offset {}: {}", offset, e))
+}
+
+fn to_json(input: &[u8], binary: Binary) -> Result<(), String> {
+    let value = parse(input, &ParseOptions::default())?.into_value();
+    let json = json::to_json(&value, binary);
+
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &json).map_err(|e| e.to_string())?;
+    writeln!(stdout).map_err(|e| e.to_string())
+}
+
+fn encode(input: &[u8]) -> Result<(), String> {
+    let json = serde_json::from_slice(input).map_err(|e| format!("invalid JSON: {}", e))?;
+    let value = json::from_json(&json)?;
+
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    hanekawa_bencode::encode_to_writer(&value, &mut stdout)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| e.to_string())
+}
+
+fn check(input: &[u8]) -> Result<(), String> {
+    let strict = ParseOptions {
+        strict: true,
+        ..Default::default()
+    };
+    parse(input, &ParseOptions::default())?;
+    Parser::new(input, &strict)
+        .parse_with_offset()
+        .map_err(|(e, _)| format!("not canonical: {}", e))?;
+
+    println!("canonical");
+    Ok(())
+}
+
+/// Unlike `Metainfo::from_bytes`, this only needs an info dict, so it also
+/// works on torrents that fail validation. A v1 info dict has `pieces`, and a
+/// v2 one has `meta version` 2; a hybrid torrent has both.
+fn info_hash(input: &[u8]) -> Result<(), String> {
+    let options = ParseOptions {
+        spans: true,
+        ..Default::default()
+    };
+    let elements = parse(input, &options)?;
+    let raw_info = elements
+        .raw(input, &[b"info"])
+        .ok_or_else(|| "missing info dict".to_owned())?;
+    let info = elements.into_value();
+    let info = info
+        .get("info")
+        .filter(|info| info.as_dict().is_some())
+        .ok_or_else(|| "missing info dict".to_owned())?;
+
+    let has_v1 = info.get("pieces").is_some();
+    let has_v2 = info.get("meta version").and_then(|v| v.as_int()) == Some(2);
+    if !has_v1 && !has_v2 {
+        return Err("info dict is neither v1 nor v2".to_owned());
+    }
+
+    if has_v1 {
+        println!("v1 {}", hex::encode(Sha1::digest(raw_info)));
+    }
+    if has_v2 {
+        println!("v2 {}", hex::encode(Sha256::digest(raw_info)));
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let input = read_input(args.file.as_deref())?;
+
+    match args.command {
+        Command::Json { binary } => to_json(&input, binary),
+        Command::Encode => encode(&input),
+        Command::Check => check(&input),
+        Command::InfoHash => info_hash(&input),
+    }
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("bencode: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(args) {
+        eprintln!("bencode: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,6 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
 use include_dir::{include_dir, Dir};
 
+use bytes::BytesMut;
+
 use hanekawa_bencode;
 
 const TORRENT_SAMPLES: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/benches/samples/");
@@ -36,6 +38,24 @@ pub fn encode_torrents(c: &mut Criterion) {
                 hanekawa_bencode::encode(&parsed);
             })
         });
+
+        group.bench_function("encoded_len", |b| {
+            b.iter(|| hanekawa_bencode::encoded_len(&parsed))
+        });
+
+        let mut buf = BytesMut::with_capacity(hanekawa_bencode::encoded_len(&parsed));
+        group.bench_function("encode_into(reused)", |b| {
+            b.iter(|| {
+                buf.clear();
+                hanekawa_bencode::encode_into(&parsed, &mut buf);
+            })
+        });
+
+        group.bench_function("encode_to_writer", |b| {
+            b.iter(|| {
+                hanekawa_bencode::encode_to_writer(&parsed, std::io::sink()).unwrap();
+            })
+        });
     }
 }
 
@@ -53,6 +73,14 @@ pub fn encode_torrents_serde(c: &mut Criterion) {
                 hanekawa_bencode::to_bytes(&parsed).unwrap();
             })
         });
+
+        let mut buf = BytesMut::new();
+        group.bench_function("encode(serde, reused)", |b| {
+            b.iter(|| {
+                buf.clear();
+                hanekawa_bencode::to_buf(&parsed, &mut buf).unwrap();
+            })
+        });
     }
 }
 
@@ -0,0 +1,37 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use hanekawa_bencode::{parse, parse_with_options, stream::Decoder, ParseOptions};
+
+fuzz_target!(|input: &[u8]| {
+    if let Ok(elements) = parse(input) {
+        elements.into_value();
+    }
+
+    let options = ParseOptions {
+        max_depth: 8,
+        max_elements: 64,
+        max_string_len: 32,
+        strict: true,
+        spans: true,
+    };
+    let parsed = parse_with_options(input, &options);
+    if let Ok(elements) = &parsed {
+        let spans = elements.spans().unwrap();
+        assert_eq!(Some(&(0..input.len())), spans.first());
+        assert!(spans.iter().all(|s| s.start < s.end && s.end <= input.len()));
+    }
+
+    // The streaming decoder accepts exactly what the parser accepts when fed
+    // in chunks.
+    let mut decoder = Decoder::new(&options);
+    let streamed = input
+        .chunks(3)
+        .try_for_each(|chunk| {
+            decoder.feed(chunk);
+            while decoder.next_event()?.is_some() {}
+            Ok(())
+        })
+        .and_then(|_| decoder.finish());
+    assert_eq!(parsed.is_ok(), streamed.is_ok());
+});
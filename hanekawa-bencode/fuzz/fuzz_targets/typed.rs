@@ -0,0 +1,38 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use hanekawa_bencode::{from_bytes, to_bytes};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, arbitrary::Arbitrary, Serialize, Deserialize)]
+enum Kind {
+    Unit,
+    Int(i64),
+    Nested(Box<Record>),
+}
+
+#[derive(Debug, PartialEq, arbitrary::Arbitrary, Serialize, Deserialize)]
+struct Extra {
+    note: String,
+    #[serde(default)]
+    count: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, arbitrary::Arbitrary, Serialize, Deserialize)]
+struct Record {
+    name: String,
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+    small: i16,
+    tags: Vec<String>,
+    parent: Option<Box<Record>>,
+    kinds: Vec<Kind>,
+    #[serde(flatten)]
+    extra: Extra,
+}
+
+fuzz_target!(|input: Record| {
+    let encoded = to_bytes(&input).unwrap();
+    let decoded: Record = from_bytes(&encoded).unwrap();
+    assert_eq!(input, decoded);
+});
@@ -0,0 +1,578 @@
+use crate::{Element, Elements};
+
+use super::{ParseOptions, Parser};
+
+use serde::de::{self, DeserializeSeed, Unexpected, Visitor};
+
+struct Deserializer<'a, 'de> {
+    elements: &'a [Element<&'de [u8]>],
+    next: usize,
+}
+
+impl<'a, 'de> Deserializer<'a, 'de> {
+    fn new(elements: &'a Elements<&'de [u8]>) -> Self {
+        Self {
+            elements: elements.as_slice(),
+            next: 0,
+        }
+    }
+
+    fn next(&mut self) -> Result<&'a Element<&'de [u8]>, Error> {
+        let element = self
+            .elements
+            .get(self.next)
+            .ok_or_else(|| Error::new("unexpected end of input"))?;
+        self.next += 1;
+
+        Ok(element)
+    }
+
+    /// Skip the next element, along with everything inside it.
+    fn skip(&mut self) -> Result<(), Error> {
+        let mut pending = 1;
+        while pending > 0 {
+            pending += match self.next()? {
+                Element::ListBegin(ct) => *ct,
+                Element::DictBegin(ct) => 2 * ct,
+                _ => 0,
+            };
+            pending -= 1;
+        }
+
+        Ok(())
+    }
+
+    fn visit_seq<V: Visitor<'de>>(&mut self, ct: usize, visitor: V) -> Result<V::Value, Error> {
+        let mut access = Access {
+            de: self,
+            remaining: ct,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+
+        if access.remaining > 0 {
+            Err(Error::new("trailing elements in list"))?
+        }
+
+        Ok(value)
+    }
+
+    fn visit_map<V: Visitor<'de>>(&mut self, ct: usize, visitor: V) -> Result<V::Value, Error> {
+        let mut access = Access {
+            de: self,
+            remaining: ct,
+        };
+        let value = visitor.visit_map(&mut access)?;
+
+        if access.remaining > 0 {
+            Err(Error::new("trailing entries in dict"))?
+        }
+
+        Ok(value)
+    }
+}
+
+fn unexpected<'a>(element: &'a Element<&[u8]>) -> Unexpected<'a> {
+    match element {
+        Element::DictBegin(_) => Unexpected::Map,
+        Element::ListBegin(_) => Unexpected::Seq,
+        Element::Int(i) => Unexpected::Signed(*i),
+        Element::Bytes(b) => Unexpected::Bytes(b),
+    }
+}
+
+fn invalid_type<T>(element: &Element<&[u8]>, exp: &dyn de::Expected) -> Result<T, Error> {
+    Err(de::Error::invalid_type(unexpected(element), exp))
+}
+
+fn utf8(b: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(b).map_err(|_| Error::new("invalid utf-8 in string"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    message: String,
+    offset: Option<usize>,
+}
+
+impl Error {
+    fn new<T: ToString>(message: T) -> Self {
+        Self {
+            message: message.to_string(),
+            offset: None,
+        }
+    }
+
+    fn at(self, offset: usize) -> Self {
+        Self {
+            offset: self.offset.or(Some(offset)),
+            ..self
+        }
+    }
+
+    /// The offset in the input of the element that could not be decoded.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.offset {
+            Some(offset) => f.write_fmt(format_args!("{} at offset {}", self.message, offset)),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Self::new(msg)
+    }
+}
+
+fn unsupported_element<T>(which: &str) -> Result<T, Error> {
+    Err(Error::new(format!("unsupported element: {}", which)))
+}
+
+impl<'a, 'b, 'de> de::Deserializer<'de> for &'b mut Deserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::Int(i) => visitor.visit_i64(*i),
+            Element::Bytes(b) => match std::str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(b),
+            },
+            Element::ListBegin(ct) => self.visit_seq(*ct, visitor),
+            Element::DictBegin(ct) => self.visit_map(*ct, visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::Int(0) => visitor.visit_bool(false),
+            Element::Int(1) => visitor.visit_bool(true),
+            Element::Int(i) => Err(de::Error::invalid_value(Unexpected::Signed(*i), &visitor)),
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        unsupported_element("f32")
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        unsupported_element("f64")
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::Bytes(b) => {
+                let s = utf8(b)?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => visitor.visit_char(c),
+                    _ => Err(de::Error::invalid_value(Unexpected::Str(s), &visitor)),
+                }
+            }
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::Bytes(b) => visitor.visit_borrowed_str(utf8(b)?),
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::Bytes(b) => visitor.visit_borrowed_bytes(b),
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // None is never encoded, so anything that is present is Some.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        unsupported_element("unit")
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        unsupported_element("unit struct")
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::ListBegin(ct) => self.visit_seq(*ct, visitor),
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next()? {
+            Element::DictBegin(ct) => self.visit_map(*ct, visitor),
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        // Unit variants are encoded as their name, and all others as a dict
+        // from their name to their contents.
+        match self.next()? {
+            Element::Bytes(b) => {
+                visitor.visit_enum(de::value::BorrowedStrDeserializer::new(utf8(b)?))
+            }
+            Element::DictBegin(1) => visitor.visit_enum(self),
+            other => invalid_type(other, &visitor),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.skip()?;
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128
+    }
+}
+
+struct Access<'b, 'a, 'de> {
+    de: &'b mut Deserializer<'a, 'de>,
+    remaining: usize,
+}
+
+impl<'b, 'a, 'de> de::SeqAccess<'de> for Access<'b, 'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'b, 'a, 'de> de::MapAccess<'de> for Access<'b, 'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'b, 'a, 'de> de::EnumAccess<'de> for &'b mut Deserializer<'a, 'de> {
+    type Error = Error;
+
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(&mut *self)?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'b, 'a, 'de> de::VariantAccess<'de> for &'b mut Deserializer<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.skip()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserialize a `T` from bencoded input, borrowing strings and byte strings
+/// from it where `T` allows.
+pub fn from_bytes<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
+    // Spans are only needed to report where an error occurred.
+    let options = ParseOptions {
+        spans: true,
+        ..Default::default()
+    };
+    let elements = Parser::new(input, &options)
+        .parse_with_offset()
+        .map_err(|(e, offset)| Error::new(e).at(offset))?;
+
+    let mut deserializer = Deserializer::new(&elements);
+
+    T::deserialize(&mut deserializer).map_err(|e| {
+        let index = deserializer.next.saturating_sub(1);
+        let offset = elements
+            .spans()
+            .and_then(|spans| spans.get(index))
+            .map_or(input.len(), |span| span.start);
+        e.at(offset)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::to_bytes;
+
+    use include_dir::{include_dir, Dir};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    static TORRENT_SAMPLES_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/benches/samples/");
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Unit,
+        Newtype(i64),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Extra {
+        note: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record<'a> {
+        name: &'a str,
+        #[serde(with = "serde_bytes")]
+        data: &'a [u8],
+        count: u32,
+        tags: Vec<String>,
+        parent: Option<i64>,
+        kinds: Vec<Kind>,
+        #[serde(flatten)]
+        extra: Extra,
+    }
+
+    #[test]
+    fn deserializes_primitives() {
+        assert_eq!(Ok(-3), from_bytes::<i64>(b"i-3e"));
+        assert_eq!(Ok("spam"), from_bytes::<&str>(b"4:spam"));
+        assert_eq!(Ok(&b"\xff\x00"[..]), from_bytes::<&[u8]>(b"2:\xff\x00"));
+        assert_eq!(Ok(true), from_bytes::<bool>(b"i1e"));
+        assert_eq!(Ok('x'), from_bytes::<char>(b"1:x"));
+        assert_eq!(
+            Ok(vec!["a".to_string(), "b".to_string()]),
+            from_bytes::<Vec<String>>(b"l1:a1:be")
+        );
+        assert_eq!(
+            Ok(BTreeMap::from([("cow", 1), ("spam", 2)])),
+            from_bytes::<BTreeMap<&str, u8>>(b"d3:cowi1e4:spami2ee")
+        );
+    }
+
+    #[test]
+    fn round_trips_structs() {
+        let record = Record {
+            name: "spam",
+            data: b"\x00\x01\xff",
+            count: 3,
+            tags: vec!["a".to_string()],
+            parent: None,
+            kinds: vec![Kind::Unit, Kind::Newtype(2)],
+            extra: Extra {
+                note: "eggs".to_string(),
+            },
+        };
+
+        let encoded = to_bytes(&record).unwrap();
+        assert_eq!(Ok(record), from_bytes(&encoded));
+    }
+
+    #[test]
+    fn deserializes_enums() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Shape {
+            Point,
+            Circle(i64),
+            Line(i64, i64),
+            Rect { w: i64, h: i64 },
+        }
+
+        assert_eq!(Ok(Shape::Point), from_bytes(b"5:Point"));
+        assert_eq!(Ok(Shape::Circle(2)), from_bytes(b"d6:Circlei2ee"));
+        assert_eq!(Ok(Shape::Line(1, 2)), from_bytes(b"d4:Lineli1ei2eee"));
+        assert_eq!(
+            Ok(Shape::Rect { w: 3, h: 4 }),
+            from_bytes(b"d4:Rectd1:hi4e1:wi3eee")
+        );
+        assert!(from_bytes::<Shape>(b"d5:Pointi1e6:Circlei2ee").is_err());
+    }
+
+    #[test]
+    fn ignores_unknown_fields() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Small {
+            b: i64,
+        }
+
+        assert_eq!(
+            Ok(Small { b: 2 }),
+            from_bytes(b"d1:ad1:xl1:yi1eee1:bi2e1:cli3eee")
+        );
+    }
+
+    #[test]
+    fn reports_error_offsets() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Small {
+            a: i64,
+            b: String,
+        }
+
+        let err = from_bytes::<Small>(b"d1:ai1e1:bi2ee").unwrap_err();
+        assert_eq!(Some(10), err.offset());
+
+        let err = from_bytes::<u8>(b"i300e").unwrap_err();
+        assert_eq!(Some(0), err.offset());
+
+        let err = from_bytes::<i64>(b"i1ex").unwrap_err();
+        assert_eq!(Some(3), err.offset());
+
+        let err = from_bytes::<String>(b"2:\xff\xff").unwrap_err();
+        assert_eq!("invalid utf-8 in string at offset 0", err.to_string());
+    }
+
+    #[test]
+    fn deserializes_torrents() {
+        #[derive(Deserialize)]
+        struct Info<'a> {
+            name: &'a str,
+            #[serde(rename = "piece length")]
+            piece_length: u64,
+            #[serde(with = "serde_bytes")]
+            pieces: &'a [u8],
+        }
+
+        #[derive(Deserialize)]
+        struct Torrent<'a> {
+            #[serde(borrow)]
+            info: Info<'a>,
+        }
+
+        for sample in TORRENT_SAMPLES_DIR.files() {
+            let torrent: Torrent = from_bytes(sample.contents()).unwrap();
+
+            assert!(!torrent.info.name.is_empty());
+            assert!(torrent.info.piece_length > 0);
+            assert_eq!(0, torrent.info.pieces.len() % 20);
+        }
+    }
+}
@@ -1,19 +1,123 @@
+pub mod de;
+pub mod stream;
+
+use std::ops::Range;
+
 use super::{Element, Elements, Error};
 
-pub fn parse(input: &[u8]) -> Result<Elements<&[u8]>, ()> {
-    let parser = Parser::new(input);
-    parser.parse().map_err(|_| ())
+/// Limits on what the parser accepts, so hostile input cannot exhaust the
+/// stack or memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// How deeply lists and dicts may be nested.
+    pub max_depth: usize,
+    /// How many elements the input may contain in total.
+    pub max_elements: usize,
+    /// The longest byte string the input may contain.
+    pub max_string_len: usize,
+    /// Reject anything that is not canonical bencode: unsorted or duplicate
+    /// dict keys, string lengths with leading zeros, and explicitly positive
+    /// integers. Each canonical value has exactly one encoding, so its hash
+    /// can be trusted.
+    pub strict: bool,
+    /// Record where each element starts and ends in the input.
+    pub spans: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 256,
+            max_elements: usize::MAX,
+            max_string_len: usize::MAX,
+            strict: false,
+            spans: false,
+        }
+    }
+}
+
+pub fn parse(input: &[u8]) -> Result<Elements<&[u8]>, Error> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_with_options<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> Result<Elements<&'a [u8]>, Error> {
+    Parser::new(input, options).parse()
+}
+
+#[inline(always)]
+fn parse_raw_int<T: lexical::FromLexical>(input: &[u8]) -> Result<T, Error> {
+    lexical::parse(input).map_err(|e| match e {
+        lexical::Error::Overflow(_) | lexical::Error::Underflow(_) => {
+            Error::IntOverflow(input.to_vec())
+        }
+        _ => Error::InvalidInt(input.to_vec()),
+    })
+}
+
+/// Parse the length prefix of a byte string that starts at `start`.
+#[inline(always)]
+fn parse_length(len: &[u8], options: &ParseOptions, start: usize) -> Result<usize, Error> {
+    // Lexical would also accept a leading `+`, which is never valid.
+    if !len.iter().all(u8::is_ascii_digit) {
+        Err(Error::InvalidLength(len.to_vec()))?
+    }
+    if options.strict && len.len() > 1 && len[0] == b'0' {
+        Err(Error::NonCanonicalLength(start))?
+    }
+    let len_num: usize = parse_raw_int(len).map_err(|e| match e {
+        Error::IntOverflow(len) => Error::LengthOverflow(len),
+        Error::InvalidInt(len) => Error::InvalidLength(len),
+        e => e,
+    })?;
+    if len_num > options.max_string_len {
+        Err(Error::StringTooLong(len_num))?
+    }
+
+    Ok(len_num)
+}
+
+/// Parse the digits of an integer, which start at `offset`.
+#[inline(always)]
+fn parse_int(num: &[u8], options: &ParseOptions, offset: usize) -> Result<i64, Error> {
+    if options.strict && num.starts_with(b"+") {
+        Err(Error::NonCanonicalInt(offset))?
+    }
+    // Reject leading -0 and leading 0, but not 0 itself.
+    if num.starts_with(&[b'-', b'0']) || (num.starts_with(&[b'0']) && num.len() != 1) {
+        Err(Error::InvalidInt(num.to_vec()))?;
+    }
+
+    parse_raw_int(num)
 }
 
 pub struct Parser<'a> {
     input: &'a [u8],
+    len: usize,
+    options: ParseOptions,
+    depth: usize,
     elements: Vec<Element<&'a [u8]>>,
+    spans: Vec<Range<usize>>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(input: &'a [u8]) -> Self {
+    pub fn new(input: &'a [u8], options: &ParseOptions) -> Self {
         let elements = Vec::with_capacity(10);
-        Self { input, elements }
+        Self {
+            input,
+            len: input.len(),
+            options: *options,
+            depth: 0,
+            elements,
+            spans: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.len - self.input.len()
     }
 
     #[inline(always)]
@@ -41,13 +145,13 @@ impl<'a> Parser<'a> {
 
     #[inline(always)]
     fn take_n(&mut self, n: usize) -> Result<&'a [u8], Error> {
-        let (head, tail) = self.input.split_at(n);
-        if head.len() == n {
-            self.input = tail;
-            Ok(head)
-        } else {
-            Err(Error::UnexpectedEnd(format!("take_n: {}", n)))
+        if n > self.input.len() {
+            Err(Error::UnexpectedEnd(format!("take_n: {}", n)))?
         }
+
+        let (head, tail) = self.input.split_at(n);
+        self.input = tail;
+        Ok(head)
     }
 
     #[inline(always)]
@@ -68,65 +172,108 @@ impl<'a> Parser<'a> {
     }
 
     #[inline(always)]
-    fn parse_raw_int<T: lexical::FromLexical>(input: &[u8]) -> Result<T, Error> {
-        lexical::parse(input).map_err(|_| Error::InvalidInt(input.to_vec()))
+    fn push(&mut self, element: Element<&'a [u8]>, start: usize) -> Result<(), Error> {
+        if self.elements.len() >= self.options.max_elements {
+            Err(Error::TooManyElements)?
+        }
+
+        self.elements.push(element);
+        if self.options.spans {
+            self.spans.push(start..self.offset());
+        }
+
+        Ok(())
+    }
+
+    /// Close a list or dict once its end marker has been consumed.
+    #[inline(always)]
+    fn close(&mut self, header_idx: usize, element: Element<&'a [u8]>) {
+        self.elements[header_idx] = element;
+        if self.options.spans {
+            self.spans[header_idx].end = self.offset();
+        }
+        self.depth -= 1;
+    }
+
+    #[inline(always)]
+    fn enter(&mut self) -> Result<(), Error> {
+        self.depth += 1;
+        if self.depth > self.options.max_depth {
+            Err(Error::TooDeep)?
+        }
+
+        Ok(())
     }
 
     fn parse_string(&mut self) -> Result<(), Error> {
+        let start = self.offset();
         let len = self.take_until(b':')?;
+        let len_num = parse_length(len, &self.options, start)?;
         self.bump_assert();
-        let len_num = Self::parse_raw_int(len)?;
         let str = self.take_n(len_num)?;
 
-        self.elements.push(Element::Bytes(str));
-
-        Ok(())
+        self.push(Element::Bytes(str), start)
     }
 
     fn parse_dict(&mut self) -> Result<(), Error> {
+        let start = self.offset();
         self.bump_assert();
+        self.enter()?;
 
         let header_idx = self.elements.len();
-        self.elements.push(Element::DictBegin(0));
+        self.push(Element::DictBegin(0), start)?;
 
         let mut ct = 0;
+        let mut last_key: Option<&'a [u8]> = None;
 
         while self.peek() != Some(b'e') {
+            let offset = self.offset();
             self.parse_string()?;
+
+            if self.options.strict {
+                let key = match self.elements.last() {
+                    Some(Element::Bytes(key)) => *key,
+                    _ => unreachable!(),
+                };
+                match last_key.map(|last| last.cmp(key)) {
+                    Some(std::cmp::Ordering::Equal) => Err(Error::DuplicateKey(offset))?,
+                    Some(std::cmp::Ordering::Greater) => Err(Error::UnsortedKey(offset))?,
+                    _ => last_key = Some(key),
+                }
+            }
+
             self.parse_value()?;
             ct += 1;
         }
 
         self.bump_assert();
 
-        self.elements[header_idx] = Element::DictBegin(ct);
+        self.close(header_idx, Element::DictBegin(ct));
 
         Ok(())
     }
 
     fn parse_int(&mut self) -> Result<(), Error> {
+        let start = self.offset();
         self.bump_assert();
 
+        let offset = self.offset();
         let num = self.take_until(b'e')?;
-        // Reject leading -0 and leading 0, but not 0 itself.
-        if num.starts_with(&[b'-', b'0']) || (num.starts_with(&[b'0']) && num.len() != 1) {
-            Err(Error::InvalidInt(num.to_vec()))?;
-        }
-        let num = lexical::parse(&num).unwrap();
+        let num = parse_int(num, &self.options, offset)?;
 
         self.bump_assert();
 
-        self.elements.push(Element::Int(num));
-
-        Ok(())
+        self.push(Element::Int(num), start)
     }
 
     fn parse_list(&mut self) -> Result<(), Error> {
+        let start = self.offset();
         self.bump_assert();
+        self.enter()?;
 
         let header_idx = self.elements.len();
 
-        self.elements.push(Element::ListBegin(0));
+        self.push(Element::ListBegin(0), start)?;
 
         let mut ct = 0;
 
@@ -137,7 +284,7 @@ impl<'a> Parser<'a> {
 
         self.bump_assert();
 
-        self.elements[header_idx] = Element::ListBegin(ct);
+        self.close(header_idx, Element::ListBegin(ct));
 
         Ok(())
     }
@@ -152,12 +299,29 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn parse(mut self) -> Result<Elements<&'a [u8]>, Error> {
-        self.parse_value()?;
-        if self.is_done() {
-            Ok(Elements::from_parts(self.elements))
-        } else {
-            Err(Error::Trailing)
+    pub fn parse(self) -> Result<Elements<&'a [u8]>, Error> {
+        self.parse_with_offset().map_err(|(e, _)| e)
+    }
+
+    /// Parse the input, reporting how far the parser got if it fails.
+    pub fn parse_with_offset(mut self) -> Result<Elements<&'a [u8]>, (Error, usize)> {
+        let result = self.parse_value().and_then(|_| match self.is_done() {
+            true => Ok(()),
+            false => Err(Error::Trailing),
+        });
+
+        match result {
+            Ok(()) => {
+                let elements = Elements::from_parts(self.elements);
+                match self.options.spans {
+                    true => Ok(elements.with_spans(self.spans)),
+                    false => Ok(elements),
+                }
+            }
+            Err(e) => {
+                let offset = self.offset();
+                Err((e, offset))
+            }
         }
     }
 }
@@ -165,6 +329,10 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::Value;
+    use include_dir::{include_dir, Dir};
+
+    static TORRENT_SAMPLES_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/benches/samples/");
 
     #[test]
     fn parses_string() {
@@ -210,6 +378,138 @@ mod test {
         )
     }
 
+    #[test]
+    fn rejects_overflows() {
+        assert_eq!(
+            Err(Error::IntOverflow(b"9223372036854775808".to_vec())),
+            parse(b"i9223372036854775808e")
+        );
+        assert_eq!(Err(Error::InvalidInt(b"-".to_vec())), parse(b"i-e"));
+        assert_eq!(
+            Err(Error::LengthOverflow(b"99999999999999999999".to_vec())),
+            parse(b"99999999999999999999:spam")
+        );
+        assert!(matches!(parse(b"5:spam"), Err(Error::UnexpectedEnd(_))));
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let options = ParseOptions {
+            max_depth: 2,
+            max_elements: 4,
+            max_string_len: 4,
+            ..Default::default()
+        };
+
+        assert!(parse_with_options(b"lli1eee", &options).is_ok());
+        assert_eq!(
+            Err(Error::TooDeep),
+            parse_with_options(b"llli1eeee", &options)
+        );
+        assert_eq!(
+            Err(Error::TooManyElements),
+            parse_with_options(b"li1ei2ei3ei4ee", &options)
+        );
+        assert_eq!(
+            Err(Error::StringTooLong(5)),
+            parse_with_options(b"5:spams", &options)
+        );
+        assert_eq!(Err(Error::TooDeep), parse(&[b'l'; 100_000]));
+    }
+
+    #[test]
+    fn rejects_non_canonical_input_when_strict() {
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        for input in [
+            &b"d1:bi1e1:ai2ee"[..],
+            b"d1:ai1e1:ai2ee",
+            b"04:spam",
+            b"i+1e",
+        ] {
+            assert!(parse(input).is_ok());
+        }
+
+        assert_eq!(
+            Err(Error::UnsortedKey(7)),
+            parse_with_options(b"d1:bi1e1:ai2ee", &strict)
+        );
+        assert_eq!(
+            Err(Error::DuplicateKey(13)),
+            parse_with_options(b"d1:ai1e1:bi2e1:bi3ee", &strict)
+        );
+        assert_eq!(
+            Err(Error::NonCanonicalLength(4)),
+            parse_with_options(b"d1:a04:spame", &strict)
+        );
+        assert_eq!(
+            Err(Error::NonCanonicalInt(1)),
+            parse_with_options(b"i+1e", &strict)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength(b"+1".to_vec())),
+            parse_with_options(b"d+1:ai1ee", &strict)
+        );
+        assert!(parse_with_options(b"+1:a", &strict).is_err());
+        assert!(parse_with_options(b"d1:a0:1:bd1:xi1e1:yi2eee", &strict).is_ok());
+    }
+
+    #[test]
+    fn records_spans() {
+        let options = ParseOptions {
+            spans: true,
+            ..Default::default()
+        };
+        let input = b"d3:cowli1e2:abe4:spamd1:xi-2eee";
+        let elements = parse_with_options(input, &options).unwrap();
+
+        assert_eq!(
+            Some(
+                &[
+                    0..31,
+                    1..6,
+                    6..15,
+                    7..10,
+                    10..14,
+                    15..21,
+                    21..30,
+                    22..25,
+                    25..29
+                ][..]
+            ),
+            elements.spans()
+        );
+        assert_eq!(Some(6..15), elements.span(&[b"cow"]));
+        assert_eq!(Some(&b"i-2e"[..]), elements.raw(input, &[b"spam", b"x"]));
+        assert_eq!(None, elements.span(&[b"cow", b"x"]));
+        assert_eq!(None, elements.span(&[b"eggs"]));
+        assert_eq!(None, parse(input).unwrap().spans());
+    }
+
+    #[test]
+    fn raw_info_matches_encoded_info() {
+        let options = ParseOptions {
+            strict: true,
+            spans: true,
+            ..Default::default()
+        };
+
+        for sample in TORRENT_SAMPLES_DIR.files() {
+            let parsed = parse_with_options(sample.contents(), &options).unwrap();
+            let raw = parsed.raw(sample.contents(), &[b"info"]).unwrap();
+
+            let info = match parsed.into_value() {
+                Value::Dict(d) => d.into_iter().find(|(k, _)| *k == b"info").unwrap().1,
+                _ => panic!("torrent is not a dict"),
+            };
+
+            assert_eq!(raw, crate::encode(&info));
+        }
+    }
+
     #[test]
     fn parses_dicts() {
         assert_eq!(
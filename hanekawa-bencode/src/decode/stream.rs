@@ -0,0 +1,660 @@
+//! An incremental decoder for input that arrives in chunks or is too large
+//! to hold in memory at once.
+//!
+//! Instead of building elements, the decoder yields [`Event`]s as soon as
+//! enough input has arrived for them. Byte strings are yielded in chunks as
+//! they arrive, so a multi-gigabyte string never has to be buffered whole.
+//! Dict keys are buffered whole, since they are needed to check ordering in
+//! strict mode, so they may be at most [`MAX_KEY_LEN`] bytes long.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::{parse_int, parse_length, ParseOptions};
+use crate::Error;
+
+/// Integers and string lengths longer than this are rejected, so the
+/// decoder never buffers unboundedly while waiting for their end.
+const MAX_HEADER_LEN: usize = 64;
+
+/// Dict keys longer than this are rejected, however long
+/// [`ParseOptions::max_string_len`] allows strings to be.
+pub const MAX_KEY_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    DictBegin,
+    ListBegin,
+    /// A dict key. The next events are its value.
+    Key(Bytes),
+    Int(i64),
+    /// The start of a byte string of this length. It is followed by
+    /// [`Event::BytesChunk`]s adding up to exactly this length, if any.
+    BytesBegin(usize),
+    BytesChunk(Bytes),
+    /// The end of the innermost open list or dict.
+    End,
+}
+
+#[derive(Debug)]
+enum Frame {
+    List,
+    Dict {
+        expect_key: bool,
+        last_key: Option<Bytes>,
+    },
+}
+
+/// An incremental pull decoder for a single bencoded value.
+///
+/// Feed it input with [`Decoder::feed`] and pull events with
+/// [`Decoder::next_event`] until it returns `None`, which means it needs
+/// more input. Once all input is fed, [`Decoder::finish`] checks that a
+/// complete value was decoded. The same [`ParseOptions`] apply as for
+/// [`parse_with_options`](super::parse_with_options), except `spans`.
+#[derive(Debug)]
+pub struct Decoder {
+    options: ParseOptions,
+    buf: BytesMut,
+    /// How many bytes have been consumed from the input.
+    offset: usize,
+    stack: Vec<Frame>,
+    elements: usize,
+    /// How much is left of the byte string being yielded in chunks.
+    remaining: Option<usize>,
+    done: bool,
+}
+
+impl Decoder {
+    pub fn new(options: &ParseOptions) -> Self {
+        Self {
+            options: *options,
+            buf: BytesMut::new(),
+            offset: 0,
+            stack: Vec::new(),
+            elements: 0,
+            remaining: None,
+            done: false,
+        }
+    }
+
+    /// Append a chunk of input.
+    pub fn feed(&mut self, chunk: impl Buf) {
+        self.buf.put(chunk);
+    }
+
+    /// How many bytes of input have been consumed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Whether a complete value has been decoded.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Check that the input fed so far was exactly one complete value.
+    pub fn finish(&self) -> Result<(), Error> {
+        match (self.done, self.buf.is_empty()) {
+            (true, true) => Ok(()),
+            (true, false) => Err(Error::Trailing),
+            (false, _) => Err(Error::UnexpectedEnd("finish".to_string())),
+        }
+    }
+
+    /// The next event, or `None` if more input is needed first or the value
+    /// is complete.
+    pub fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        if let Some(remaining) = self.remaining {
+            if self.buf.is_empty() {
+                return Ok(None);
+            }
+
+            let n = remaining.min(self.buf.len());
+            let chunk = self.consume(n);
+            if remaining == n {
+                self.remaining = None;
+                self.value_done();
+            } else {
+                self.remaining = Some(remaining - n);
+            }
+
+            return Ok(Some(Event::BytesChunk(chunk)));
+        }
+
+        if self.done {
+            return match self.buf.is_empty() {
+                true => Ok(None),
+                false => Err(Error::Trailing),
+            };
+        }
+
+        let b = match self.buf.first() {
+            Some(b) => *b,
+            None => return Ok(None),
+        };
+
+        match self.stack.last() {
+            Some(Frame::Dict {
+                expect_key: true, ..
+            }) => match b {
+                b'e' => Ok(Some(self.end())),
+                _ => self.key(),
+            },
+            Some(Frame::List) if b == b'e' => Ok(Some(self.end())),
+            _ => self.value(b),
+        }
+    }
+
+    fn consume(&mut self, n: usize) -> Bytes {
+        self.offset += n;
+        self.buf.split_to(n).freeze()
+    }
+
+    fn push(&mut self) -> Result<(), Error> {
+        if self.elements >= self.options.max_elements {
+            Err(Error::TooManyElements)?
+        }
+        self.elements += 1;
+
+        Ok(())
+    }
+
+    fn enter(&mut self, frame: Frame) -> Result<(), Error> {
+        if self.stack.len() >= self.options.max_depth {
+            Err(Error::TooDeep)?
+        }
+        self.push()?;
+        self.stack.push(frame);
+        self.consume(1);
+
+        Ok(())
+    }
+
+    fn end(&mut self) -> Event {
+        self.consume(1);
+        self.stack.pop();
+        self.value_done();
+        Event::End
+    }
+
+    /// Expect a key next if the completed value was in a dict.
+    fn value_done(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Dict { expect_key, .. }) => *expect_key = true,
+            Some(Frame::List) => {}
+            None => self.done = true,
+        }
+    }
+
+    /// Find the end of an integer or string length, if it has arrived.
+    fn header(&self, terminator: u8, start: usize) -> Result<Option<usize>, Error> {
+        let window = &self.buf[..self.buf.len().min(start + MAX_HEADER_LEN + 1)];
+        match memchr::memchr(terminator, &window[start..]) {
+            Some(i) => Ok(Some(start + i)),
+            None if window.len() > start + MAX_HEADER_LEN => match terminator {
+                b':' => Err(Error::InvalidLength(window[start..].to_vec())),
+                _ => Err(Error::InvalidInt(window[start..].to_vec())),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn key(&mut self) -> Result<Option<Event>, Error> {
+        let colon = match self.header(b':', 0)? {
+            Some(colon) => colon,
+            None => return Ok(None),
+        };
+        let len = parse_length(&self.buf[..colon], &self.options, self.offset)?;
+        if len > MAX_KEY_LEN {
+            Err(Error::StringTooLong(len))?
+        }
+        if len > self.buf.len() - colon - 1 {
+            return Ok(None);
+        }
+
+        let offset = self.offset;
+        self.push()?;
+        self.consume(colon + 1);
+        let key = self.consume(len);
+
+        if let Some(Frame::Dict {
+            expect_key,
+            last_key,
+        }) = self.stack.last_mut()
+        {
+            if self.options.strict {
+                match last_key.as_ref().map(|last| last.cmp(&key)) {
+                    Some(std::cmp::Ordering::Equal) => Err(Error::DuplicateKey(offset))?,
+                    Some(std::cmp::Ordering::Greater) => Err(Error::UnsortedKey(offset))?,
+                    _ => *last_key = Some(key.clone()),
+                }
+            }
+            *expect_key = false;
+        }
+
+        Ok(Some(Event::Key(key)))
+    }
+
+    fn value(&mut self, b: u8) -> Result<Option<Event>, Error> {
+        match b {
+            b'd' => {
+                self.enter(Frame::Dict {
+                    expect_key: true,
+                    last_key: None,
+                })?;
+                Ok(Some(Event::DictBegin))
+            }
+            b'l' => {
+                self.enter(Frame::List)?;
+                Ok(Some(Event::ListBegin))
+            }
+            b'i' => {
+                let end = match self.header(b'e', 1)? {
+                    Some(end) => end,
+                    None => return Ok(None),
+                };
+                let num = parse_int(&self.buf[1..end], &self.options, self.offset + 1)?;
+
+                self.push()?;
+                self.consume(end + 1);
+                self.value_done();
+                Ok(Some(Event::Int(num)))
+            }
+            b'0'..=b'9' => {
+                let colon = match self.header(b':', 0)? {
+                    Some(colon) => colon,
+                    None => return Ok(None),
+                };
+                let len = parse_length(&self.buf[..colon], &self.options, self.offset)?;
+
+                self.push()?;
+                self.consume(colon + 1);
+                match len {
+                    0 => self.value_done(),
+                    len => self.remaining = Some(len),
+                }
+                Ok(Some(Event::BytesBegin(len)))
+            }
+            _ => Err(Error::UnexpectedEnd("parse_value".to_string())),
+        }
+    }
+}
+
+/// How many bytes [`AsyncDecoder`] asks its reader for at a time.
+#[cfg(feature = "tokio")]
+const READ_SIZE: usize = 64 * 1024;
+
+/// An error from [`AsyncDecoder`].
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub enum ReadError {
+    Io(std::io::Error),
+    Decode(Error),
+}
+
+#[cfg(feature = "tokio")]
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => f.write_fmt(format_args!("failed to read input: {}", e)),
+            Self::Decode(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl std::error::Error for ReadError {}
+
+#[cfg(feature = "tokio")]
+impl From<std::io::Error> for ReadError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<Error> for ReadError {
+    fn from(value: Error) -> Self {
+        Self::Decode(value)
+    }
+}
+
+/// A [`Decoder`] that pulls its input from an [`AsyncRead`](tokio::io::AsyncRead).
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncDecoder<R> {
+    reader: R,
+    decoder: Decoder,
+    eof: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + Unpin> AsyncDecoder<R> {
+    pub fn new(reader: R, options: &ParseOptions) -> Self {
+        Self {
+            reader,
+            decoder: Decoder::new(options),
+            eof: false,
+        }
+    }
+
+    /// The next event, reading more input as needed, or `None` once the
+    /// reader is exhausted and the value is complete. Input after the value
+    /// is rejected.
+    pub async fn next_event(&mut self) -> Result<Option<Event>, ReadError> {
+        use tokio::io::AsyncReadExt;
+
+        loop {
+            if let Some(event) = self.decoder.next_event()? {
+                return Ok(Some(event));
+            }
+
+            if self.eof {
+                self.decoder.finish()?;
+                return Ok(None);
+            }
+
+            self.decoder.buf.reserve(READ_SIZE);
+            if self.reader.read_buf(&mut self.decoder.buf).await? == 0 {
+                self.eof = true;
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parse_with_options, Element};
+    use include_dir::{include_dir, Dir};
+
+    static TORRENT_SAMPLES_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/benches/samples/");
+
+    /// An event with byte strings reassembled from their chunks.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Flat {
+        DictBegin,
+        ListBegin,
+        Key(Vec<u8>),
+        Int(i64),
+        Bytes(Vec<u8>),
+        End,
+    }
+
+    /// Decode `input` fed in chunks of `chunk_size`, pulling events after
+    /// each chunk.
+    fn decode(input: &[u8], chunk_size: usize, options: &ParseOptions) -> Result<Vec<Flat>, Error> {
+        let mut decoder = Decoder::new(options);
+        let mut events = vec![];
+        let mut bytes: Option<(usize, Vec<u8>)> = None;
+
+        for chunk in input.chunks(chunk_size) {
+            decoder.feed(chunk);
+            while let Some(event) = decoder.next_event()? {
+                match event {
+                    Event::DictBegin => events.push(Flat::DictBegin),
+                    Event::ListBegin => events.push(Flat::ListBegin),
+                    Event::Key(key) => events.push(Flat::Key(key.to_vec())),
+                    Event::Int(i) => events.push(Flat::Int(i)),
+                    Event::BytesBegin(0) => events.push(Flat::Bytes(vec![])),
+                    Event::BytesBegin(len) => bytes = Some((len, vec![])),
+                    Event::BytesChunk(chunk) => {
+                        let (len, mut buf) = bytes.take().unwrap();
+                        buf.extend_from_slice(&chunk);
+                        match buf.len() == len {
+                            true => events.push(Flat::Bytes(buf)),
+                            false => bytes = Some((len, buf)),
+                        }
+                    }
+                    Event::End => events.push(Flat::End),
+                }
+            }
+        }
+        decoder.finish()?;
+        assert_eq!(input.len(), decoder.offset());
+
+        Ok(events)
+    }
+
+    /// The events the decoder should yield for what the parser accepts.
+    fn expected(input: &[u8], options: &ParseOptions) -> Result<Vec<Flat>, Error> {
+        fn walk(elements: &[Element<&[u8]>], idx: &mut usize, events: &mut Vec<Flat>) {
+            let element = &elements[*idx];
+            *idx += 1;
+            match element {
+                Element::Int(i) => events.push(Flat::Int(*i)),
+                Element::Bytes(b) => events.push(Flat::Bytes(b.to_vec())),
+                Element::ListBegin(ct) => {
+                    events.push(Flat::ListBegin);
+                    for _ in 0..*ct {
+                        walk(elements, idx, events);
+                    }
+                    events.push(Flat::End);
+                }
+                Element::DictBegin(ct) => {
+                    events.push(Flat::DictBegin);
+                    for _ in 0..*ct {
+                        if let Element::Bytes(key) = &elements[*idx] {
+                            events.push(Flat::Key(key.to_vec()));
+                        }
+                        *idx += 1;
+                        walk(elements, idx, events);
+                    }
+                    events.push(Flat::End);
+                }
+            }
+        }
+
+        let elements = parse_with_options(input, options)?;
+        let mut events = vec![];
+        walk(elements.as_slice(), &mut 0, &mut events);
+
+        Ok(events)
+    }
+
+    #[test]
+    fn decodes_in_chunks() {
+        let options = ParseOptions::default();
+        let input = b"d3:cowli1e2:abe4:spamd1:xi-2e1:y0:ee";
+
+        assert_eq!(
+            vec![
+                Flat::DictBegin,
+                Flat::Key(b"cow".to_vec()),
+                Flat::ListBegin,
+                Flat::Int(1),
+                Flat::Bytes(b"ab".to_vec()),
+                Flat::End,
+                Flat::Key(b"spam".to_vec()),
+                Flat::DictBegin,
+                Flat::Key(b"x".to_vec()),
+                Flat::Int(-2),
+                Flat::Key(b"y".to_vec()),
+                Flat::Bytes(vec![]),
+                Flat::End,
+                Flat::End,
+            ],
+            decode(input, input.len(), &options).unwrap()
+        );
+
+        for chunk_size in 1..input.len() {
+            assert_eq!(
+                decode(input, input.len(), &options),
+                decode(input, chunk_size, &options)
+            );
+        }
+    }
+
+    #[test]
+    fn yields_byte_strings_in_chunks() {
+        let mut decoder = Decoder::new(&ParseOptions::default());
+        decoder.feed(&b"10:0123"[..]);
+
+        assert_eq!(Some(Event::BytesBegin(10)), decoder.next_event().unwrap());
+        assert_eq!(
+            Some(Event::BytesChunk(Bytes::from_static(b"0123"))),
+            decoder.next_event().unwrap()
+        );
+        assert_eq!(None, decoder.next_event().unwrap());
+        assert!(decoder.finish().is_err());
+
+        decoder.feed(&b"456789"[..]);
+        assert_eq!(
+            Some(Event::BytesChunk(Bytes::from_static(b"456789"))),
+            decoder.next_event().unwrap()
+        );
+        assert_eq!(None, decoder.next_event().unwrap());
+        assert!(decoder.is_done());
+        assert_eq!(Ok(()), decoder.finish());
+
+        decoder.feed(&b"i1e"[..]);
+        assert_eq!(Err(Error::Trailing), decoder.next_event());
+    }
+
+    #[test]
+    fn matches_parser_on_samples() {
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        for file in TORRENT_SAMPLES_DIR.files() {
+            let input = file.contents();
+            let expected = expected(input, &options).unwrap();
+            for chunk_size in [1, 7, 4096, input.len()] {
+                assert_eq!(
+                    expected,
+                    decode(input, chunk_size, &options).unwrap(),
+                    "{:?} in chunks of {}",
+                    file.path(),
+                    chunk_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_parser_on_invalid_input() {
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let limited = ParseOptions {
+            max_depth: 2,
+            max_elements: 4,
+            max_string_len: 4,
+            ..Default::default()
+        };
+
+        for (input, options) in [
+            (&b"i03e"[..], ParseOptions::default()),
+            (b"i-0e", ParseOptions::default()),
+            (b"i9223372036854775808e", ParseOptions::default()),
+            (b"5:spam", ParseOptions::default()),
+            (b"li1e", ParseOptions::default()),
+            (b"d1:ae", ParseOptions::default()),
+            (b"i1ei2e", ParseOptions::default()),
+            (b"x", ParseOptions::default()),
+            (b"d1:bi1e1:ai2ee", strict),
+            (b"d1:ai1e1:bi2e1:bi3ee", strict),
+            (b"d1:a04:spame", strict),
+            (b"i+1e", strict),
+            (b"llli1eeee", limited),
+            (b"li1ei2ei3ei4ee", limited),
+            (b"5:spams", limited),
+        ] {
+            let expected = expected(input, &options);
+            assert!(expected.is_err(), "{:?}", input);
+            for chunk_size in 1..=input.len() {
+                let result = decode(input, chunk_size, &options);
+                assert!(result.is_err(), "{:?} in chunks of {}", input, chunk_size);
+                // Errors found before the end of input carry the same
+                // offsets as the parser's.
+                if let Err(Error::UnsortedKey(_) | Error::DuplicateKey(_)) = expected {
+                    assert_eq!(expected, result);
+                }
+            }
+        }
+
+        for (input, options) in [
+            (&b"d1:bi1e1:ai2ee"[..], ParseOptions::default()),
+            (b"04:spam", ParseOptions::default()),
+            (b"lli1eee", limited),
+        ] {
+            assert_eq!(
+                expected(input, &options).unwrap(),
+                decode(input, 1, &options).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_signed_lengths_like_parser() {
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        for options in [ParseOptions::default(), strict] {
+            for input in [&b"d+1:ai1ee"[..], b"d1:a+1:be", b"+1:a"] {
+                let expected = expected(input, &options);
+                assert!(expected.is_err(), "{:?}", input);
+                for chunk_size in 1..=input.len() {
+                    assert_eq!(expected, decode(input, chunk_size, &options));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounds_buffered_headers() {
+        let mut decoder = Decoder::new(&ParseOptions::default());
+        decoder.feed(&[b'1'; MAX_HEADER_LEN][..]);
+        assert_eq!(None, decoder.next_event().unwrap());
+
+        decoder.feed(&b"1"[..]);
+        assert!(matches!(decoder.next_event(), Err(Error::InvalidLength(_))));
+    }
+
+    #[test]
+    fn bounds_buffered_keys() {
+        let mut decoder = Decoder::new(&ParseOptions::default());
+        decoder.feed(&b"d18446744073709551615:a"[..]);
+        assert_eq!(Some(Event::DictBegin), decoder.next_event().unwrap());
+        assert_eq!(Err(Error::StringTooLong(usize::MAX)), decoder.next_event());
+
+        let mut decoder = Decoder::new(&ParseOptions::default());
+        decoder.feed(&format!("d{}:a", MAX_KEY_LEN + 1).into_bytes()[..]);
+        assert_eq!(Some(Event::DictBegin), decoder.next_event().unwrap());
+        assert_eq!(
+            Err(Error::StringTooLong(MAX_KEY_LEN + 1)),
+            decoder.next_event()
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn decodes_from_async_reader() {
+        let input = TORRENT_SAMPLES_DIR.files().next().unwrap().contents();
+        let options = ParseOptions::default();
+
+        let mut decoder = AsyncDecoder::new(input, &options);
+        let mut events = 0;
+        while decoder.next_event().await.unwrap().is_some() {
+            events += 1;
+        }
+        assert!(events > 0);
+
+        let mut decoder = AsyncDecoder::new(&b"li1e"[..], &options);
+        assert_eq!(Some(Event::ListBegin), decoder.next_event().await.unwrap());
+        assert_eq!(Some(Event::Int(1)), decoder.next_event().await.unwrap());
+        assert!(matches!(
+            decoder.next_event().await,
+            Err(ReadError::Decode(Error::UnexpectedEnd(_)))
+        ));
+    }
+}
@@ -1,9 +1,47 @@
 pub mod ser;
 
 use super::{Map, Value};
-use bytes::{BufMut, BytesMut};
+use bytes::BufMut;
 
-fn encode_string<B: AsRef<[u8]> + Ord>(bytes: B, buf: &mut BytesMut) {
+/// Where encoded bytes go.
+trait Sink {
+    fn put_slice(&mut self, bytes: &[u8]);
+
+    fn put_u8(&mut self, b: u8);
+}
+
+impl<T: BufMut> Sink for T {
+    #[inline(always)]
+    fn put_slice(&mut self, bytes: &[u8]) {
+        BufMut::put_slice(self, bytes)
+    }
+
+    #[inline(always)]
+    fn put_u8(&mut self, b: u8) {
+        BufMut::put_u8(self, b)
+    }
+}
+
+/// Adapts a writer to a sink, keeping the first error and dropping
+/// everything after it.
+struct WriteSink<W> {
+    writer: W,
+    error: Option<std::io::Error>,
+}
+
+impl<W: std::io::Write> Sink for WriteSink<W> {
+    fn put_slice(&mut self, bytes: &[u8]) {
+        if self.error.is_none() {
+            self.error = self.writer.write_all(bytes).err();
+        }
+    }
+
+    fn put_u8(&mut self, b: u8) {
+        self.put_slice(&[b])
+    }
+}
+
+fn encode_string<B: AsRef<[u8]>>(bytes: B, buf: &mut impl Sink) {
     use lexical::{FormattedSize, ToLexical};
     let mut digits = [0; usize::FORMATTED_SIZE_DECIMAL];
 
@@ -11,12 +49,12 @@ fn encode_string<B: AsRef<[u8]> + Ord>(bytes: B, buf: &mut BytesMut) {
 
     buf.put_slice(bytes.len().to_lexical(&mut digits));
     buf.put_u8(b':');
-    buf.put_slice(&bytes);
+    buf.put_slice(bytes);
 }
 
-fn encode_integer(i: i64, buf: &mut BytesMut) {
+fn encode_integer(i: i64, buf: &mut impl Sink) {
     use lexical::{FormattedSize, ToLexical};
-    let mut digits = [0; usize::FORMATTED_SIZE_DECIMAL];
+    let mut digits = [0; i64::FORMATTED_SIZE_DECIMAL];
 
     buf.put_u8(b'i');
     buf.put_slice(i.to_lexical(&mut digits));
@@ -24,16 +62,16 @@ fn encode_integer(i: i64, buf: &mut BytesMut) {
 }
 
 #[inline(always)]
-fn encode_list_begin(buf: &mut BytesMut) {
+fn encode_list_begin(buf: &mut impl Sink) {
     buf.put_u8(b'l');
 }
 
 #[inline(always)]
-fn encode_list_end(buf: &mut BytesMut) {
+fn encode_list_end(buf: &mut impl Sink) {
     buf.put_u8(b'e')
 }
 
-fn encode_list<B: AsRef<[u8]> + Ord>(vs: &Vec<Value<B>>, buf: &mut BytesMut) {
+fn encode_list<B: AsRef<[u8]> + Ord>(vs: &Vec<Value<B>>, buf: &mut impl Sink) {
     encode_list_begin(buf);
     for v in vs {
         encode_value(v, buf);
@@ -42,16 +80,16 @@ fn encode_list<B: AsRef<[u8]> + Ord>(vs: &Vec<Value<B>>, buf: &mut BytesMut) {
 }
 
 #[inline(always)]
-fn encode_dict_begin(buf: &mut BytesMut) {
+fn encode_dict_begin(buf: &mut impl Sink) {
     buf.put_u8(b'd');
 }
 
 #[inline(always)]
-fn encode_dict_end(buf: &mut BytesMut) {
+fn encode_dict_end(buf: &mut impl Sink) {
     buf.put_u8(b'e');
 }
 
-fn encode_dict<B: AsRef<[u8]> + Ord>(vs: &Map<B, Value<B>>, buf: &mut BytesMut) {
+fn encode_dict<B: AsRef<[u8]> + Ord>(vs: &Map<B, Value<B>>, buf: &mut impl Sink) {
     encode_dict_begin(buf);
     for (k, v) in vs {
         encode_string(k, buf);
@@ -60,18 +98,116 @@ fn encode_dict<B: AsRef<[u8]> + Ord>(vs: &Map<B, Value<B>>, buf: &mut BytesMut)
     encode_dict_end(buf);
 }
 
-fn encode_value<B: AsRef<[u8]> + Ord>(value: &Value<B>, buf: &mut BytesMut) {
+fn encode_value<B: AsRef<[u8]> + Ord>(value: &Value<B>, buf: &mut impl Sink) {
     match value {
-        Value::Bytes(b) => encode_string(&b, buf),
+        Value::Bytes(b) => encode_string(b, buf),
         Value::Int(i) => encode_integer(*i, buf),
         Value::List(vs) => encode_list(vs, buf),
         Value::Dict(vs) => encode_dict(vs, buf),
     }
 }
 
+/// The number of decimal digits in `n`.
+fn digits(n: u64) -> usize {
+    n.checked_ilog10().unwrap_or(0) as usize + 1
+}
+
+fn string_len(len: usize) -> usize {
+    digits(len as u64) + 1 + len
+}
+
+/// How many bytes `value` encodes to, so a buffer of the right size can be
+/// allocated up front.
+pub fn encoded_len<B: AsRef<[u8]> + Ord>(value: &Value<B>) -> usize {
+    match value {
+        Value::Bytes(b) => string_len(b.as_ref().len()),
+        Value::Int(i) => 2 + (*i < 0) as usize + digits(i.unsigned_abs()),
+        Value::List(vs) => 2 + vs.iter().map(encoded_len).sum::<usize>(),
+        Value::Dict(vs) => {
+            2 + vs
+                .into_iter()
+                .map(|(k, v)| string_len(k.as_ref().len()) + encoded_len(v))
+                .sum::<usize>()
+        }
+    }
+}
+
 pub fn encode<B: AsRef<[u8]> + Ord>(value: &Value<B>) -> Vec<u8> {
-    let mut buf = BytesMut::new();
+    let mut buf = Vec::with_capacity(encoded_len(value));
     encode_value(value, &mut buf);
 
-    buf.to_vec()
+    buf
+}
+
+/// Append the encoding of `value` to `buf`.
+///
+/// # Panics
+///
+/// Like [`BufMut::put_slice`], if `buf` has no room left and cannot grow.
+pub fn encode_into<B: AsRef<[u8]> + Ord>(value: &Value<B>, buf: &mut impl BufMut) {
+    encode_value(value, buf);
+}
+
+/// Write the encoding of `value` to `writer`. Encoding makes many small
+/// writes, so unbuffered writers such as files and sockets should be wrapped
+/// in a [`BufWriter`](std::io::BufWriter).
+pub fn encode_to_writer<B: AsRef<[u8]> + Ord>(
+    value: &Value<B>,
+    writer: impl std::io::Write,
+) -> std::io::Result<()> {
+    let mut sink = WriteSink {
+        writer,
+        error: None,
+    };
+    encode_value(value, &mut sink);
+
+    match sink.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn computes_encoded_len() {
+        for input in [
+            &b"i0e"[..],
+            b"i-1e",
+            b"i9223372036854775807e",
+            b"i-9223372036854775808e",
+            b"0:",
+            b"10:0123456789",
+            b"le",
+            b"de",
+            b"d3:cowli1e2:abe4:spamd1:xi-20eee",
+        ] {
+            let value = crate::parse(input).unwrap().into_value();
+            assert_eq!(input.len(), encoded_len(&value), "{:?}", input);
+            assert_eq!(input, encode(&value));
+        }
+    }
+
+    #[test]
+    fn encodes_into_buffers_and_writers() {
+        let input = b"d3:cowli1e2:abe4:spamd1:xi-20eee";
+        let value = crate::parse(input).unwrap().into_value();
+
+        let mut buf = bytes::BytesMut::from(&b"prefix"[..]);
+        encode_into(&value, &mut buf);
+        assert_eq!(&b"prefix"[..], &buf[..6]);
+        assert_eq!(&input[..], &buf[6..]);
+
+        let mut written = vec![];
+        encode_to_writer(&value, &mut written).unwrap();
+        assert_eq!(&input[..], written);
+
+        let mut short = [0; 8];
+        assert_eq!(
+            std::io::ErrorKind::WriteZero,
+            encode_to_writer(&value, &mut short[..]).unwrap_err().kind()
+        );
+    }
 }
@@ -180,9 +180,9 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        unsupported_element(Some("unit variant"))
+        self.serialize_str(variant)
     }
 
     fn serialize_newtype_struct<T: ?Sized>(
@@ -200,13 +200,20 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: serde::Serialize,
     {
-        value.serialize(self)
+        self.reject_if_writing_map_key()?;
+
+        encode_dict_begin(&mut self.buf);
+        encode_string(variant.as_bytes(), &mut self.buf);
+        value.serialize(&mut *self)?;
+        encode_dict_end(&mut self.buf);
+
+        Ok(())
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
@@ -319,9 +326,17 @@ impl<'a> ser::SerializeStruct for StructSerializer<'a> {
     {
         use serde::Serialize;
 
+        let start = self.serializer.buf.len();
         key.serialize(&mut *self.serializer)?;
+
+        let value_start = self.serializer.buf.len();
         value.serialize(&mut *self.serializer)?;
 
+        // Values such as None encode to nothing, so leave out their keys.
+        if self.serializer.buf.len() == value_start {
+            self.serializer.buf.truncate(start);
+        }
+
         Ok(())
     }
 
@@ -334,12 +349,17 @@ impl<'a> ser::SerializeStruct for StructSerializer<'a> {
 
 struct MapSerializer<'a> {
     serializer: &'a mut Serializer,
+    key_start: usize,
 }
 
 impl<'a> MapSerializer<'a> {
     fn new(serializer: &'a mut Serializer) -> Self {
         encode_dict_begin(&mut serializer.buf);
-        Self { serializer }
+        let key_start = serializer.buf.len();
+        Self {
+            serializer,
+            key_start,
+        }
     }
 }
 
@@ -354,6 +374,7 @@ impl<'a> ser::SerializeMap for MapSerializer<'a> {
     {
         let current = self.serializer.writing_map_key;
         self.serializer.writing_map_key = true;
+        self.key_start = self.serializer.buf.len();
 
         key.serialize(&mut *self.serializer)?;
 
@@ -366,8 +387,14 @@ impl<'a> ser::SerializeMap for MapSerializer<'a> {
     where
         T: serde::Serialize,
     {
+        let value_start = self.serializer.buf.len();
         value.serialize(&mut *self.serializer)?;
 
+        // As with struct fields, leave out keys whose values encode to nothing.
+        if self.serializer.buf.len() == value_start {
+            self.serializer.buf.truncate(self.key_start);
+        }
+
         Ok(())
     }
 
@@ -465,6 +492,20 @@ impl<B: AsRef<[u8]> + Ord> serde::Serialize for Elements<B> {
     }
 }
 
+/// Encode `value` as bencode.
+///
+/// Bencode has no null, so struct fields and map entries whose value is
+/// `None` are left out. Enums are externally tagged, as [`from_bytes`]
+/// expects: a unit variant encodes as its name, and a newtype variant as a
+/// dict from its name to its value. Tuple and struct variants are not
+/// supported.
+///
+/// Compatibility: older versions rejected unit variants, encoded newtype
+/// variants as their contents alone, and wrote keys with no value for
+/// `None`. Data written by them with newtype variants must be read as the
+/// inner type, not the enum.
+///
+/// [`from_bytes`]: crate::from_bytes
 pub fn to_bytes<T: serde::Serialize>(value: &T) -> Result<Bytes, Error> {
     let mut serializer = Serializer::new();
     value.serialize(&mut serializer)?;
@@ -472,6 +513,24 @@ pub fn to_bytes<T: serde::Serialize>(value: &T) -> Result<Bytes, Error> {
     Ok(serializer.buf.into())
 }
 
+/// Append the encoding of `value` to `buf`, reusing its capacity. On error,
+/// `buf` is left as it was.
+pub fn to_buf<T: serde::Serialize>(value: &T, buf: &mut BytesMut) -> Result<(), Error> {
+    let start = buf.len();
+    let mut serializer = Serializer {
+        buf: std::mem::take(buf),
+        writing_map_key: false,
+    };
+    let result = value.serialize(&mut serializer);
+
+    *buf = serializer.buf;
+    if result.is_err() {
+        buf.truncate(start);
+    }
+
+    result
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -492,4 +551,59 @@ mod test {
             assert_eq!(sample.contents(), encoded.unwrap());
         }
     }
+
+    #[test]
+    fn serializes_into_reused_buffer() {
+        let mut buf = BytesMut::with_capacity(64);
+
+        for sample in TORRENT_SAMPLES_DIR.files() {
+            let parsed = crate::parse(sample.contents()).unwrap();
+
+            buf.clear();
+            to_buf(&parsed, &mut buf).unwrap();
+            assert_eq!(sample.contents(), &buf[..]);
+        }
+
+        buf.clear();
+        to_buf(&"spam", &mut buf).unwrap();
+        assert!(to_buf(&vec![1.5], &mut buf).is_err());
+        assert_eq!(&b"4:spam"[..], &buf[..]);
+    }
+
+    #[test]
+    fn serializes_unit_and_newtype_variants_externally_tagged() {
+        #[derive(serde::Serialize)]
+        enum Kind {
+            Unit,
+            Newtype(i64),
+            Tuple(i64, i64),
+            Struct { x: i64 },
+        }
+
+        assert_eq!(&b"4:Unit"[..], to_bytes(&Kind::Unit).unwrap());
+        assert_eq!(&b"d7:Newtypei1ee"[..], to_bytes(&Kind::Newtype(1)).unwrap());
+        assert!(to_bytes(&Kind::Tuple(1, 2)).is_err());
+        assert!(to_bytes(&Kind::Struct { x: 1 }).is_err());
+    }
+
+    #[test]
+    fn leaves_out_none_entries() {
+        #[derive(serde::Serialize)]
+        struct Optional {
+            a: Option<i64>,
+            b: Option<i64>,
+        }
+
+        assert_eq!(
+            &b"d1:bi2ee"[..],
+            to_bytes(&Optional {
+                a: None,
+                b: Some(2)
+            })
+            .unwrap()
+        );
+
+        let map = std::collections::BTreeMap::from([("a", None), ("b", Some(2))]);
+        assert_eq!(&b"d1:bi2ee"[..], to_bytes(&map).unwrap());
+    }
 }
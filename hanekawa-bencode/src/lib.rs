@@ -1,11 +1,14 @@
 mod decode;
 mod encode;
+mod macros;
 mod map;
 mod repr;
 
-pub use decode::parse;
-pub use encode::encode;
-pub use encode::ser::to_bytes;
+pub use decode::de::{from_bytes, Error as DecodeError};
+pub use decode::stream;
+pub use decode::{parse, parse_with_options, ParseOptions, Parser};
+pub use encode::ser::{to_buf, to_bytes};
+pub use encode::{encode, encode_into, encode_to_writer, encoded_len};
 
 pub use map::Map;
 pub use repr::{Element, Elements, Error, Value};
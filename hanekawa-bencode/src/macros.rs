@@ -0,0 +1,124 @@
+/// Build a [`Value<Vec<u8>>`](crate::Value) from a JSON-like literal.
+///
+/// Dicts are written `{ key => value, .. }` and lists `[value, ..]`. Keys
+/// are anything that is `AsRef<[u8]>`, and other values anything a `Value`
+/// can be converted from, such as integers, strings, byte strings or other
+/// values. Dict keys are sorted, so the result encodes canonically.
+///
+/// ```
+/// use hanekawa_bencode::{bencode, encode};
+///
+/// let value = bencode!({
+///     "info" => { "name" => "x", "length" => 5 },
+///     "list" => [1, -2, b"y"],
+/// });
+/// assert_eq!(
+///     &b"d4:infod6:lengthi5e4:name1:xe4:listli1ei-2e1:yee"[..],
+///     encode(&value)
+/// );
+/// assert_eq!(Some("x"), value.get_path(&["info", "name"]).unwrap().as_str());
+/// ```
+///
+/// The outer braces of a dict may be left out: `bencode! { "key" => [1, "x"] }`.
+#[macro_export]
+macro_rules! bencode {
+    (@list $list:ident) => {};
+    (@list $list:ident [ $($value:tt)* ] $(, $($rest:tt)*)?) => {
+        $list.push($crate::bencode!([ $($value)* ]));
+        $crate::bencode!(@list $list $($($rest)*)?);
+    };
+    (@list $list:ident { $($value:tt)* } $(, $($rest:tt)*)?) => {
+        $list.push($crate::bencode!({ $($value)* }));
+        $crate::bencode!(@list $list $($($rest)*)?);
+    };
+    (@list $list:ident $value:expr $(, $($rest:tt)*)?) => {
+        $list.push($crate::bencode!($value));
+        $crate::bencode!(@list $list $($($rest)*)?);
+    };
+
+    (@dict $map:ident) => {};
+    (@dict $map:ident $key:expr => [ $($value:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@entry $map $key, $crate::bencode!([ $($value)* ]));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@dict $map:ident $key:expr => { $($value:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@entry $map $key, $crate::bencode!({ $($value)* }));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@dict $map:ident $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@entry $map $key, $crate::bencode!($value));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@entry $map:ident $key:expr, $value:expr) => {
+        $map.insert(
+            ::core::convert::AsRef::<[u8]>::as_ref(&$key).to_vec(),
+            $value,
+        );
+    };
+
+    ([ $($value:tt)* ]) => {{
+        #[allow(unused_mut, clippy::vec_init_then_push)]
+        let list = {
+            let mut list = ::std::vec::Vec::new();
+            $crate::bencode!(@list list $($value)*);
+            list
+        };
+        $crate::Value::<::std::vec::Vec<u8>>::List(list)
+    }};
+    ({ $($entry:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::Map::new();
+        $crate::bencode!(@dict map $($entry)*);
+        map.ensure_order();
+        $crate::Value::<::std::vec::Vec<u8>>::Dict(map)
+    }};
+    ($key:expr => $($rest:tt)*) => {
+        $crate::bencode!({ $key => $($rest)* })
+    };
+    ($value:expr) => {
+        $crate::Value::<::std::vec::Vec<u8>>::from($value)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{encode, parse, Map, Value};
+
+    #[test]
+    fn builds_values() {
+        assert_eq!(Value::Int(-3), bencode!(-3));
+        assert_eq!(Value::Bytes(b"spam".to_vec()), bencode!("spam"));
+        assert_eq!(Value::List(vec![]), bencode!([]));
+        assert_eq!(Value::Dict(Map::new()), bencode!({}));
+
+        let name = String::from("test");
+        let pieces = vec![0; 20];
+        let value = bencode! {
+            "info" => {
+                "pieces" => pieces.clone(),
+                "name" => name,
+                "piece length" => 16384,
+            },
+            "announce-list" => [["a", "b"], [], [{ "x" => 1 + 1 }]],
+        };
+
+        assert_eq!(
+            parse(
+                b"d13:announce-listll1:a1:bele\
+                    ld1:xi2eeee\
+                    4:infod4:name4:test12:piece lengthi16384e\
+                    6:pieces20:\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0ee"
+            )
+            .unwrap()
+            .into_value()
+            .into_owned(),
+            value
+        );
+
+        let reordered = bencode!({
+            "info" => value.get("info").unwrap().clone(),
+            "announce-list" => [["a", "b"], [], [{ "x" => 2 }],],
+        });
+        assert_eq!(encode(&value), encode(&reordered));
+    }
+}
@@ -25,6 +25,14 @@ impl<K: Ord, V> Map<K, V> {
         self.entries.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (K, V)> {
+        self.entries.iter()
+    }
+
     pub fn insert(&mut self, key: K, value: V) {
         self.entries.push((key, value));
     }
@@ -34,6 +42,16 @@ impl<K: Ord, V> Map<K, V> {
     }
 }
 
+impl<K: AsRef<[u8]>, V> Map<K, V> {
+    /// The value of the first entry with this key.
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| v)
+    }
+}
+
 impl<'a, K, V> IntoIterator for Map<K, V> {
     type Item = (K, V);
     type IntoIter = std::vec::IntoIter<(K, V)>;
@@ -1,3 +1,7 @@
+use std::ops::Range;
+
+use bytes::Bytes;
+
 use crate::map::Map;
 
 #[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
@@ -43,8 +47,151 @@ impl<B: Ord> Value<B> {
         ii.into_elements(self);
         Elements {
             elements: ii.elements,
+            spans: None,
+        }
+    }
+
+    /// Convert every byte string, including dict keys, with `f`.
+    pub fn map_bytes<C: Ord>(self, mut f: impl FnMut(B) -> C) -> Value<C> {
+        fn map<B: Ord, C: Ord>(value: Value<B>, f: &mut impl FnMut(B) -> C) -> Value<C> {
+            match value {
+                Value::Bytes(b) => Value::Bytes(f(b)),
+                Value::Int(i) => Value::Int(i),
+                Value::List(vs) => Value::List(vs.into_iter().map(|v| map(v, f)).collect()),
+                Value::Dict(m) => {
+                    Value::Dict(m.into_iter().map(|(k, v)| (f(k), map(v, f))).collect())
+                }
+            }
+        }
+
+        map(self, &mut f)
+    }
+}
+
+impl<B: AsRef<[u8]> + Ord> Value<B> {
+    /// Copy the value so it no longer borrows from the input.
+    pub fn into_owned(self) -> Value<Vec<u8>> {
+        self.map_bytes(|b| b.as_ref().to_vec())
+    }
+
+    /// Copy the value into reference-counted byte strings, which are cheap
+    /// to clone.
+    pub fn into_shared(self) -> Value<Bytes> {
+        self.map_bytes(|b| Bytes::copy_from_slice(b.as_ref()))
+    }
+
+    /// A value borrowing its byte strings from this one.
+    pub fn to_borrowed(&self) -> Value<&[u8]> {
+        match self {
+            Value::Bytes(b) => Value::Bytes(b.as_ref()),
+            Value::Int(i) => Value::Int(*i),
+            Value::List(vs) => Value::List(vs.iter().map(Value::to_borrowed).collect()),
+            Value::Dict(m) => Value::Dict(
+                m.into_iter()
+                    .map(|(k, v)| (k.as_ref(), v.to_borrowed()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The value of a key, if this is a dict containing it.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&Value<B>> {
+        self.as_dict()?.get(key.as_ref())
+    }
+
+    /// The value at a path of dict keys, such as `["info", "name"]`.
+    pub fn get_path<K: AsRef<[u8]>>(&self, path: &[K]) -> Option<&Value<B>> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// The byte string, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
         }
     }
+
+    pub fn as_list(&self) -> Option<&[Value<B>]> {
+        match self {
+            Value::List(vs) => Some(vs),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&Map<B, Value<B>>> {
+        match self {
+            Value::Dict(m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<Value<&'a [u8]>> for Value<Vec<u8>> {
+    fn from(value: Value<&'a [u8]>) -> Self {
+        value.into_owned()
+    }
+}
+
+impl<'a> From<Value<&'a [u8]>> for Value<Bytes> {
+    fn from(value: Value<&'a [u8]>) -> Self {
+        value.into_shared()
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {
+        $(
+            impl<B: Ord> From<$t> for Value<B> {
+                fn from(i: $t) -> Self {
+                    Value::Int(i.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_int!(i8, i16, i32, i64, u8, u16, u32);
+
+impl From<&str> for Value<Vec<u8>> {
+    fn from(s: &str) -> Self {
+        Value::Bytes(s.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Value<Vec<u8>> {
+    fn from(s: String) -> Self {
+        Value::Bytes(s.into_bytes())
+    }
+}
+
+impl From<&[u8]> for Value<Vec<u8>> {
+    fn from(b: &[u8]) -> Self {
+        Value::Bytes(b.to_vec())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Value<Vec<u8>> {
+    fn from(b: &[u8; N]) -> Self {
+        Value::Bytes(b.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Value<Vec<u8>> {
+    fn from(b: Vec<u8>) -> Self {
+        Value::Bytes(b)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -52,9 +199,56 @@ pub enum Error {
     UnexpectedEnd(String),
     ExpectedFar(u8),
     InvalidInt(Vec<u8>),
+    IntOverflow(Vec<u8>),
+    InvalidLength(Vec<u8>),
+    LengthOverflow(Vec<u8>),
+    StringTooLong(usize),
+    TooDeep,
+    TooManyElements,
+    /// A non-canonical string length in strict mode, at this offset.
+    NonCanonicalLength(usize),
+    /// A non-canonical integer in strict mode, at this offset.
+    NonCanonicalInt(usize),
+    /// A dict key that sorts before the previous key in strict mode, at this
+    /// offset.
+    UnsortedKey(usize),
+    /// A repeated dict key in strict mode, at this offset.
+    DuplicateKey(usize),
     Trailing,
 }
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd(_) => f.write_str("unexpected end of input"),
+            Self::ExpectedFar(b) => f.write_fmt(format_args!("expected '{}'", *b as char)),
+            Self::InvalidInt(_) => f.write_str("invalid integer"),
+            Self::IntOverflow(_) => f.write_str("integer out of range"),
+            Self::InvalidLength(_) => f.write_str("invalid string length"),
+            Self::LengthOverflow(_) => f.write_str("string length out of range"),
+            Self::StringTooLong(len) => f.write_fmt(format_args!("string too long: {}", len)),
+            Self::TooDeep => f.write_str("nesting too deep"),
+            Self::TooManyElements => f.write_str("too many elements"),
+            Self::NonCanonicalLength(offset) => f.write_fmt(format_args!(
+                "non-canonical string length at offset {}",
+                offset
+            )),
+            Self::NonCanonicalInt(offset) => {
+                f.write_fmt(format_args!("non-canonical integer at offset {}", offset))
+            }
+            Self::UnsortedKey(offset) => {
+                f.write_fmt(format_args!("unsorted dict key at offset {}", offset))
+            }
+            Self::DuplicateKey(offset) => {
+                f.write_fmt(format_args!("duplicate dict key at offset {}", offset))
+            }
+            Self::Trailing => f.write_str("trailing data after value"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Element<B> {
     DictBegin(usize),
@@ -66,11 +260,91 @@ pub enum Element<B> {
 #[derive(Debug, PartialEq, Eq)]
 pub struct Elements<B> {
     elements: Vec<Element<B>>,
+    spans: Option<Vec<Range<usize>>>,
 }
 
 impl<B> Elements<B> {
     pub(crate) fn from_parts(elements: Vec<Element<B>>) -> Self {
-        Self { elements }
+        Self {
+            elements,
+            spans: None,
+        }
+    }
+
+    pub(crate) fn with_spans(self, spans: Vec<Range<usize>>) -> Self {
+        Self {
+            spans: Some(spans),
+            ..self
+        }
+    }
+
+    /// Where each element starts and ends in the input, if the parser was
+    /// asked to record them. Lists and dicts span all of their contents.
+    pub fn spans(&self) -> Option<&[Range<usize>]> {
+        self.spans.as_deref()
+    }
+
+    /// The index of the element after the one at `idx` and its contents.
+    fn skip(&self, idx: usize) -> usize {
+        let mut idx = idx;
+        let mut pending = 1;
+        while pending > 0 {
+            pending += match self.elements.get(idx) {
+                Some(Element::ListBegin(ct)) => *ct,
+                Some(Element::DictBegin(ct)) => 2 * ct,
+                Some(_) => 0,
+                None => return idx,
+            };
+            pending -= 1;
+            idx += 1;
+        }
+        idx
+    }
+
+    pub(crate) fn as_slice(&self) -> &[Element<B>] {
+        &self.elements
+    }
+}
+
+impl<B: AsRef<[u8]>> Elements<B> {
+    /// Find the element at a path of dict keys, such as `["info"]`.
+    fn find(&self, path: &[&[u8]]) -> Option<usize> {
+        let mut idx = 0;
+        for key in path {
+            let ct = match self.elements.get(idx)? {
+                Element::DictBegin(ct) => *ct,
+                _ => return None,
+            };
+            idx += 1;
+
+            let mut found = None;
+            for _ in 0..ct {
+                let matches =
+                    matches!(self.elements.get(idx)?, Element::Bytes(k) if k.as_ref() == *key);
+                idx += 1;
+                if matches {
+                    found = Some(idx);
+                    break;
+                }
+                idx = self.skip(idx);
+            }
+            idx = found?;
+        }
+        Some(idx)
+    }
+
+    /// Where the value at a path of dict keys starts and ends in the input.
+    /// Spans must have been recorded when parsing.
+    pub fn span(&self, path: &[&[u8]]) -> Option<Range<usize>> {
+        let idx = self.find(path)?;
+        self.spans.as_ref()?.get(idx).cloned()
+    }
+
+    /// The raw bytes of the value at a path of dict keys, exactly as they
+    /// appear in `input`, which must be what was parsed. This is what an info
+    /// hash is computed over.
+    pub fn raw<'i>(&self, input: &'i [u8], path: &[&[u8]]) -> Option<&'i [u8]> {
+        input.get(self.span(path)?)
     }
 }
 
@@ -182,4 +456,50 @@ mod test {
 
         assert_eq!(exp, value);
     }
+
+    #[test]
+    fn converts_between_byte_containers() {
+        let input = b"d4:infod4:name4:spame4:listli1e1:xee";
+        let borrowed = crate::parse(input).unwrap().into_value();
+
+        let owned: Value<Vec<u8>> = borrowed.clone().into();
+        let shared: Value<Bytes> = borrowed.clone().into();
+
+        assert_eq!(borrowed, owned.to_borrowed());
+        assert_eq!(borrowed, shared.to_borrowed());
+        assert_eq!(
+            Value::List(vec![Value::Int(1), Value::Bytes(1)]),
+            owned.get("list").unwrap().clone().map_bytes(|b| b.len())
+        );
+    }
+
+    #[test]
+    fn gets_values_by_path() {
+        let input = b"d4:infod6:lengthi5e4:name4:spame4:listli1e1:xee";
+        let value = crate::parse(input).unwrap().into_value();
+
+        assert_eq!(
+            Some("spam"),
+            value
+                .get("info")
+                .and_then(|i| i.get("name"))
+                .and_then(Value::as_str)
+        );
+        assert_eq!(
+            Some(5),
+            value.get_path(&["info", "length"]).and_then(Value::as_int)
+        );
+        assert_eq!(
+            Some(2),
+            value.get("list").and_then(Value::as_list).map(<[_]>::len)
+        );
+        assert_eq!(
+            Some(&b"x"[..]),
+            value.get("list").unwrap().as_list().unwrap()[1].as_bytes()
+        );
+        assert_eq!(Some(2), value.as_dict().map(Map::len));
+        assert_eq!(None, value.get_path(&["info", "name", "x"]));
+        assert_eq!(None, value.get("missing"));
+        assert_eq!(None, value.get("info").and_then(Value::as_int));
+    }
 }
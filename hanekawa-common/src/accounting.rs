@@ -0,0 +1,126 @@
+//! Per-user transfer accounting and ratio enforcement.
+
+use crate::types::{Event, UserStats};
+
+/// The bytes transferred since a peer's previous announce, given the
+/// cumulative counter it reported then and now.
+///
+/// Counters restart from zero when a client restarts, so a counter lower
+/// than before means the whole current value is new. A repeated `started`
+/// announce is still measured against the stored counters, so it cannot
+/// credit the same bytes twice. Without a previous announce there is
+/// nothing to compare against: only a `started` announce is counted, since
+/// a peer returning after being purged reports counters that were already
+/// accounted for.
+pub fn transfer_delta(previous: Option<u64>, current: u64, event: &Event) -> u64 {
+    match previous {
+        Some(previous) => current.checked_sub(previous).unwrap_or(current),
+        None if *event == Event::Started => current,
+        None => 0,
+    }
+}
+
+/// What to do with users whose ratio is below the minimum.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RatioAction {
+    #[default]
+    Off,
+    /// Let them leech, with a warning in HTTP announce responses.
+    Warn,
+    /// Refuse their announces while they are leeching.
+    Refuse,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RatioPolicy {
+    pub action: RatioAction,
+    pub min_ratio: f64,
+    /// How many bytes a user may download before their ratio applies.
+    pub grace_bytes: u64,
+}
+
+impl Default for RatioPolicy {
+    fn default() -> Self {
+        Self {
+            action: RatioAction::Off,
+            min_ratio: 0.5,
+            grace_bytes: 5 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl RatioPolicy {
+    /// Check a leeching user's totals against the policy. Returns a
+    /// warning to pass on, or why they are refused.
+    pub fn check(&self, stats: &UserStats) -> Result<Option<String>, String> {
+        if self.action == RatioAction::Off || stats.credited_downloaded <= self.grace_bytes {
+            return Ok(None);
+        }
+
+        let ratio = stats.ratio().unwrap_or(f64::INFINITY);
+
+        if ratio >= self.min_ratio {
+            return Ok(None);
+        }
+
+        let message = format!(
+            "ratio {ratio:.2} is below the minimum of {:.2}",
+            self.min_ratio
+        );
+
+        match self.action {
+            RatioAction::Off => Ok(None),
+            RatioAction::Warn => Ok(Some(message)),
+            RatioAction::Refuse => Err(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn stats(uploaded: u64, downloaded: u64) -> UserStats {
+        UserStats {
+            uploaded,
+            downloaded,
+            credited_uploaded: uploaded,
+            credited_downloaded: downloaded,
+        }
+    }
+
+    #[test]
+    fn computes_deltas_across_restarts_and_duplicates() {
+        assert_eq!(50, transfer_delta(Some(100), 150, &Event::Interval));
+        assert_eq!(0, transfer_delta(Some(150), 150, &Event::Interval));
+        assert_eq!(20, transfer_delta(Some(150), 20, &Event::Interval));
+        assert_eq!(50, transfer_delta(Some(150), 200, &Event::Started));
+        assert_eq!(0, transfer_delta(Some(200), 200, &Event::Started));
+        assert_eq!(10, transfer_delta(Some(150), 10, &Event::Started));
+        assert_eq!(10, transfer_delta(None, 10, &Event::Started));
+        assert_eq!(0, transfer_delta(None, 10, &Event::Interval));
+    }
+
+    #[test]
+    fn applies_ratio_policy_after_grace() {
+        let warn = RatioPolicy {
+            action: RatioAction::Warn,
+            min_ratio: 0.5,
+            grace_bytes: GIB,
+        };
+        let refuse = RatioPolicy {
+            action: RatioAction::Refuse,
+            ..warn.clone()
+        };
+
+        assert_eq!(Ok(None), warn.check(&stats(0, GIB)));
+        assert_eq!(Ok(None), warn.check(&stats(5 * GIB, 10 * GIB)));
+        assert!(matches!(warn.check(&stats(GIB, 10 * GIB)), Ok(Some(_))));
+        assert!(refuse.check(&stats(GIB, 10 * GIB)).is_err());
+        assert_eq!(Ok(None), RatioPolicy::default().check(&stats(0, 100 * GIB)));
+    }
+}
@@ -0,0 +1,329 @@
+//! Identifying BitTorrent clients from their peer IDs.
+
+use std::cmp::Ordering;
+
+use crate::types::PeerId;
+
+const AZUREUS_CLIENTS: &[(&str, &str)] = &[
+    ("AZ", "Vuze"),
+    ("BC", "BitComet"),
+    ("BI", "BiglyBT"),
+    ("BT", "BitTorrent"),
+    ("DE", "Deluge"),
+    ("FD", "Free Download Manager"),
+    ("KT", "KTorrent"),
+    ("LT", "libtorrent (rakshasa)"),
+    ("lt", "libtorrent (Rasterbar)"),
+    ("qB", "qBittorrent"),
+    ("SD", "Thunder"),
+    ("TR", "Transmission"),
+    ("UM", "µTorrent Mac"),
+    ("UT", "µTorrent"),
+    ("WW", "WebTorrent"),
+    ("XL", "Xunlei"),
+];
+
+const SHADOW_CLIENTS: &[(&str, &str)] = &[
+    ("A", "ABC"),
+    ("O", "Osprey Permaseed"),
+    ("Q", "BTQueue"),
+    ("R", "Tribler"),
+    ("S", "Shadow's client"),
+    ("T", "BitTornado"),
+    ("U", "UPnP NAT Bit Torrent"),
+];
+
+/// A client version, compared component-wise with missing trailing
+/// components treated as zero, so `4.5` equals `4.5.0.0`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientVersion(pub Vec<u16>);
+
+impl ClientVersion {
+    fn component(&self, i: usize) -> u16 {
+        self.0.get(i).copied().unwrap_or(0)
+    }
+}
+
+impl Ord for ClientVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+
+        (0..len)
+            .map(|i| self.component(i).cmp(&other.component(i)))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for ClientVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ClientVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ClientVersion {}
+
+impl std::str::FromStr for ClientVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('.')
+            .map(|c| {
+                c.parse()
+                    .map_err(|_| format!("invalid client version: {s}"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl std::fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components: Vec<_> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&components.join("."))
+    }
+}
+
+impl serde::Serialize for ClientVersion {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ClientVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The client that generated a peer ID.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Client {
+    /// The client's identifier in the peer ID, such as `qB`.
+    pub code: String,
+    pub name: String,
+    pub version: ClientVersion,
+}
+
+/// Decode a version character: `0-9`, then `A-Z` as 10-35, then `a-z` as
+/// 36-61 and `.` as 62.
+fn version_component(c: u8) -> Option<u16> {
+    match c {
+        b'0'..=b'9' => Some((c - b'0') as u16),
+        b'A'..=b'Z' => Some((c - b'A') as u16 + 10),
+        b'a'..=b'z' => Some((c - b'a') as u16 + 36),
+        b'.' => Some(62),
+        _ => None,
+    }
+}
+
+fn name(clients: &[(&str, &str)], code: &str) -> String {
+    clients
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, name)| name)
+        .to_string()
+}
+
+impl Client {
+    /// Parse an Azureus-style (`-qB4500-`) or Shad0w-style (`T03A-----`)
+    /// peer ID.
+    pub fn from_peer_id(peer_id: &PeerId) -> Option<Self> {
+        Self::from_azureus(&peer_id.0).or_else(|| Self::from_shadow(&peer_id.0))
+    }
+
+    fn from_azureus(bs: &[u8]) -> Option<Self> {
+        let (code, version) = match bs.get(..8)? {
+            [b'-', a, b, version @ .., b'-']
+                if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() =>
+            {
+                (std::str::from_utf8(&[*a, *b]).ok()?.to_string(), version)
+            }
+            _ => return None,
+        };
+
+        let version = version
+            .iter()
+            .map(|c| version_component(*c))
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            name: name(AZUREUS_CLIENTS, &code),
+            code,
+            version: ClientVersion(version),
+        })
+    }
+
+    fn from_shadow(bs: &[u8]) -> Option<Self> {
+        let prefix = bs.get(..9)?;
+        let code = std::str::from_utf8(&prefix[..1]).ok()?;
+
+        if !SHADOW_CLIENTS.iter().any(|(c, _)| *c == code) || &prefix[6..] != b"---" {
+            return None;
+        }
+
+        let version = prefix[1..6]
+            .iter()
+            .take_while(|c| **c != b'-')
+            .map(|c| version_component(*c))
+            .collect::<Option<Vec<_>>>()?;
+
+        if version.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            code: code.to_string(),
+            name: name(SHADOW_CLIENTS, code),
+            version: ClientVersion(version),
+        })
+    }
+}
+
+/// A client, optionally restricted to an inclusive range of versions.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClientRule {
+    /// The client's identifier in the peer ID, such as `qB` or `T`.
+    pub client: String,
+    #[serde(default)]
+    pub min_version: Option<ClientVersion>,
+    #[serde(default)]
+    pub max_version: Option<ClientVersion>,
+}
+
+impl ClientRule {
+    fn matches(&self, client: &Client) -> bool {
+        let above_min = match &self.min_version {
+            Some(v) => &client.version >= v,
+            None => true,
+        };
+        let below_max = match &self.max_version {
+            Some(v) => &client.version <= v,
+            None => true,
+        };
+
+        self.client == client.code && above_min && below_max
+    }
+}
+
+/// Which clients may announce. Denied clients are always refused. If any
+/// clients are allowed, every other client is refused, including those
+/// whose peer IDs cannot be identified.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ClientPolicy {
+    pub allow: Vec<ClientRule>,
+    pub deny: Vec<ClientRule>,
+}
+
+impl ClientPolicy {
+    /// Check a peer ID against the policy, returning why it is refused.
+    pub fn check(&self, peer_id: &PeerId) -> Result<(), String> {
+        if self.allow.is_empty() && self.deny.is_empty() {
+            return Ok(());
+        }
+
+        let client = match Client::from_peer_id(peer_id) {
+            Some(client) => client,
+            None if self.allow.is_empty() => return Ok(()),
+            None => return Err("unidentified client".to_string()),
+        };
+
+        let denied = self.deny.iter().any(|r| r.matches(&client));
+        let allowed = self.allow.is_empty() || self.allow.iter().any(|r| r.matches(&client));
+
+        if denied || !allowed {
+            Err(format!("{} {}", client.name, client.version))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn peer_id(s: &str) -> PeerId {
+        PeerId(s.as_bytes().to_vec())
+    }
+
+    fn rule(client: &str, min: Option<&str>, max: Option<&str>) -> ClientRule {
+        ClientRule {
+            client: client.to_string(),
+            min_version: min.map(|v| v.parse().unwrap()),
+            max_version: max.map(|v| v.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn parses_azureus_style_peer_ids() {
+        let client = Client::from_peer_id(&peer_id("-qB4500-abcdefghijkl")).unwrap();
+
+        assert_eq!("qB", client.code);
+        assert_eq!("qBittorrent", client.name);
+        assert_eq!("4.5.0.0", client.version.to_string());
+
+        let client = Client::from_peer_id(&peer_id("-ZZ12A0-abcdefghijkl")).unwrap();
+
+        assert_eq!("ZZ", client.name);
+        assert_eq!(ClientVersion(vec![1, 2, 10]), client.version);
+    }
+
+    #[test]
+    fn parses_shadow_style_peer_ids() {
+        let client = Client::from_peer_id(&peer_id("T03A-----abcdefghijk")).unwrap();
+
+        assert_eq!("BitTornado", client.name);
+        assert_eq!("0.3.10", client.version.to_string());
+    }
+
+    #[test]
+    fn does_not_identify_other_peer_ids() {
+        assert_eq!(None, Client::from_peer_id(&peer_id("M7-2-2--abcdefghijkl")));
+        assert_eq!(None, Client::from_peer_id(&peer_id("Xabcdefghijklmnopqrs")));
+        assert_eq!(None, Client::from_peer_id(&PeerId(vec![0xff; 20])));
+    }
+
+    #[test]
+    fn compares_versions_with_implicit_zeros() {
+        let v = |s: &str| s.parse::<ClientVersion>().unwrap();
+
+        assert_eq!(v("4.5"), v("4.5.0.0"));
+        assert!(v("4.5.1") > v("4.5"));
+        assert!(v("4.10") > v("4.9.9"));
+    }
+
+    #[test]
+    fn enforces_allowed_and_denied_clients() {
+        let policy = ClientPolicy {
+            allow: vec![rule("qB", Some("4.4"), None), rule("TR", None, None)],
+            deny: vec![rule("TR", None, Some("2.9"))],
+        };
+
+        assert!(policy.check(&peer_id("-qB4500-abcdefghijkl")).is_ok());
+        assert!(policy.check(&peer_id("-qB4300-abcdefghijkl")).is_err());
+        assert!(policy.check(&peer_id("-TR3000-abcdefghijkl")).is_ok());
+        assert!(policy.check(&peer_id("-TR2900-abcdefghijkl")).is_err());
+        assert!(policy.check(&peer_id("-UT355S-abcdefghijkl")).is_err());
+        assert!(policy.check(&peer_id("M7-2-2--abcdefghijkl")).is_err());
+
+        let deny_only = ClientPolicy {
+            allow: vec![],
+            deny: vec![rule("XL", None, None)],
+        };
+
+        assert!(deny_only.check(&peer_id("-XL0012-abcdefghijkl")).is_err());
+        assert!(deny_only.check(&peer_id("M7-2-2--abcdefghijkl")).is_ok());
+        assert!(ClientPolicy::default()
+            .check(&peer_id("-XL0012-abcdefghijkl"))
+            .is_ok());
+    }
+}
@@ -0,0 +1,119 @@
+//! Rules for detecting users who stop seeding too soon after completing
+//! a torrent.
+
+use time::OffsetDateTime;
+
+use crate::types::{ObligationStatus, SeedingObligation};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HitAndRunRules {
+    pub enabled: bool,
+    /// How often to check pending obligations, in seconds.
+    pub check_interval: u32,
+    /// How long users must seed after completing a torrent, in seconds.
+    pub min_seed_time: u64,
+    /// How long after completing users have to meet the requirements, in
+    /// seconds. Users still seeding when it ends are not flagged.
+    pub seed_window: u64,
+    /// Reaching this ratio on the torrent meets the requirements early.
+    pub min_ratio: Option<f64>,
+}
+
+impl Default for HitAndRunRules {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval: 300,
+            min_seed_time: 72 * 60 * 60,
+            seed_window: 14 * 24 * 60 * 60,
+            min_ratio: Some(1.0),
+        }
+    }
+}
+
+impl HitAndRunRules {
+    pub fn evaluate(
+        &self,
+        obligation: &SeedingObligation,
+        now: OffsetDateTime,
+    ) -> ObligationStatus {
+        if obligation.seeded_seconds >= self.min_seed_time {
+            return ObligationStatus::Satisfied;
+        }
+
+        if let Some(min_ratio) = self.min_ratio {
+            if obligation.downloaded > 0
+                && obligation.uploaded as f64 / obligation.downloaded as f64 >= min_ratio
+            {
+                return ObligationStatus::Satisfied;
+            }
+        }
+
+        let deadline = obligation.completed_ts + std::time::Duration::from_secs(self.seed_window);
+
+        if now >= deadline && !obligation.is_seeding {
+            ObligationStatus::HitAndRun
+        } else {
+            ObligationStatus::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::types::InfoHash;
+
+    const HOUR: u64 = 60 * 60;
+
+    fn obligation(seeded_hours: u64, is_seeding: bool, uploaded: u64) -> SeedingObligation {
+        SeedingObligation {
+            user_id: 1,
+            info_hash: InfoHash(vec![0; 20]),
+            completed_ts: OffsetDateTime::UNIX_EPOCH,
+            status: ObligationStatus::Pending,
+            resolved_ts: None,
+            seeded_seconds: seeded_hours * HOUR,
+            is_seeding,
+            uploaded,
+            downloaded: 100,
+        }
+    }
+
+    #[test]
+    fn flags_users_who_stop_seeding_before_the_deadline() {
+        let rules = HitAndRunRules {
+            enabled: true,
+            check_interval: 300,
+            min_seed_time: 72 * HOUR,
+            seed_window: 24 * 7 * HOUR,
+            min_ratio: Some(1.0),
+        };
+
+        let during = OffsetDateTime::UNIX_EPOCH + std::time::Duration::from_secs(24 * HOUR);
+        let after = OffsetDateTime::UNIX_EPOCH + std::time::Duration::from_secs(24 * 8 * HOUR);
+
+        assert_eq!(
+            ObligationStatus::Satisfied,
+            rules.evaluate(&obligation(72, false, 0), during)
+        );
+        assert_eq!(
+            ObligationStatus::Satisfied,
+            rules.evaluate(&obligation(1, false, 100), during)
+        );
+        assert_eq!(
+            ObligationStatus::Pending,
+            rules.evaluate(&obligation(10, false, 0), during)
+        );
+        assert_eq!(
+            ObligationStatus::Pending,
+            rules.evaluate(&obligation(10, true, 0), after)
+        );
+        assert_eq!(
+            ObligationStatus::HitAndRun,
+            rules.evaluate(&obligation(10, false, 50), after)
+        );
+    }
+}
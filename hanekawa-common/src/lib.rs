@@ -1,43 +1,124 @@
+pub mod accounting;
+pub mod client;
+pub mod hit_and_run;
+pub mod metainfo;
 pub mod repository;
 pub mod task;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod types;
 
 use std::{net::Ipv4Addr, sync::Arc};
 
 #[derive(serde::Deserialize, Debug, Clone)]
 pub struct Config {
+    pub role: Role,
     pub database_url: String,
-    pub message_queue_url: String,
+    pub message_queue_url: Option<String>,
+    pub task_queue_backend: TaskQueueBackend,
+    pub task_visibility_timeout: u32,
+    pub task_max_attempts: u32,
+    pub task_retry_base_delay: u32,
+    pub task_retry_max_delay: u32,
+    pub task_concurrency: u32,
+    pub task_prefetch: u16,
+    pub task_drain_timeout: u32,
     pub bind_ip: Ipv4Addr,
     pub http_bind_port: u16,
     pub udp_bind_port: u16,
+    /// Keys UDP connection IDs. Every tracker process behind the same
+    /// address must share it, or clients are rejected when consecutive
+    /// requests reach different processes. Random per process if unset.
+    pub udp_connection_secret: Option<String>,
     pub peer_announce_interval: u32,
     pub peer_activity_timeout: u32,
+    pub peer_purge_interval: u32,
     pub only_allowed_info_hashes: bool,
+    pub private_mode: bool,
+    #[serde(default)]
+    pub ratio_policy: accounting::RatioPolicy,
+    #[serde(default)]
+    pub hit_and_run: hit_and_run::HitAndRunRules,
+    pub ban_refresh_interval: u32,
+    #[serde(default)]
+    pub client_policy: client::ClientPolicy,
     pub enable_admin_api: bool,
+    pub admin_bootstrap_token: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskQueueBackend {
+    Amqp,
+    Postgres,
+}
+
+/// Which parts of the tracker a process runs.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Serve HTTP and UDP tracker requests and the admin API.
+    Tracker,
+    /// Consume background tasks and fire scheduled jobs.
+    Worker,
+    All,
+}
+
+impl Role {
+    pub fn runs_tracker(&self) -> bool {
+        matches!(self, Self::Tracker | Self::All)
+    }
+
+    pub fn runs_worker(&self) -> bool {
+        matches!(self, Self::Worker | Self::All)
+    }
 }
 
 impl Config {
     pub fn default_config() -> impl serde::Serialize {
         #[derive(serde::Serialize)]
         struct DefaultConfig {
+            pub role: Role,
             pub bind_ip: Ipv4Addr,
             pub http_bind_port: u16,
             pub udp_bind_port: u16,
             pub peer_announce_interval: u32,
             pub peer_activity_timeout: u32,
+            pub peer_purge_interval: u32,
             pub only_allowed_info_hashes: bool,
+            pub private_mode: bool,
+            pub ban_refresh_interval: u32,
             pub enable_admin_api: bool,
+            pub task_queue_backend: TaskQueueBackend,
+            pub task_visibility_timeout: u32,
+            pub task_max_attempts: u32,
+            pub task_retry_base_delay: u32,
+            pub task_retry_max_delay: u32,
+            pub task_concurrency: u32,
+            pub task_prefetch: u16,
+            pub task_drain_timeout: u32,
         }
 
         let defaults = DefaultConfig {
+            role: Role::All,
             bind_ip: "0.0.0.0".parse().unwrap(),
             http_bind_port: 8001,
             udp_bind_port: 8002,
             peer_announce_interval: 60,
             peer_activity_timeout: 120,
+            peer_purge_interval: 60,
             only_allowed_info_hashes: false,
+            private_mode: false,
+            ban_refresh_interval: 30,
             enable_admin_api: false,
+            task_queue_backend: TaskQueueBackend::Amqp,
+            task_visibility_timeout: 30,
+            task_max_attempts: 5,
+            task_retry_base_delay: 10,
+            task_retry_max_delay: 3600,
+            task_concurrency: 4,
+            task_prefetch: 16,
+            task_drain_timeout: 30,
         };
 
         defaults
@@ -46,7 +127,14 @@ impl Config {
 
 #[derive(Clone)]
 pub struct Services {
+    pub api_key_repository: Arc<dyn crate::repository::api_key::ApiKeyRepository>,
+    pub audit_repository: Arc<dyn crate::repository::audit::AuditRepository>,
+    pub ban_repository: Arc<dyn crate::repository::ban::BanRepository>,
     pub peer_repository: Arc<dyn crate::repository::peer::PeerRepository>,
     pub info_hash_repository: Arc<dyn crate::repository::info_hash::InfoHashRepository>,
+    pub schedule_repository: Arc<dyn crate::repository::schedule::ScheduleRepository>,
+    pub seeding_repository: Arc<dyn crate::repository::seeding::SeedingRepository>,
+    pub user_repository: Arc<dyn crate::repository::user::UserRepository>,
     pub task_queue: Arc<dyn crate::task::TaskQueue>,
+    pub dead_letter_queue: Arc<dyn crate::task::DeadLetterQueue>,
 }
@@ -0,0 +1,652 @@
+//! Typed `.torrent` metainfo: BitTorrent v1 (BEP 3), v2 (BEP 52) and hybrid
+//! torrents, with announce lists (BEP 12), web seeds (BEP 19) and the private
+//! flag (BEP 27).
+
+use std::collections::BTreeMap;
+
+use hanekawa_bencode::{DecodeError, ParseOptions};
+use serde_bytes::ByteBuf;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::types::InfoHash;
+
+const V1_PIECE_HASH_LEN: usize = 20;
+const V2_PIECE_HASH_LEN: usize = 32;
+const MIN_V2_PIECE_LENGTH: u64 = 16 * 1024;
+
+#[derive(Debug)]
+pub enum MetainfoError {
+    Parse(hanekawa_bencode::Error),
+    Decode(DecodeError),
+    Invalid(String),
+}
+
+impl std::fmt::Display for MetainfoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "invalid bencode: {e}"),
+            Self::Decode(e) => write!(f, "invalid metainfo: {e}"),
+            Self::Invalid(s) => write!(f, "invalid metainfo: {s}"),
+        }
+    }
+}
+
+impl std::error::Error for MetainfoError {}
+
+fn invalid<T>(msg: impl Into<String>) -> Result<T, MetainfoError> {
+    Err(MetainfoError::Invalid(msg.into()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Version {
+    V1,
+    V2,
+    Hybrid,
+}
+
+impl Version {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+            Self::V2 => "v2",
+            Self::Hybrid => "hybrid",
+        }
+    }
+}
+
+/// A file in the v1 file list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct File {
+    /// The path below the torrent's name. Empty for single-file torrents,
+    /// whose name is the file name.
+    pub path: Vec<String>,
+    pub length: u64,
+    /// Whether this file only pads the next file out to a piece boundary
+    /// (BEP 47), as in hybrid torrents.
+    pub padding: bool,
+}
+
+/// A file in the v2 file tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeFile {
+    pub path: Vec<String>,
+    pub length: u64,
+    /// The root of the file's merkle tree. Only empty files have none.
+    pub pieces_root: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Info {
+    pub name: String,
+    pub piece_length: u64,
+    pub private: bool,
+    /// The concatenated SHA-1 piece hashes, in v1 and hybrid torrents.
+    pub pieces: Option<Vec<u8>>,
+    /// The v1 file list, in v1 and hybrid torrents.
+    pub files: Option<Vec<File>>,
+    /// The v2 file tree in path order, in v2 and hybrid torrents.
+    pub file_tree: Option<Vec<TreeFile>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metainfo {
+    pub announce: Option<String>,
+    /// Tiers of trackers, tried in order.
+    pub announce_list: Vec<Vec<String>>,
+    /// Web seed URLs.
+    pub url_list: Vec<String>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    pub creation_date: Option<i64>,
+    pub info: Info,
+    /// The concatenated SHA-256 hashes of the pieces of each v2 file larger
+    /// than one piece, keyed by the file's pieces root.
+    pub piece_layers: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The SHA-1 of the info dict, in v1 and hybrid torrents.
+    pub info_hash_v1: Option<InfoHash>,
+    /// The SHA-256 of the info dict, in v2 and hybrid torrents.
+    pub info_hash_v2: Option<InfoHash>,
+}
+
+mod raw {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+    use serde_bytes::ByteBuf;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub(super) enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Metainfo {
+        pub(super) announce: Option<String>,
+        #[serde(rename = "announce-list", default)]
+        pub(super) announce_list: Vec<Vec<String>>,
+        #[serde(rename = "url-list")]
+        pub(super) url_list: Option<OneOrMany>,
+        pub(super) comment: Option<String>,
+        #[serde(rename = "created by")]
+        pub(super) created_by: Option<String>,
+        #[serde(rename = "creation date")]
+        pub(super) creation_date: Option<i64>,
+        pub(super) info: Info,
+        #[serde(rename = "piece layers", default)]
+        pub(super) piece_layers: BTreeMap<ByteBuf, ByteBuf>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Info {
+        pub(super) name: String,
+        #[serde(rename = "piece length")]
+        pub(super) piece_length: u64,
+        #[serde(default)]
+        pub(super) private: bool,
+        pub(super) pieces: Option<ByteBuf>,
+        pub(super) length: Option<u64>,
+        pub(super) files: Option<Vec<File>>,
+        #[serde(rename = "meta version")]
+        pub(super) meta_version: Option<i64>,
+        #[serde(rename = "file tree")]
+        pub(super) file_tree: Option<BTreeMap<String, Node>>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct File {
+        pub(super) length: u64,
+        pub(super) path: Vec<String>,
+        pub(super) attr: Option<String>,
+    }
+
+    /// A file tree entry: a file's details under the empty key, or a
+    /// directory's contents.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub(super) enum Node {
+        File {
+            length: u64,
+            #[serde(rename = "pieces root")]
+            pieces_root: Option<ByteBuf>,
+        },
+        Dir(BTreeMap<String, Node>),
+    }
+}
+
+fn check_path(path: &[String]) -> Result<(), MetainfoError> {
+    let is_valid = |c: &String| !c.is_empty() && c != "." && c != ".." && !c.contains('/');
+
+    if path.is_empty() || !path.iter().all(is_valid) {
+        return invalid(format!("invalid file path: {:?}", path));
+    }
+
+    Ok(())
+}
+
+fn flatten_file_tree(
+    tree: BTreeMap<String, raw::Node>,
+    path: &mut Vec<String>,
+    files: &mut Vec<TreeFile>,
+) -> Result<(), MetainfoError> {
+    if tree.contains_key("") && tree.len() > 1 {
+        return invalid(format!("file is also a directory: {:?}", path));
+    }
+
+    for (name, node) in tree {
+        match (name.is_empty(), node) {
+            (
+                true,
+                raw::Node::File {
+                    length,
+                    pieces_root,
+                },
+            ) => {
+                check_path(path)?;
+                files.push(TreeFile {
+                    path: path.clone(),
+                    length,
+                    pieces_root: pieces_root.map(ByteBuf::into_vec),
+                });
+            }
+            (false, raw::Node::Dir(children)) => {
+                path.push(name);
+                flatten_file_tree(children, path, files)?;
+                path.pop();
+            }
+            _ => return invalid(format!("malformed file tree entry: {:?}", path)),
+        }
+    }
+
+    Ok(())
+}
+
+/// The sum of file lengths, which must fit in a bencode integer.
+fn sum_lengths(lengths: impl IntoIterator<Item = u64>) -> Result<u64, MetainfoError> {
+    lengths
+        .into_iter()
+        .try_fold(0_u64, |total, length| total.checked_add(length))
+        .filter(|total| i64::try_from(*total).is_ok())
+        .map_or_else(|| invalid("total length overflows"), Ok)
+}
+
+fn check_v1(info: &raw::Info, files: &[File]) -> Result<(), MetainfoError> {
+    let pieces = info
+        .pieces
+        .as_ref()
+        .map(|p| p.as_slice())
+        .unwrap_or_default();
+    if !pieces.len().is_multiple_of(V1_PIECE_HASH_LEN) {
+        return invalid("pieces is not a whole number of hashes");
+    }
+
+    let total = sum_lengths(files.iter().map(|f| f.length))?;
+    let expected = total.div_ceil(info.piece_length);
+    if pieces.len() as u64 / V1_PIECE_HASH_LEN as u64 != expected {
+        return invalid(format!(
+            "expected {} pieces for {} bytes, found {}",
+            expected,
+            total,
+            pieces.len() / V1_PIECE_HASH_LEN
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_v2(
+    piece_length: u64,
+    files: &[TreeFile],
+    piece_layers: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Result<(), MetainfoError> {
+    if !piece_length.is_power_of_two() || piece_length < MIN_V2_PIECE_LENGTH {
+        return invalid(format!("invalid v2 piece length: {}", piece_length));
+    }
+
+    for file in files {
+        let pieces_root = match (&file.pieces_root, file.length) {
+            (None, 0) => continue,
+            (Some(root), length) if length > 0 && root.len() == V2_PIECE_HASH_LEN => root,
+            _ => return invalid(format!("invalid pieces root: {:?}", file.path)),
+        };
+
+        if file.length <= piece_length {
+            continue;
+        }
+
+        let expected = usize::try_from(file.length.div_ceil(piece_length))
+            .ok()
+            .and_then(|pieces| pieces.checked_mul(V2_PIECE_HASH_LEN));
+        match piece_layers.get(pieces_root) {
+            Some(layer) if Some(layer.len()) == expected => {}
+            _ => return invalid(format!("missing or invalid piece layer: {:?}", file.path)),
+        }
+    }
+
+    Ok(())
+}
+
+impl Metainfo {
+    /// Parse and validate a `.torrent` file. It must be canonical bencode,
+    /// since its info hashes are computed over the raw info dict.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MetainfoError> {
+        let options = ParseOptions {
+            strict: true,
+            spans: true,
+            ..Default::default()
+        };
+        let elements =
+            hanekawa_bencode::parse_with_options(bytes, &options).map_err(MetainfoError::Parse)?;
+        let raw_info = match elements.raw(bytes, &[b"info"]) {
+            Some(raw_info) => raw_info,
+            None => return invalid("missing info dict"),
+        };
+
+        let raw: raw::Metainfo =
+            hanekawa_bencode::from_bytes(bytes).map_err(MetainfoError::Decode)?;
+        let info = raw.info;
+
+        if info.piece_length == 0 {
+            return invalid("piece length must be positive");
+        }
+
+        let has_v1 = info.pieces.is_some();
+        let has_v2 = match (info.meta_version, &info.file_tree) {
+            (None, None) => false,
+            (Some(2), Some(_)) => true,
+            (Some(2), None) => return invalid("missing file tree"),
+            (None, Some(_)) => return invalid("missing meta version"),
+            (Some(v), _) => return invalid(format!("unsupported meta version: {}", v)),
+        };
+        if !has_v1 && !has_v2 {
+            return invalid("missing pieces or file tree");
+        }
+
+        let files = if has_v1 {
+            let files = match (info.length, &info.files) {
+                (Some(length), None) => vec![File {
+                    path: vec![],
+                    length,
+                    padding: false,
+                }],
+                (None, Some(files)) if !files.is_empty() => files
+                    .iter()
+                    .map(|f| {
+                        check_path(&f.path)?;
+                        Ok(File {
+                            path: f.path.clone(),
+                            length: f.length,
+                            padding: f.attr.as_deref().unwrap_or_default().contains('p'),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => return invalid("expected either a length or a list of files"),
+            };
+            check_v1(&info, &files)?;
+            Some(files)
+        } else {
+            None
+        };
+
+        let piece_layers: BTreeMap<_, _> = raw
+            .piece_layers
+            .into_iter()
+            .map(|(k, v)| (k.into_vec(), v.into_vec()))
+            .collect();
+
+        let file_tree = match info.file_tree {
+            Some(tree) if has_v2 => {
+                let mut tree_files = vec![];
+                flatten_file_tree(tree, &mut vec![], &mut tree_files)?;
+                sum_lengths(tree_files.iter().map(|f| f.length))?;
+                check_v2(info.piece_length, &tree_files, &piece_layers)?;
+                Some(tree_files)
+            }
+            _ => None,
+        };
+
+        if let (Some(files), Some(tree_files)) = (&files, &file_tree) {
+            // The v1 part of a hybrid torrent describes the same files, with
+            // padding between them.
+            let single_file_path = vec![info.name.clone()];
+            let v1_files = files
+                .iter()
+                .filter(|f| !f.padding)
+                .map(|f| match f.path.is_empty() {
+                    true => (&single_file_path, f.length),
+                    false => (&f.path, f.length),
+                });
+            let v2_files = tree_files.iter().map(|f| (&f.path, f.length));
+
+            if !v1_files.eq(v2_files) {
+                return invalid("v1 and v2 file lists differ");
+            }
+        }
+
+        let info_hash_v1 = has_v1.then(|| InfoHash(Sha1::digest(raw_info).to_vec()));
+        let info_hash_v2 = has_v2.then(|| InfoHash(Sha256::digest(raw_info).to_vec()));
+
+        Ok(Self {
+            announce: raw.announce,
+            announce_list: raw.announce_list,
+            url_list: match raw.url_list {
+                Some(raw::OneOrMany::One(url)) => vec![url],
+                Some(raw::OneOrMany::Many(urls)) => urls,
+                None => vec![],
+            },
+            comment: raw.comment,
+            created_by: raw.created_by,
+            creation_date: raw.creation_date,
+            info: Info {
+                name: info.name,
+                piece_length: info.piece_length,
+                private: info.private,
+                pieces: info.pieces.map(ByteBuf::into_vec),
+                files,
+                file_tree,
+            },
+            piece_layers,
+            info_hash_v1,
+            info_hash_v2,
+        })
+    }
+
+    pub fn version(&self) -> Version {
+        match (&self.info_hash_v1, &self.info_hash_v2) {
+            (Some(_), Some(_)) => Version::Hybrid,
+            (None, Some(_)) => Version::V2,
+            _ => Version::V1,
+        }
+    }
+
+    /// The total size of the torrent's files, not counting padding.
+    pub fn total_length(&self) -> u64 {
+        // Parsing rejects totals that overflow, so this only saturates for
+        // a `Metainfo` built by hand.
+        let lengths: Box<dyn Iterator<Item = u64>> = match (&self.info.files, &self.info.file_tree)
+        {
+            (Some(files), _) => Box::new(files.iter().filter(|f| !f.padding).map(|f| f.length)),
+            (None, Some(files)) => Box::new(files.iter().map(|f| f.length)),
+            (None, None) => Box::new(std::iter::empty()),
+        };
+
+        lengths.fold(0, u64::saturating_add)
+    }
+
+    /// The number of files in the torrent, not counting padding.
+    pub fn file_count(&self) -> usize {
+        match (&self.info.files, &self.info.file_tree) {
+            (Some(files), _) => files.iter().filter(|f| !f.padding).count(),
+            (None, Some(files)) => files.len(),
+            (None, None) => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hanekawa_bencode::{bencode, Map, Value};
+    use include_dir::{include_dir, Dir};
+
+    static TORRENT_SAMPLES_DIR: Dir<'_> =
+        include_dir!("$CARGO_MANIFEST_DIR/../hanekawa-bencode/benches/samples/");
+
+    fn bytes(b: &[u8]) -> Value<Vec<u8>> {
+        Value::Bytes(b.to_vec())
+    }
+
+    fn dict(entries: Vec<(&str, Value<Vec<u8>>)>) -> Value<Vec<u8>> {
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .map(|(k, v)| (k.as_bytes().to_vec(), v))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Value::Dict(Map::from_raw(entries))
+    }
+
+    fn tree_file(length: i64, root: Option<u8>) -> Value<Vec<u8>> {
+        let file = match root {
+            Some(root) => bencode!({ "length" => length, "pieces root" => &[root; 32] }),
+            None => bencode!({ "length" => length }),
+        };
+        bencode!({ "" => file })
+    }
+
+    fn v1_file(path: &[&str], length: i64, padding: bool) -> Value<Vec<u8>> {
+        let path = Value::List(path.iter().map(|&p| p.into()).collect());
+        match padding {
+            true => bencode!({ "attr" => "p", "length" => length, "path" => path }),
+            false => bencode!({ "length" => length, "path" => path }),
+        }
+    }
+
+    /// A torrent with a two-piece file, a small file in a directory and an
+    /// empty file, optionally with the padded v1 file list of a hybrid.
+    fn v2_torrent(hybrid: bool, piece_layers: bool) -> Vec<u8> {
+        let file_tree = bencode!({
+            "a.txt" => tree_file(20000, Some(1)),
+            "dir" => { "b.txt" => tree_file(10, Some(2)) },
+            "empty" => tree_file(0, None),
+        });
+
+        let mut info = vec![
+            ("name", bytes(b"test")),
+            ("piece length", Value::Int(16384)),
+            ("meta version", Value::Int(2)),
+            ("file tree", file_tree),
+            ("private", Value::Int(1)),
+        ];
+        if hybrid {
+            info.push((
+                "files",
+                Value::List(vec![
+                    v1_file(&["a.txt"], 20000, false),
+                    v1_file(&[".pad", "12768"], 12768, true),
+                    v1_file(&["dir", "b.txt"], 10, false),
+                    v1_file(&[".pad", "16374"], 16374, true),
+                    v1_file(&["empty"], 0, false),
+                ]),
+            ));
+            info.push(("pieces", bytes(&[0; 60])));
+        }
+
+        let mut torrent = vec![
+            ("announce", bytes(b"http://tracker.example/announce")),
+            ("url-list", bytes(b"http://seed.example/")),
+            ("info", dict(info)),
+        ];
+        if piece_layers {
+            torrent.push(("piece layers", bencode!({ [1; 32] => &[3; 64] })));
+        }
+
+        hanekawa_bencode::encode(&dict(torrent))
+    }
+
+    #[test]
+    fn parses_v1_samples() {
+        for sample in TORRENT_SAMPLES_DIR.files() {
+            let metainfo = Metainfo::from_bytes(sample.contents()).unwrap();
+
+            assert_eq!(Version::V1, metainfo.version());
+            assert!(!metainfo.url_list.is_empty());
+            assert!(!metainfo.announce_list.is_empty());
+            assert!(!metainfo.info.private);
+            assert!(metainfo.total_length() > 0);
+        }
+
+        let voynich = TORRENT_SAMPLES_DIR
+            .get_file("TheVoynichManuscript_archive.torrent")
+            .unwrap();
+        let metainfo = Metainfo::from_bytes(voynich.contents()).unwrap();
+
+        assert_eq!(
+            "01754695f7174eb85aad2a436795cfec4fb20897",
+            metainfo.info_hash_v1.as_ref().unwrap().to_hex()
+        );
+        assert_eq!(13, metainfo.info.files.as_ref().unwrap().len());
+        assert_eq!(101420791, metainfo.total_length());
+    }
+
+    #[test]
+    fn parses_v2_and_hybrid_torrents() {
+        let v2 = Metainfo::from_bytes(&v2_torrent(false, true)).unwrap();
+
+        assert_eq!(Version::V2, v2.version());
+        assert!(v2.info.private);
+        assert_eq!(vec!["http://seed.example/".to_string()], v2.url_list);
+        assert_eq!(20010, v2.total_length());
+        assert_eq!(InfoHash::V2_LEN, v2.info_hash_v2.unwrap().0.len());
+
+        let file_tree = v2.info.file_tree.unwrap();
+        assert_eq!(vec!["dir", "b.txt"], file_tree[1].path);
+        assert_eq!(None, file_tree[2].pieces_root);
+
+        let hybrid = Metainfo::from_bytes(&v2_torrent(true, true)).unwrap();
+
+        assert_eq!(Version::Hybrid, hybrid.version());
+        assert_eq!(20010, hybrid.total_length());
+        assert!(hybrid.info_hash_v1.is_some());
+    }
+
+    #[test]
+    fn rejects_invalid_torrents() {
+        let reject = |input: &[u8], reason: &str| {
+            let err = Metainfo::from_bytes(input).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}", err);
+        };
+
+        let single = |length: i64, pieces: usize, path: Option<&str>| {
+            let mut info = vec![
+                ("name", bytes(b"test")),
+                ("piece length", Value::Int(16384)),
+                ("pieces", bytes(&vec![0; pieces])),
+            ];
+            match path {
+                Some(path) => info.push((
+                    "files",
+                    Value::List(vec![v1_file(&["dir", path], length, false)]),
+                )),
+                None => info.push(("length", Value::Int(length))),
+            }
+            hanekawa_bencode::encode(&dict(vec![("info", dict(info))]))
+        };
+
+        assert!(Metainfo::from_bytes(&single(16385, 40, None)).is_ok());
+        reject(&single(16385, 20, None), "expected 2 pieces");
+        reject(&single(10, 30, None), "whole number");
+        reject(&single(10, 20, Some("..")), "invalid file path");
+        reject(
+            b"d4:infod4:name4:test12:piece lengthi16384eee",
+            "missing pieces",
+        );
+        reject(b"d1:bi1e1:ai2ee", "invalid bencode");
+        reject(&v2_torrent(false, false), "missing or invalid piece layer");
+    }
+
+    #[test]
+    fn rejects_overflowing_lengths() {
+        let reject = |input: &[u8]| {
+            let err = Metainfo::from_bytes(input).unwrap_err().to_string();
+            assert!(err.contains("total length overflows"), "{}", err);
+        };
+
+        let v1 = dict(vec![(
+            "info",
+            dict(vec![
+                ("name", bytes(b"test")),
+                ("piece length", Value::Int(16384)),
+                ("pieces", bytes(b"")),
+                (
+                    "files",
+                    Value::List(vec![
+                        v1_file(&["a"], i64::MAX, false),
+                        v1_file(&["b"], i64::MAX, false),
+                        v1_file(&["c"], 2, false),
+                    ]),
+                ),
+            ]),
+        )]);
+        reject(&hanekawa_bencode::encode(&v1));
+
+        let v2 = dict(vec![(
+            "info",
+            dict(vec![
+                ("name", bytes(b"test")),
+                ("piece length", Value::Int(16384)),
+                ("meta version", Value::Int(2)),
+                (
+                    "file tree",
+                    bencode!({
+                        "a" => tree_file(i64::MAX, Some(1)),
+                        "b" => tree_file(i64::MAX, Some(2)),
+                    }),
+                ),
+            ]),
+        )]);
+        reject(&hanekawa_bencode::encode(&v2));
+    }
+}
@@ -0,0 +1,46 @@
+use crate::types::{ApiKey, ApiKeyScope};
+
+use super::{audit::Actor, Error};
+
+#[derive(Debug, Clone)]
+pub struct CreateApiKey<'a> {
+    pub name: &'a str,
+    pub scope: ApiKeyScope,
+    pub key_hash: &'a [u8],
+    pub actor: Actor,
+}
+
+/// Create a key from configuration rather than on behalf of an admin, so
+/// nothing is audited.
+#[derive(Debug, Clone)]
+pub struct EnsureApiKey<'a> {
+    pub name: &'a str,
+    pub scope: ApiKeyScope,
+    pub key_hash: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct GetApiKey<'a> {
+    pub key_hash: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokeApiKey {
+    pub id: i64,
+    pub actor: Actor,
+}
+
+#[async_trait::async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create_api_key(&self, cmd: CreateApiKey<'_>) -> Result<ApiKey, Error>;
+
+    /// Create an API key unless one with the same hash already exists.
+    async fn ensure_api_key(&self, cmd: EnsureApiKey<'_>) -> Result<(), Error>;
+
+    async fn get_api_key(&self, cmd: GetApiKey<'_>) -> Result<Option<ApiKey>, Error>;
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, Error>;
+
+    /// Revoke an API key, returning whether an active key was revoked.
+    async fn revoke_api_key(&self, cmd: RevokeApiKey) -> Result<bool, Error>;
+}
@@ -0,0 +1,46 @@
+use std::net::IpAddr;
+
+use time::OffsetDateTime;
+
+use crate::types::{AuditAction, AuditEntry, InfoHash};
+
+use super::Error;
+
+/// Who made an audited change. Commands which change state on behalf of an
+/// admin carry one, and record it in the same transaction as the change.
+#[derive(Debug, Clone, Copy)]
+pub struct Actor {
+    /// The id of the API key the change was made with.
+    pub api_key_id: i64,
+    pub ip: IpAddr,
+}
+
+/// Record a change made outside the database, which cannot share a
+/// transaction with its audit entry.
+#[derive(Debug, Clone)]
+pub struct AppendAuditEntry<'a> {
+    pub actor: Actor,
+    pub action: AuditAction,
+    pub target_id: Option<i64>,
+    pub details: Option<&'a str>,
+}
+
+/// Filters for listing audit entries. Unset filters match everything.
+/// Entries are returned newest first, starting below `before_id`.
+#[derive(Debug, Clone)]
+pub struct ListAuditEntries<'a> {
+    pub actor: Option<i64>,
+    pub info_hash: Option<&'a InfoHash>,
+    pub since: Option<OffsetDateTime>,
+    pub until: Option<OffsetDateTime>,
+    pub before_id: Option<i64>,
+    pub limit: i64,
+}
+
+#[async_trait::async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn append_audit_entry(&self, cmd: AppendAuditEntry<'_>) -> Result<(), Error>;
+
+    async fn list_audit_entries(&self, cmd: ListAuditEntries<'_>)
+        -> Result<Vec<AuditEntry>, Error>;
+}
@@ -0,0 +1,36 @@
+use time::OffsetDateTime;
+
+use crate::types::{Ban, BanRule};
+
+use super::{audit::Actor, Error};
+
+#[derive(Debug, Clone)]
+pub struct CreateBan<'a> {
+    pub rule: &'a BanRule,
+    pub reason: &'a str,
+    pub expires_ts: Option<OffsetDateTime>,
+    pub actor: Actor,
+}
+
+/// List bans that have not expired by `active_at`, or every ban if it is
+/// `None`.
+#[derive(Debug, Clone)]
+pub struct ListBans {
+    pub active_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteBan {
+    pub id: i64,
+    pub actor: Actor,
+}
+
+#[async_trait::async_trait]
+pub trait BanRepository: Send + Sync {
+    async fn create_ban(&self, cmd: CreateBan<'_>) -> Result<Ban, Error>;
+
+    async fn list_bans(&self, cmd: ListBans) -> Result<Vec<Ban>, Error>;
+
+    /// Delete a ban, returning whether it existed.
+    async fn delete_ban(&self, cmd: DeleteBan) -> Result<bool, Error>;
+}
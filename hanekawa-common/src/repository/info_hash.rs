@@ -1,6 +1,10 @@
-use crate::types::{InfoHash, InfoHashStatus, InfoHashSummary};
+use std::collections::HashMap;
 
-use super::Error;
+use crate::types::{
+    AuditAction, InfoHash, InfoHashStatus, InfoHashSummary, TorrentFlags, TorrentMetadata,
+};
+
+use super::{audit::Actor, Error};
 
 #[derive(Debug, Clone)]
 pub struct GetInfoHashSummary<'a> {
@@ -13,6 +17,52 @@ pub struct UpdateInfoHash<'a> {
     pub status: InfoHashStatus,
 }
 
+/// Set the status of many info hashes at once, recording each change in
+/// the audit log.
+#[derive(Debug, Clone)]
+pub struct UpdateInfoHashes<'a> {
+    pub info_hashes: &'a [InfoHash],
+    pub status: InfoHashStatus,
+    pub action: AuditAction,
+    pub actor: Actor,
+}
+
+/// List known info hashes ordered by hash, starting after `after`. A
+/// `limit` of `None` returns every match.
+#[derive(Debug, Clone)]
+pub struct ListInfoHashes<'a> {
+    pub status: Option<InfoHashStatus>,
+    pub after: Option<&'a InfoHash>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetTorrentFlags<'a> {
+    pub info_hash: &'a InfoHash,
+}
+
+#[derive(Debug, Clone)]
+pub struct SetTorrentFlags<'a> {
+    pub info_hash: &'a InfoHash,
+    pub flags: TorrentFlags,
+    pub actor: Actor,
+}
+
+/// Set the status of and record the metadata of an uploaded torrent under
+/// each of its info hashes, auditing the status changes.
+#[derive(Debug, Clone)]
+pub struct RegisterTorrent<'a> {
+    pub info_hashes: &'a [InfoHash],
+    pub metadata: &'a TorrentMetadata,
+    pub status: InfoHashStatus,
+    pub actor: Actor,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetTorrentNames<'a> {
+    pub info_hashes: &'a [InfoHash],
+}
+
 #[async_trait::async_trait]
 pub trait InfoHashRepository: Send + Sync {
     async fn get_info_hash_summary(
@@ -21,4 +71,28 @@ pub trait InfoHashRepository: Send + Sync {
     ) -> Result<InfoHashSummary, Error>;
 
     async fn update_info_hash(&self, cmd: UpdateInfoHash<'_>) -> Result<(), Error>;
+
+    /// Returns the status each distinct info hash had before the update.
+    async fn update_info_hashes(
+        &self,
+        cmd: UpdateInfoHashes<'_>,
+    ) -> Result<Vec<InfoHashSummary>, Error>;
+
+    async fn list_info_hashes(
+        &self,
+        cmd: ListInfoHashes<'_>,
+    ) -> Result<Vec<InfoHashSummary>, Error>;
+
+    async fn get_torrent_flags(&self, cmd: GetTorrentFlags<'_>) -> Result<TorrentFlags, Error>;
+
+    async fn set_torrent_flags(&self, cmd: SetTorrentFlags<'_>) -> Result<(), Error>;
+
+    async fn register_torrent(&self, cmd: RegisterTorrent<'_>) -> Result<(), Error>;
+
+    /// Returns the names of those info hashes that belong to an uploaded
+    /// torrent.
+    async fn get_torrent_names(
+        &self,
+        cmd: GetTorrentNames<'_>,
+    ) -> Result<HashMap<InfoHash, String>, Error>;
 }
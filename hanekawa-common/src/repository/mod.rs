@@ -1,5 +1,11 @@
+pub mod api_key;
+pub mod audit;
+pub mod ban;
 pub mod info_hash;
 pub mod peer;
+pub mod schedule;
+pub mod seeding;
+pub mod user;
 
 #[derive(Debug)]
 pub enum Error {}
@@ -1,4 +1,6 @@
-use crate::types::{Event, InfoHash, Peer, PeerId, PeerStatistics};
+use crate::types::{
+    Event, InfoHash, Peer, PeerDetails, PeerId, PeerStatistics, TorrentSort, TorrentSummary,
+};
 use std::collections::HashMap;
 use std::net::IpAddr;
 
@@ -17,6 +19,9 @@ pub struct UpdatePeerAnnounce {
     pub left: u64,
     pub event: Event,
     pub update_timestamp: OffsetDateTime,
+    /// The user the announce is attributed to, in private mode.
+    #[serde(default)]
+    pub user_id: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +36,33 @@ pub struct GetPeerStatistics<'a> {
     pub active_after: OffsetDateTime,
 }
 
+#[derive(Debug, Clone)]
+pub struct PurgePeers {
+    pub inactive_before: OffsetDateTime,
+}
+
+/// List torrents with peers active after `active_after`.
+#[derive(Debug, Clone)]
+pub struct ListTorrents {
+    pub active_after: OffsetDateTime,
+    pub sort: TorrentSort,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetPeerDetails<'a> {
+    pub info_hash: &'a InfoHash,
+    pub active_after: OffsetDateTime,
+}
+
+/// Count distinct peers active after `active_after`, grouped by the first
+/// bytes of their peer IDs, which identify the client.
+#[derive(Debug, Clone)]
+pub struct CountPeerIdPrefixes {
+    pub active_after: OffsetDateTime,
+}
+
 #[async_trait::async_trait]
 pub trait PeerRepository: Send + Sync {
     async fn update_peer_announce(&self, cmd: &UpdatePeerAnnounce) -> Result<(), Error>;
@@ -39,4 +71,11 @@ pub trait PeerRepository: Send + Sync {
         &self,
         cmd: GetPeerStatistics<'_>,
     ) -> Result<HashMap<InfoHash, PeerStatistics>, Error>;
+    async fn purge_peers(&self, cmd: PurgePeers) -> Result<u64, Error>;
+    async fn list_torrents(&self, cmd: ListTorrents) -> Result<Vec<TorrentSummary>, Error>;
+    async fn get_peer_details(&self, cmd: GetPeerDetails<'_>) -> Result<Vec<PeerDetails>, Error>;
+    async fn count_peer_id_prefixes(
+        &self,
+        cmd: CountPeerIdPrefixes,
+    ) -> Result<Vec<(PeerId, u64)>, Error>;
 }
@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+use super::Error;
+
+#[derive(Debug, Clone)]
+pub struct ClaimScheduledRun<'a> {
+    pub name: &'a str,
+    pub every: Duration,
+}
+
+#[async_trait::async_trait]
+pub trait ScheduleRepository: Send + Sync {
+    /// Claim the current run of a scheduled job, returning whether the
+    /// caller should fire it.
+    async fn claim_scheduled_run(&self, cmd: ClaimScheduledRun<'_>) -> Result<bool, Error>;
+}
@@ -0,0 +1,34 @@
+use crate::types::{InfoHash, ObligationStatus, SeedingObligation};
+
+use time::OffsetDateTime;
+
+use super::Error;
+
+/// List seeding obligations, counting sessions with announces after
+/// `active_after` as still seeding.
+#[derive(Debug, Clone)]
+pub struct ListSeedingObligations {
+    pub user_id: Option<i64>,
+    pub status: Option<ObligationStatus>,
+    pub active_after: OffsetDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolveSeedingObligations<'a> {
+    pub resolutions: &'a [(i64, InfoHash, ObligationStatus)],
+    pub resolved_ts: OffsetDateTime,
+}
+
+#[async_trait::async_trait]
+pub trait SeedingRepository: Send + Sync {
+    async fn list_seeding_obligations(
+        &self,
+        cmd: ListSeedingObligations,
+    ) -> Result<Vec<SeedingObligation>, Error>;
+
+    /// Set the status of pending obligations, leaving resolved ones alone.
+    async fn resolve_seeding_obligations(
+        &self,
+        cmd: ResolveSeedingObligations<'_>,
+    ) -> Result<u64, Error>;
+}
@@ -0,0 +1,43 @@
+use crate::types::{User, UserStats};
+
+use super::{audit::Actor, Error};
+
+#[derive(Debug, Clone)]
+pub struct CreateUser<'a> {
+    pub name: &'a str,
+    pub passkey: &'a str,
+    pub actor: Actor,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetUserByPasskey<'a> {
+    pub passkey: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokePasskey {
+    pub user_id: i64,
+    pub actor: Actor,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetUserStats {
+    pub user_id: i64,
+}
+
+#[async_trait::async_trait]
+pub trait UserRepository: Send + Sync {
+    /// Create a user, returning `None` if the name is already taken.
+    async fn create_user(&self, cmd: CreateUser<'_>) -> Result<Option<User>, Error>;
+
+    async fn get_user_by_passkey(&self, cmd: GetUserByPasskey<'_>) -> Result<Option<User>, Error>;
+
+    async fn list_users(&self) -> Result<Vec<User>, Error>;
+
+    /// Revoke a user's passkey, returning whether an active passkey was
+    /// revoked.
+    async fn revoke_passkey(&self, cmd: RevokePasskey) -> Result<bool, Error>;
+
+    /// A user's transfer totals, or `None` if the user does not exist.
+    async fn get_user_stats(&self, cmd: GetUserStats) -> Result<Option<UserStats>, Error>;
+}
@@ -1,3 +1,7 @@
+use std::{sync::Arc, time::Duration};
+
+use time::OffsetDateTime;
+
 #[typetag::serde(tag = "type")]
 #[async_trait::async_trait]
 pub trait Task: Send + Sync {
@@ -7,4 +11,104 @@ pub trait Task: Send + Sync {
 #[async_trait::async_trait]
 pub trait TaskQueue: Send + Sync {
     async fn enqueue(&self, task: &dyn Task) -> Option<()>;
+
+    /// Enqueue a task which will not be executed before `at`.
+    async fn enqueue_at(&self, task: &dyn Task, at: OffsetDateTime) -> Option<()>;
+
+    /// Enqueue a task which will not be executed until `delay` has passed.
+    async fn enqueue_after(&self, task: &dyn Task, delay: Duration) -> Option<()> {
+        self.enqueue_at(task, OffsetDateTime::now_utc() + delay)
+            .await
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeadLetter {
+    pub payload: String,
+    pub attempts: u32,
+    pub reason: String,
+}
+
+#[async_trait::async_trait]
+pub trait DeadLetterQueue: Send + Sync {
+    /// Returns `None` if the queue cannot look up `limit` letters at once.
+    async fn dead_letters(&self, limit: usize) -> Option<Vec<DeadLetter>>;
+
+    /// Returns how many letters were replayed, or `None` if the queue
+    /// cannot look up `limit` letters at once.
+    async fn replay_dead_letters(&self, limit: usize) -> Option<usize>;
+}
+
+#[derive(Clone)]
+pub struct ScheduledJob {
+    pub name: String,
+    pub every: Duration,
+    pub task: Arc<dyn Task>,
+}
+
+/// Recurring tasks, enqueued by whichever node claims each run first.
+#[derive(Clone, Default)]
+pub struct ScheduleRegistry {
+    jobs: Vec<ScheduledJob>,
+}
+
+impl ScheduleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn every(
+        mut self,
+        name: impl Into<String>,
+        every: Duration,
+        task: impl Task + 'static,
+    ) -> Self {
+        self.jobs.push(ScheduledJob {
+            name: name.into(),
+            every,
+            task: Arc::new(task),
+        });
+        self
+    }
+
+    pub fn jobs(&self) -> &[ScheduledJob] {
+        &self.jobs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct NoopTask;
+
+    #[typetag::serde]
+    #[async_trait::async_trait]
+    impl Task for NoopTask {
+        async fn execute(&self, _ctx: &crate::Services) -> Option<()> {
+            Some(())
+        }
+    }
+
+    #[test]
+    fn registers_jobs_in_order() {
+        let registry = ScheduleRegistry::new()
+            .every("first", Duration::from_secs(60), NoopTask)
+            .every("second", Duration::from_secs(5), NoopTask);
+
+        let jobs: Vec<_> = registry
+            .jobs()
+            .iter()
+            .map(|job| (job.name.as_str(), job.every))
+            .collect();
+        assert_eq!(
+            vec![
+                ("first", Duration::from_secs(60)),
+                ("second", Duration::from_secs(5))
+            ],
+            jobs
+        );
+        assert!(ScheduleRegistry::new().jobs().is_empty());
+    }
 }
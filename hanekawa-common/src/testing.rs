@@ -0,0 +1,436 @@
+//! In-memory repositories and task queues, so services can be tested
+//! without a database or message broker. Only what tests exercise is
+//! implemented; anything else panics.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use time::OffsetDateTime;
+
+use crate::repository::{
+    api_key::{ApiKeyRepository, CreateApiKey, EnsureApiKey, GetApiKey, RevokeApiKey},
+    audit::{AppendAuditEntry, AuditRepository, ListAuditEntries},
+    ban::{BanRepository, CreateBan, DeleteBan, ListBans},
+    info_hash::{
+        GetInfoHashSummary, GetTorrentFlags, GetTorrentNames, InfoHashRepository, ListInfoHashes,
+        RegisterTorrent, SetTorrentFlags, UpdateInfoHash, UpdateInfoHashes,
+    },
+    peer::{
+        CountPeerIdPrefixes, GetPeerDetails, GetPeerStatistics, GetPeers, ListTorrents,
+        PeerRepository, PurgePeers, UpdatePeerAnnounce,
+    },
+    schedule::{ClaimScheduledRun, ScheduleRepository},
+    seeding::{ListSeedingObligations, ResolveSeedingObligations, SeedingRepository},
+    user::{CreateUser, GetUserByPasskey, GetUserStats, RevokePasskey, UserRepository},
+    Error,
+};
+use crate::task::{DeadLetter, DeadLetterQueue, Task, TaskQueue};
+use crate::types::{
+    ApiKey, ApiKeyScope, AuditAction, AuditEntry, Ban, InfoHash, InfoHashStatus, InfoHashSummary,
+    Peer, PeerDetails, PeerId, PeerStatistics, SeedingObligation, TorrentFlags, TorrentMetadata,
+    TorrentSummary, User, UserStats,
+};
+use crate::{Config, Services};
+
+/// The default configuration, as if loaded without a config file.
+pub fn config() -> Config {
+    let mut config = serde_json::to_value(Config::default_config()).unwrap();
+    config["database_url"] = "postgres://localhost/hanekawa".into();
+
+    serde_json::from_value(config).unwrap()
+}
+
+#[derive(Debug, Default)]
+pub struct State {
+    /// API keys and the hashes of their tokens.
+    pub api_keys: Vec<(ApiKey, Vec<u8>)>,
+    /// Stored info hashes, and whether each is allowed.
+    pub info_hashes: HashMap<InfoHash, bool>,
+    /// The metadata of uploaded torrents, under each of their info hashes.
+    pub torrents: HashMap<InfoHash, TorrentMetadata>,
+    /// Audited actions and their targets, oldest first.
+    pub audit_log: Vec<(AuditAction, Option<i64>)>,
+    /// When each scheduled job may next run.
+    pub scheduled_runs: HashMap<String, OffsetDateTime>,
+    pub users: Vec<User>,
+    /// Enqueued tasks, serialized as JSON.
+    pub tasks: Vec<String>,
+}
+
+/// Every repository and queue, backed by one shared [`State`].
+#[derive(Debug, Default)]
+pub struct Memory {
+    state: Mutex<State>,
+}
+
+impl Memory {
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    pub fn services(self: &Arc<Self>) -> Services {
+        Services {
+            api_key_repository: self.clone(),
+            audit_repository: self.clone(),
+            ban_repository: self.clone(),
+            peer_repository: self.clone(),
+            info_hash_repository: self.clone(),
+            schedule_repository: self.clone(),
+            seeding_repository: self.clone(),
+            user_repository: self.clone(),
+            task_queue: self.clone(),
+            dead_letter_queue: self.clone(),
+        }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl State {
+    fn insert_api_key(&mut self, name: &str, scope: ApiKeyScope, key_hash: &[u8]) -> ApiKey {
+        let api_key = ApiKey {
+            id: self.api_keys.len() as i64 + 1,
+            name: name.to_owned(),
+            scope,
+            created_ts: OffsetDateTime::now_utc(),
+            revoked_ts: None,
+        };
+        self.api_keys.push((api_key.clone(), key_hash.to_vec()));
+
+        api_key
+    }
+
+    fn audit(&mut self, action: AuditAction, target_id: Option<i64>) {
+        self.audit_log.push((action, target_id));
+    }
+
+    fn update_info_hashes(
+        &mut self,
+        info_hashes: &[InfoHash],
+        status: &InfoHashStatus,
+        action: AuditAction,
+    ) -> Vec<InfoHashSummary> {
+        let mut previous: Vec<InfoHashSummary> = vec![];
+
+        for info_hash in info_hashes {
+            if previous.iter().any(|s| s.info_hash == *info_hash) {
+                continue;
+            }
+
+            let status = match status {
+                InfoHashStatus::ExplicitAllow => self.info_hashes.insert(info_hash.clone(), true),
+                InfoHashStatus::ExplicitDeny => self.info_hashes.insert(info_hash.clone(), false),
+                InfoHashStatus::Unknown => self.info_hashes.remove(info_hash),
+            };
+            previous.push(InfoHashSummary {
+                info_hash: info_hash.clone(),
+                status: match status {
+                    Some(true) => InfoHashStatus::ExplicitAllow,
+                    Some(false) => InfoHashStatus::ExplicitDeny,
+                    None => InfoHashStatus::Unknown,
+                },
+                name: None,
+            });
+            self.audit(action, None);
+        }
+
+        previous
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyRepository for Memory {
+    async fn create_api_key(&self, cmd: CreateApiKey<'_>) -> Result<ApiKey, Error> {
+        let mut state = self.state();
+        let api_key = state.insert_api_key(cmd.name, cmd.scope, cmd.key_hash);
+        state.audit(AuditAction::CreateApiKey, Some(api_key.id));
+
+        Ok(api_key)
+    }
+
+    async fn ensure_api_key(&self, cmd: EnsureApiKey<'_>) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.api_keys.iter().any(|(_, hash)| hash == cmd.key_hash) {
+            state.insert_api_key(cmd.name, cmd.scope, cmd.key_hash);
+        }
+
+        Ok(())
+    }
+
+    async fn get_api_key(&self, cmd: GetApiKey<'_>) -> Result<Option<ApiKey>, Error> {
+        Ok(self
+            .state()
+            .api_keys
+            .iter()
+            .find(|(_, hash)| hash == cmd.key_hash)
+            .map(|(api_key, _)| api_key.clone()))
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, Error> {
+        Ok(self
+            .state()
+            .api_keys
+            .iter()
+            .map(|(api_key, _)| api_key.clone())
+            .collect())
+    }
+
+    async fn revoke_api_key(&self, cmd: RevokeApiKey) -> Result<bool, Error> {
+        let mut state = self.state();
+        let api_key = state
+            .api_keys
+            .iter_mut()
+            .map(|(api_key, _)| api_key)
+            .find(|api_key| api_key.id == cmd.id && api_key.revoked_ts.is_none());
+
+        match api_key {
+            Some(api_key) => api_key.revoked_ts = Some(OffsetDateTime::now_utc()),
+            None => return Ok(false),
+        }
+        state.audit(AuditAction::RevokeApiKey, Some(cmd.id));
+
+        Ok(true)
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for Memory {
+    async fn append_audit_entry(&self, cmd: AppendAuditEntry<'_>) -> Result<(), Error> {
+        self.state().audit(cmd.action, cmd.target_id);
+
+        Ok(())
+    }
+
+    async fn list_audit_entries(
+        &self,
+        _cmd: ListAuditEntries<'_>,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        unimplemented!()
+    }
+}
+
+#[async_trait::async_trait]
+impl BanRepository for Memory {
+    async fn create_ban(&self, _cmd: CreateBan<'_>) -> Result<Ban, Error> {
+        unimplemented!()
+    }
+
+    async fn list_bans(&self, _cmd: ListBans) -> Result<Vec<Ban>, Error> {
+        unimplemented!()
+    }
+
+    async fn delete_ban(&self, _cmd: DeleteBan) -> Result<bool, Error> {
+        unimplemented!()
+    }
+}
+
+#[async_trait::async_trait]
+impl PeerRepository for Memory {
+    async fn update_peer_announce(&self, _cmd: &UpdatePeerAnnounce) -> Result<(), Error> {
+        unimplemented!()
+    }
+
+    async fn get_peers(&self, _cmd: GetPeers<'_>) -> Result<Vec<Peer>, Error> {
+        unimplemented!()
+    }
+
+    async fn get_peer_statistics(
+        &self,
+        _cmd: GetPeerStatistics<'_>,
+    ) -> Result<HashMap<InfoHash, PeerStatistics>, Error> {
+        unimplemented!()
+    }
+
+    async fn purge_peers(&self, _cmd: PurgePeers) -> Result<u64, Error> {
+        unimplemented!()
+    }
+
+    async fn list_torrents(&self, _cmd: ListTorrents) -> Result<Vec<TorrentSummary>, Error> {
+        unimplemented!()
+    }
+
+    async fn get_peer_details(&self, _cmd: GetPeerDetails<'_>) -> Result<Vec<PeerDetails>, Error> {
+        unimplemented!()
+    }
+
+    async fn count_peer_id_prefixes(
+        &self,
+        _cmd: CountPeerIdPrefixes,
+    ) -> Result<Vec<(PeerId, u64)>, Error> {
+        unimplemented!()
+    }
+}
+
+#[async_trait::async_trait]
+impl InfoHashRepository for Memory {
+    async fn get_info_hash_summary(
+        &self,
+        _cmd: GetInfoHashSummary<'_>,
+    ) -> Result<InfoHashSummary, Error> {
+        unimplemented!()
+    }
+
+    async fn update_info_hash(&self, _cmd: UpdateInfoHash<'_>) -> Result<(), Error> {
+        unimplemented!()
+    }
+
+    async fn update_info_hashes(
+        &self,
+        cmd: UpdateInfoHashes<'_>,
+    ) -> Result<Vec<InfoHashSummary>, Error> {
+        Ok(self
+            .state()
+            .update_info_hashes(cmd.info_hashes, &cmd.status, cmd.action))
+    }
+
+    async fn list_info_hashes(
+        &self,
+        _cmd: ListInfoHashes<'_>,
+    ) -> Result<Vec<InfoHashSummary>, Error> {
+        unimplemented!()
+    }
+
+    async fn get_torrent_flags(&self, _cmd: GetTorrentFlags<'_>) -> Result<TorrentFlags, Error> {
+        unimplemented!()
+    }
+
+    async fn set_torrent_flags(&self, _cmd: SetTorrentFlags<'_>) -> Result<(), Error> {
+        unimplemented!()
+    }
+
+    async fn register_torrent(&self, cmd: RegisterTorrent<'_>) -> Result<(), Error> {
+        let mut state = self.state();
+        state.update_info_hashes(cmd.info_hashes, &cmd.status, AuditAction::UploadTorrent);
+        for info_hash in cmd.info_hashes {
+            state
+                .torrents
+                .insert(info_hash.clone(), cmd.metadata.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn get_torrent_names(
+        &self,
+        _cmd: GetTorrentNames<'_>,
+    ) -> Result<HashMap<InfoHash, String>, Error> {
+        unimplemented!()
+    }
+}
+
+#[async_trait::async_trait]
+impl ScheduleRepository for Memory {
+    async fn claim_scheduled_run(&self, cmd: ClaimScheduledRun<'_>) -> Result<bool, Error> {
+        let now = OffsetDateTime::now_utc();
+        let mut state = self.state();
+
+        match state.scheduled_runs.get(cmd.name) {
+            Some(next_run) if *next_run > now => Ok(false),
+            _ => {
+                state
+                    .scheduled_runs
+                    .insert(cmd.name.to_owned(), now + cmd.every);
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SeedingRepository for Memory {
+    async fn list_seeding_obligations(
+        &self,
+        _cmd: ListSeedingObligations,
+    ) -> Result<Vec<SeedingObligation>, Error> {
+        unimplemented!()
+    }
+
+    async fn resolve_seeding_obligations(
+        &self,
+        _cmd: ResolveSeedingObligations<'_>,
+    ) -> Result<u64, Error> {
+        unimplemented!()
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for Memory {
+    async fn create_user(&self, cmd: CreateUser<'_>) -> Result<Option<User>, Error> {
+        let mut state = self.state();
+        if state.users.iter().any(|u| u.name == cmd.name) {
+            return Ok(None);
+        }
+
+        let user = User {
+            id: state.users.len() as i64 + 1,
+            name: cmd.name.to_owned(),
+            passkey: cmd.passkey.to_owned(),
+            created_ts: OffsetDateTime::now_utc(),
+            revoked_ts: None,
+        };
+        state.users.push(user.clone());
+        state.audit(AuditAction::CreateUser, Some(user.id));
+
+        Ok(Some(user))
+    }
+
+    async fn get_user_by_passkey(&self, cmd: GetUserByPasskey<'_>) -> Result<Option<User>, Error> {
+        Ok(self
+            .state()
+            .users
+            .iter()
+            .find(|u| u.passkey == cmd.passkey)
+            .cloned())
+    }
+
+    async fn list_users(&self) -> Result<Vec<User>, Error> {
+        unimplemented!()
+    }
+
+    async fn revoke_passkey(&self, cmd: RevokePasskey) -> Result<bool, Error> {
+        let mut state = self.state();
+        let user = state
+            .users
+            .iter_mut()
+            .find(|u| u.id == cmd.user_id && u.revoked_ts.is_none());
+
+        match user {
+            Some(user) => user.revoked_ts = Some(OffsetDateTime::now_utc()),
+            None => return Ok(false),
+        }
+        state.audit(AuditAction::RevokePasskey, Some(cmd.user_id));
+
+        Ok(true)
+    }
+
+    async fn get_user_stats(&self, _cmd: GetUserStats) -> Result<Option<UserStats>, Error> {
+        unimplemented!()
+    }
+}
+
+#[async_trait::async_trait]
+impl TaskQueue for Memory {
+    async fn enqueue(&self, task: &dyn Task) -> Option<()> {
+        let payload = serde_json::to_string(task).unwrap();
+        self.state().tasks.push(payload);
+
+        Some(())
+    }
+
+    async fn enqueue_at(&self, task: &dyn Task, _at: OffsetDateTime) -> Option<()> {
+        self.enqueue(task).await
+    }
+}
+
+#[async_trait::async_trait]
+impl DeadLetterQueue for Memory {
+    async fn dead_letters(&self, _limit: usize) -> Option<Vec<DeadLetter>> {
+        Some(vec![])
+    }
+
+    async fn replay_dead_letters(&self, _limit: usize) -> Option<usize> {
+        Some(0)
+    }
+}
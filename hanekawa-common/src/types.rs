@@ -1,17 +1,48 @@
 use std::net::IpAddr;
 
+use time::OffsetDateTime;
+
+use crate::client::Client;
+use crate::metainfo::Version;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(transparent)]
 pub struct PeerId(#[serde(with = "serde_bytes")] pub Vec<u8>);
 
+impl PeerId {
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    /// The client that generated this peer ID, if it follows a known
+    /// convention.
+    pub fn client(&self) -> Option<Client> {
+        Client::from_peer_id(self)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[serde(transparent)]
 pub struct InfoHash(#[serde(with = "serde_bytes")] pub Vec<u8>);
 
 impl InfoHash {
-    pub fn from_hex(s: impl AsRef<str>) -> Self {
-        let bytes = hex::decode(s.as_ref()).unwrap();
-        Self(bytes)
+    /// The length of a SHA-1 (BitTorrent v1) info hash.
+    pub const V1_LEN: usize = 20;
+    /// The length of a SHA-256 (BitTorrent v2) info hash.
+    pub const V2_LEN: usize = 32;
+
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Result<Self, InfoHashError> {
+        let bytes = bytes.into();
+
+        match bytes.len() {
+            Self::V1_LEN | Self::V2_LEN => Ok(Self(bytes)),
+            len => Err(InfoHashError::InvalidLength(len)),
+        }
+    }
+
+    pub fn from_hex(s: impl AsRef<str>) -> Result<Self, InfoHashError> {
+        let bytes = hex::decode(s.as_ref()).map_err(|_| InfoHashError::InvalidHex)?;
+        Self::from_bytes(bytes)
     }
 
     pub fn to_hex(&self) -> String {
@@ -19,6 +50,28 @@ impl InfoHash {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InfoHashError {
+    InvalidHex,
+    InvalidLength(usize),
+}
+
+impl std::fmt::Display for InfoHashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHex => f.write_str("info hash is not valid hex"),
+            Self::InvalidLength(len) => f.write_fmt(format_args!(
+                "info hash must be {} or {} bytes, got {}",
+                InfoHash::V1_LEN,
+                InfoHash::V2_LEN,
+                len
+            )),
+        }
+    }
+}
+
+impl std::error::Error for InfoHashError {}
+
 #[derive(Debug, PartialEq, Eq, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Event {
@@ -34,6 +87,20 @@ impl Default for Event {
     }
 }
 
+impl std::str::FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "started" => Ok(Self::Started),
+            "completed" => Ok(Self::Completed),
+            "stopped" => Ok(Self::Stopped),
+            "interval" => Ok(Self::Interval),
+            _ => Err(format!("unknown event: {s}")),
+        }
+    }
+}
+
 impl ToString for Event {
     fn to_string(&self) -> String {
         match self {
@@ -60,15 +127,565 @@ pub struct PeerStatistics {
     pub incomplete: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InfoHashStatus {
     Unknown,
     ExplicitAllow,
     ExplicitDeny,
 }
 
-#[derive(Debug)]
+impl InfoHashStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::ExplicitAllow => "explicit_allow",
+            Self::ExplicitDeny => "explicit_deny",
+        }
+    }
+}
+
+impl std::str::FromStr for InfoHashStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unknown" => Ok(Self::Unknown),
+            "explicit_allow" => Ok(Self::ExplicitAllow),
+            "explicit_deny" => Ok(Self::ExplicitDeny),
+            _ => Err(format!("unknown info hash status: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct InfoHashSummary {
+    #[serde(serialize_with = "serialize_hex")]
     pub info_hash: InfoHash,
     pub status: InfoHashStatus,
+    /// The name of the torrent, if it was uploaded. Only filled in when
+    /// listing info hashes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// What the tracker knows about an uploaded `.torrent` file.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TorrentMetadata {
+    pub name: String,
+    pub total_length: u64,
+    pub file_count: u64,
+    pub private: bool,
+    pub version: Version,
+}
+
+/// Adjustments to how transfers on a torrent count towards users' ratios.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TorrentFlags {
+    /// Downloads are not counted.
+    pub freeleech: bool,
+    /// Uploads count twice.
+    pub double_upload: bool,
+}
+
+impl TorrentFlags {
+    /// The uploaded and downloaded bytes credited to a user.
+    pub fn credit(&self, uploaded: u64, downloaded: u64) -> (u64, u64) {
+        let uploaded = if self.double_upload {
+            uploaded.saturating_mul(2)
+        } else {
+            uploaded
+        };
+        let downloaded = if self.freeleech { 0 } else { downloaded };
+
+        (uploaded, downloaded)
+    }
+}
+
+/// The permissions of an admin API key. `Write` implies `Read`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    Read,
+    Write,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiKeyScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            _ => Err(format!("unknown api key scope: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub scope: ApiKeyScope,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_ts: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_ts: Option<OffsetDateTime>,
+}
+
+/// A tracker user, identified on announces by their passkey.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+    pub passkey: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_ts: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_ts: Option<OffsetDateTime>,
+}
+
+/// A user's transfer totals across all torrents, in bytes. The credited
+/// totals have torrent flags applied, and determine the user's ratio.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct UserStats {
+    pub uploaded: u64,
+    pub downloaded: u64,
+    pub credited_uploaded: u64,
+    pub credited_downloaded: u64,
+}
+
+impl UserStats {
+    /// `None` until the user has downloaded something.
+    pub fn ratio(&self) -> Option<f64> {
+        match self.credited_downloaded {
+            0 => None,
+            downloaded => Some(self.credited_uploaded as f64 / downloaded as f64),
+        }
+    }
+}
+
+/// Where a user stands on seeding a torrent they completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObligationStatus {
+    Pending,
+    Satisfied,
+    /// The user stopped seeding before meeting the requirements.
+    HitAndRun,
+}
+
+impl ObligationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Satisfied => "satisfied",
+            Self::HitAndRun => "hit_and_run",
+        }
+    }
+}
+
+impl std::str::FromStr for ObligationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "satisfied" => Ok(Self::Satisfied),
+            "hit_and_run" => Ok(Self::HitAndRun),
+            _ => Err(format!("unknown obligation status: {s}")),
+        }
+    }
+}
+
+/// A user's obligation to seed a torrent after completing it, with the
+/// seeding they have done since.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SeedingObligation {
+    pub user_id: i64,
+    #[serde(serialize_with = "serialize_hex")]
+    pub info_hash: InfoHash,
+    #[serde(with = "time::serde::rfc3339")]
+    pub completed_ts: OffsetDateTime,
+    pub status: ObligationStatus,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub resolved_ts: Option<OffsetDateTime>,
+    /// Seconds spent seeding since completion.
+    pub seeded_seconds: u64,
+    /// Whether the user has an active seeding session.
+    pub is_seeding: bool,
+    pub uploaded: u64,
+    pub downloaded: u64,
+}
+
+/// The kind of admin mutation recorded in an audit entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    UpdateInfoHash,
+    ImportInfoHashes,
+    UploadTorrent,
+    CreateApiKey,
+    RevokeApiKey,
+    ReplayDeadLetters,
+    CreateBan,
+    DeleteBan,
+    CreateUser,
+    RevokePasskey,
+    SetTorrentFlags,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UpdateInfoHash => "update_info_hash",
+            Self::ImportInfoHashes => "import_info_hashes",
+            Self::UploadTorrent => "upload_torrent",
+            Self::CreateApiKey => "create_api_key",
+            Self::RevokeApiKey => "revoke_api_key",
+            Self::ReplayDeadLetters => "replay_dead_letters",
+            Self::CreateBan => "create_ban",
+            Self::DeleteBan => "delete_ban",
+            Self::CreateUser => "create_user",
+            Self::RevokePasskey => "revoke_passkey",
+            Self::SetTorrentFlags => "set_torrent_flags",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "update_info_hash" => Ok(Self::UpdateInfoHash),
+            "import_info_hashes" => Ok(Self::ImportInfoHashes),
+            "upload_torrent" => Ok(Self::UploadTorrent),
+            "create_api_key" => Ok(Self::CreateApiKey),
+            "revoke_api_key" => Ok(Self::RevokeApiKey),
+            "replay_dead_letters" => Ok(Self::ReplayDeadLetters),
+            "create_ban" => Ok(Self::CreateBan),
+            "delete_ban" => Ok(Self::DeleteBan),
+            "create_user" => Ok(Self::CreateUser),
+            "revoke_passkey" => Ok(Self::RevokePasskey),
+            "set_torrent_flags" => Ok(Self::SetTorrentFlags),
+            _ => Err(format!("unknown audit action: {s}")),
+        }
+    }
+}
+
+/// One admin change. Info hash status changes fill in the info hash and
+/// statuses; other actions name the API key, ban or user they act on by
+/// `target_id`, and describe anything else in `details`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// The id of the API key that performed the action.
+    pub actor: i64,
+    pub action: AuditAction,
+    #[serde(
+        serialize_with = "serialize_optional_hex",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub info_hash: Option<InfoHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_status: Option<InfoHashStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_status: Option<InfoHashStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_ts: OffsetDateTime,
+    pub ip: IpAddr,
+}
+
+/// How to order torrents when listing swarms.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TorrentSort {
+    /// Most active peers first.
+    #[default]
+    Peers,
+    Seeders,
+    Leechers,
+    Snatches,
+    /// Most recently announced first.
+    LastAnnounce,
+}
+
+impl TorrentSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Peers => "peers",
+            Self::Seeders => "seeders",
+            Self::Leechers => "leechers",
+            Self::Snatches => "snatches",
+            Self::LastAnnounce => "last_announce",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TorrentSummary {
+    #[serde(serialize_with = "serialize_hex")]
+    pub info_hash: InfoHash,
+    pub seeders: u64,
+    pub leechers: u64,
+    pub snatches: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_announce_ts: OffsetDateTime,
+    /// The name of the torrent, if it was uploaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PeerDetails {
+    #[serde(serialize_with = "serialize_peer_id_hex")]
+    pub peer_id: PeerId,
+    pub client: Option<Client>,
+    pub ip: IpAddr,
+    pub port: u16,
+    pub uploaded: u64,
+    pub downloaded: u64,
+    pub left: u64,
+    pub event: Option<Event>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_announce_ts: OffsetDateTime,
+}
+
+/// An IPv4 or IPv6 network in CIDR notation. A bare address is parsed
+/// as a network containing only that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Build a network, clearing any host bits of `addr`.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, String> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        if prefix_len > max_len {
+            return Err(format!("invalid prefix length for {addr}: {prefix_len}"));
+        }
+
+        let addr = match addr {
+            IpAddr::V4(a) => {
+                let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+                IpAddr::V4((u32::from(a) & mask).into())
+            }
+            IpAddr::V6(a) => {
+                let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+                IpAddr::V6((u128::from(a) & mask).into())
+            }
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match Self::new(ip, self.prefix_len) {
+            Ok(network) => network.addr == self.addr,
+            Err(_) => false,
+        }
+    }
+}
+
+impl std::str::FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("invalid ip address: {addr}"))?;
+
+        let prefix_len = match prefix_len {
+            Some(len) => len
+                .parse()
+                .map_err(|_| format!("invalid prefix length: {len}"))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}/{}", self.addr, self.prefix_len))
+    }
+}
+
+impl serde::Serialize for Cidr {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Cidr {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What a ban matches on.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum BanRule {
+    /// A single address or a CIDR range.
+    Network(Cidr),
+    /// Peer IDs starting with this prefix, such as `-XL0012-`.
+    PeerId(String),
+}
+
+impl BanRule {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::PeerId(_) => "peer_id",
+        }
+    }
+
+    pub fn value(&self) -> String {
+        match self {
+            Self::Network(cidr) => cidr.to_string(),
+            Self::PeerId(prefix) => prefix.clone(),
+        }
+    }
+
+    pub fn from_parts(kind: &str, value: &str) -> Result<Self, String> {
+        match kind {
+            "network" => Ok(Self::Network(value.parse()?)),
+            "peer_id" => Ok(Self::PeerId(value.to_string())),
+            _ => Err(format!("unknown ban kind: {kind}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Ban {
+    pub id: i64,
+    #[serde(flatten)]
+    pub rule: BanRule,
+    pub reason: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_ts: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_ts: Option<OffsetDateTime>,
+}
+
+fn serialize_hex<S: serde::Serializer>(info_hash: &InfoHash, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&info_hash.to_hex())
+}
+
+fn serialize_optional_hex<S: serde::Serializer>(
+    info_hash: &Option<InfoHash>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match info_hash {
+        Some(info_hash) => serialize_hex(info_hash, s),
+        None => s.serialize_none(),
+    }
+}
+
+fn serialize_peer_id_hex<S: serde::Serializer>(peer_id: &PeerId, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&peer_id.to_hex())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_v1_and_v2_info_hashes() {
+        let v1 = InfoHash::from_hex("aa".repeat(20)).unwrap();
+        let v2 = InfoHash::from_hex("BB".repeat(32)).unwrap();
+
+        assert_eq!(vec![0xaa; 20], v1.0);
+        assert_eq!(vec![0xbb; 32], v2.0);
+    }
+
+    #[test]
+    fn rejects_invalid_info_hashes() {
+        assert_eq!(
+            InfoHashError::InvalidHex,
+            InfoHash::from_hex("nothex").unwrap_err()
+        );
+        assert_eq!(
+            InfoHashError::InvalidLength(3),
+            InfoHash::from_hex("aabbcc").unwrap_err()
+        );
+        assert_eq!(
+            InfoHashError::InvalidLength(0),
+            InfoHash::from_bytes(vec![]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn parses_and_matches_cidr_ranges() {
+        let network: Cidr = "10.1.2.3/16".parse().unwrap();
+
+        assert_eq!("10.1.0.0/16", network.to_string());
+        assert!(network.contains("10.1.255.1".parse().unwrap()));
+        assert!(!network.contains("10.2.0.1".parse().unwrap()));
+        assert!(!network.contains("::1".parse().unwrap()));
+
+        let single: Cidr = "2001:db8::1".parse().unwrap();
+        assert_eq!(128, single.prefix_len());
+        assert!(single.contains("2001:db8::1".parse().unwrap()));
+        assert!(!single.contains("2001:db8::2".parse().unwrap()));
+
+        let all: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains("192.0.2.1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("nonsense/8".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn credits_transfers_by_torrent_flags() {
+        let flags = |freeleech, double_upload| TorrentFlags {
+            freeleech,
+            double_upload,
+        };
+
+        assert_eq!((10, 20), flags(false, false).credit(10, 20));
+        assert_eq!((10, 0), flags(true, false).credit(10, 20));
+        assert_eq!((20, 20), flags(false, true).credit(10, 20));
+        assert_eq!((20, 0), flags(true, true).credit(10, 20));
+    }
 }
@@ -0,0 +1,421 @@
+use std::{sync::Arc, time::Duration};
+
+use hanekawa_common::{
+    task::{DeadLetter, DeadLetterQueue, Task, TaskQueue},
+    Config,
+};
+
+use time::OffsetDateTime;
+
+use futures::{stream::BoxStream, StreamExt};
+use lapin::{
+    options::{BasicAckOptions, BasicGetOptions, BasicNackOptions, BasicQosOptions},
+    protocol,
+    types::{AMQPValue, FieldTable, LongString},
+    BasicProperties, Connection,
+};
+
+use super::{Message, RetryPolicy};
+
+const TASKS_QUEUE: &str = "tasks";
+const DEAD_LETTER_QUEUE: &str = "tasks.dead";
+
+const ATTEMPTS_HEADER: &str = "x-attempts";
+const REASON_HEADER: &str = "x-reason";
+
+/// Delays are rounded up to whole seconds to bound the number of queues.
+fn delay_queue_secs(delay: Duration) -> u64 {
+    delay.as_secs() + u64::from(delay.subsec_nanos() > 0)
+}
+
+// Delayed messages wait in a queue with a fixed TTL which dead-letters them
+// back into the tasks queue. Each delay gets its own queue, as RabbitMQ only
+// expires messages at the head of a queue.
+async fn declare_delay_queue(chan: &lapin::Channel, delay: Duration) -> String {
+    let secs = delay_queue_secs(delay);
+    let ttl_ms = secs * 1000;
+
+    let mut arguments = FieldTable::default();
+    arguments.insert(
+        "x-message-ttl".into(),
+        AMQPValue::LongLongInt(ttl_ms as i64),
+    );
+    // Unused delay queues are removed. Every publish redeclares the queue,
+    // so it outlives any message in it.
+    arguments.insert(
+        "x-expires".into(),
+        AMQPValue::LongLongInt((2 * ttl_ms + 60_000) as i64),
+    );
+    arguments.insert(
+        "x-dead-letter-exchange".into(),
+        AMQPValue::LongString("".into()),
+    );
+    arguments.insert(
+        "x-dead-letter-routing-key".into(),
+        AMQPValue::LongString(TASKS_QUEUE.into()),
+    );
+
+    let name = format!("tasks.delay.{}", secs);
+    chan.queue_declare(&name, Default::default(), arguments)
+        .await
+        .unwrap();
+
+    name
+}
+
+fn get_attempts(properties: &BasicProperties) -> u32 {
+    let attempts = properties
+        .headers()
+        .as_ref()
+        .and_then(|h| h.inner().get(ATTEMPTS_HEADER).cloned());
+
+    match attempts {
+        Some(AMQPValue::LongUInt(n)) => n,
+        Some(AMQPValue::LongLongInt(n)) => n as u32,
+        Some(AMQPValue::LongInt(n)) => n as u32,
+        _ => 0,
+    }
+}
+
+fn get_reason(properties: &BasicProperties) -> String {
+    let reason = properties
+        .headers()
+        .as_ref()
+        .and_then(|h| h.inner().get(REASON_HEADER).cloned());
+
+    match reason {
+        Some(AMQPValue::LongString(s)) => s.to_string(),
+        _ => String::new(),
+    }
+}
+
+async fn publish(
+    chan: &lapin::Channel,
+    queue: &str,
+    payload: &[u8],
+    attempts: u32,
+    reason: Option<&str>,
+) {
+    let mut headers = FieldTable::default();
+    headers.insert(ATTEMPTS_HEADER.into(), AMQPValue::LongUInt(attempts));
+    if let Some(reason) = reason {
+        headers.insert(
+            REASON_HEADER.into(),
+            AMQPValue::LongString(LongString::from(reason)),
+        );
+    }
+
+    chan.basic_publish(
+        "",
+        queue,
+        Default::default(),
+        payload,
+        BasicProperties::default().with_headers(headers),
+    )
+    .await
+    .unwrap();
+}
+
+pub struct AmqpMessage {
+    content: Box<dyn Task>,
+    delivery: lapin::message::Delivery,
+    attempts: u32,
+    chan: lapin::Channel,
+    policy: RetryPolicy,
+}
+
+impl AmqpMessage {
+    pub fn content(&self) -> &dyn Task {
+        self.content.as_ref()
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub async fn ack(self, success: bool) {
+        if !success {
+            if self.policy.is_exhausted(self.attempts) {
+                publish(
+                    &self.chan,
+                    DEAD_LETTER_QUEUE,
+                    &self.delivery.data,
+                    self.attempts,
+                    Some("exceeded maximum attempts"),
+                )
+                .await;
+            } else {
+                let delay = self.policy.delay(self.attempts);
+                publish(
+                    &self.chan,
+                    &declare_delay_queue(&self.chan, delay).await,
+                    &self.delivery.data,
+                    self.attempts,
+                    None,
+                )
+                .await;
+            }
+        }
+
+        self.delivery.acker.ack(Default::default()).await.unwrap();
+    }
+}
+
+#[async_trait::async_trait]
+impl Message for AmqpMessage {
+    fn content(&self) -> &dyn Task {
+        AmqpMessage::content(self)
+    }
+
+    fn attempts(&self) -> u32 {
+        AmqpMessage::attempts(self)
+    }
+
+    async fn ack(self: Box<Self>, success: bool) {
+        AmqpMessage::ack(*self, success).await
+    }
+}
+
+#[derive(Clone)]
+pub struct AmqpConnection {
+    pub(crate) inner: Arc<Connection>,
+    pub(crate) policy: RetryPolicy,
+    pub(crate) prefetch: u16,
+}
+
+impl AmqpConnection {
+    pub async fn connect(cfg: &Config) -> AmqpConnection {
+        let url = cfg
+            .message_queue_url
+            .as_deref()
+            .expect("message_queue_url is required for the amqp task queue backend");
+
+        let conn = lapin::Connection::connect(url, Default::default())
+            .await
+            .unwrap();
+
+        let inner = Arc::new(conn);
+
+        AmqpConnection {
+            inner,
+            policy: RetryPolicy::from_config(cfg),
+            prefetch: cfg.task_prefetch,
+        }
+    }
+}
+
+pub struct AmqpTaskQueue {
+    chan: lapin::Channel,
+    conn: AmqpConnection,
+}
+
+impl AmqpTaskQueue {
+    pub async fn new(connection: AmqpConnection) -> Self {
+        let chan = connection.inner.create_channel().await.unwrap();
+
+        let s = Self {
+            chan,
+            conn: connection,
+        };
+
+        s.initialize_topology().await;
+        s
+    }
+
+    async fn initialize_topology(&self) {
+        let _tasks = self
+            .chan
+            .queue_declare(TASKS_QUEUE, Default::default(), Default::default())
+            .await
+            .unwrap();
+
+        let _dead = self
+            .chan
+            .queue_declare(DEAD_LETTER_QUEUE, Default::default(), Default::default())
+            .await
+            .unwrap();
+    }
+
+    pub async fn consume(&self) -> BoxStream<'static, AmqpMessage> {
+        self.chan
+            .basic_qos(self.conn.prefetch, BasicQosOptions::default())
+            .await
+            .unwrap();
+
+        let consumer = self
+            .chan
+            .basic_consume(TASKS_QUEUE, "", Default::default(), Default::default())
+            .await
+            .unwrap();
+
+        let chan = self.chan.clone();
+        let policy = self.conn.policy;
+
+        let stream = consumer.filter_map(move |delivery| {
+            let chan = chan.clone();
+
+            async move {
+                let delivery = delivery.unwrap();
+                let attempts = get_attempts(&delivery.properties) + 1;
+
+                match serde_json::from_slice::<Box<dyn Task>>(&delivery.data) {
+                    Ok(payload) => Some(AmqpMessage {
+                        content: payload,
+                        delivery,
+                        attempts,
+                        chan,
+                        policy,
+                    }),
+                    Err(e) => {
+                        // Undecodable payloads will never succeed, so skip
+                        // straight to the dead letter queue.
+                        let reason = format!("undecodable payload: {}", e);
+                        publish(
+                            &chan,
+                            DEAD_LETTER_QUEUE,
+                            &delivery.data,
+                            attempts,
+                            Some(&reason),
+                        )
+                        .await;
+                        delivery.acker.ack(Default::default()).await.unwrap();
+                        None
+                    }
+                }
+            }
+        });
+
+        Box::pin(stream)
+    }
+}
+
+#[async_trait::async_trait]
+impl TaskQueue for AmqpTaskQueue {
+    async fn enqueue(&self, task: &dyn hanekawa_common::task::Task) -> Option<()> {
+        let payload = serde_json::to_string(task).unwrap();
+
+        self.chan
+            .basic_publish(
+                "",
+                TASKS_QUEUE,
+                Default::default(),
+                payload.as_bytes(),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+        Some(())
+    }
+
+    async fn enqueue_at(&self, task: &dyn Task, at: OffsetDateTime) -> Option<()> {
+        let delay = at - OffsetDateTime::now_utc();
+        if !delay.is_positive() {
+            return self.enqueue(task).await;
+        }
+
+        let payload = serde_json::to_string(task).unwrap();
+        let queue = declare_delay_queue(&self.chan, delay.unsigned_abs()).await;
+
+        self.chan
+            .basic_publish(
+                "",
+                &queue,
+                Default::default(),
+                payload.as_bytes(),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+
+        Some(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DeadLetterQueue for AmqpTaskQueue {
+    async fn dead_letters(&self, limit: usize) -> Option<Vec<DeadLetter>> {
+        let chan = self.conn.inner.create_channel().await.unwrap();
+
+        let mut deliveries = Vec::new();
+        while deliveries.len() < limit {
+            let message = chan
+                .basic_get(DEAD_LETTER_QUEUE, BasicGetOptions::default())
+                .await
+                .unwrap();
+
+            match message {
+                Some(message) => deliveries.push(message.delivery),
+                None => break,
+            }
+        }
+
+        let mut dead_letters = Vec::with_capacity(deliveries.len());
+        for delivery in deliveries {
+            dead_letters.push(DeadLetter {
+                payload: String::from_utf8_lossy(&delivery.data).to_string(),
+                attempts: get_attempts(&delivery.properties),
+                reason: get_reason(&delivery.properties),
+            });
+
+            // Only peeking, so put the message back.
+            delivery
+                .acker
+                .nack(BasicNackOptions {
+                    requeue: true,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        chan.close(protocol::constants::REPLY_SUCCESS, "OK")
+            .await
+            .unwrap();
+
+        Some(dead_letters)
+    }
+
+    async fn replay_dead_letters(&self, limit: usize) -> Option<usize> {
+        let mut replayed = 0;
+
+        while replayed < limit {
+            let message = self
+                .chan
+                .basic_get(DEAD_LETTER_QUEUE, BasicGetOptions::default())
+                .await
+                .unwrap();
+
+            let message = match message {
+                Some(message) => message,
+                None => break,
+            };
+
+            publish(&self.chan, TASKS_QUEUE, &message.delivery.data, 0, None).await;
+            message
+                .delivery
+                .acker
+                .ack(BasicAckOptions::default())
+                .await
+                .unwrap();
+
+            replayed += 1;
+        }
+
+        Some(replayed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rounds_delays_up_to_whole_seconds() {
+        assert_eq!(0, delay_queue_secs(Duration::ZERO));
+        assert_eq!(1, delay_queue_secs(Duration::from_nanos(1)));
+        assert_eq!(1, delay_queue_secs(Duration::from_secs(1)));
+        assert_eq!(2, delay_queue_secs(Duration::from_millis(1001)));
+        assert_eq!(60, delay_queue_secs(Duration::from_secs(60)));
+    }
+}
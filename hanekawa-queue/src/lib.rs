@@ -1,144 +1,208 @@
-use std::sync::Arc;
+mod amqp;
+mod postgres;
+mod scheduler;
+
+pub use amqp::{AmqpConnection, AmqpMessage, AmqpTaskQueue};
+pub use postgres::{PgConnection, PgMessage, PgTaskQueue};
+pub use scheduler::Scheduler;
+
+use std::{sync::Arc, time::Duration};
 
 use hanekawa_common::{
-    task::{Task, TaskQueue},
-    Config, Services,
+    task::{DeadLetterQueue, Task, TaskQueue},
+    Config, Services, TaskQueueBackend,
 };
 
-use futures::{Stream, StreamExt};
-use lapin::{Channel, Connection};
+use futures::{
+    stream::{BoxStream, FuturesUnordered},
+    StreamExt,
+};
+use sqlx::postgres::PgPool;
 use tokio_util::sync::CancellationToken;
 
-pub struct AmqpMessage {
-    content: Box<dyn Task>,
-    delivery: lapin::message::Delivery,
-}
+/// A task delivered to a consumer, which must be acknowledged once processed.
+#[async_trait::async_trait]
+pub trait Message: Send {
+    fn content(&self) -> &dyn Task;
 
-impl AmqpMessage {
-    pub fn content(&self) -> &dyn Task {
-        self.content.as_ref()
-    }
+    /// The number of times this task has been delivered, including this delivery.
+    fn attempts(&self) -> u32;
 
-    pub async fn ack(self, success: bool) {
-        if success {
-            self.delivery.acker.ack(Default::default()).await.unwrap();
-        } else {
-            self.delivery.acker.nack(Default::default()).await.unwrap();
-        }
-    }
+    async fn ack(self: Box<Self>, success: bool);
 }
 
-#[derive(Clone)]
-pub struct QueueConnection {
-    inner: Arc<Connection>,
+/// Exponential backoff for retrying failed tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
 }
 
-impl QueueConnection {
-    pub async fn connect(cfg: &Config) -> QueueConnection {
-        let conn = lapin::Connection::connect(&cfg.message_queue_url, Default::default())
-            .await
-            .unwrap();
+impl RetryPolicy {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            max_attempts: cfg.task_max_attempts,
+            base_delay: Duration::from_secs(cfg.task_retry_base_delay as u64),
+            max_delay: Duration::from_secs(cfg.task_retry_max_delay as u64),
+        }
+    }
 
-        let inner = Arc::new(conn);
+    /// The delay before retrying a task which has failed `attempts` times.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(attempts.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
 
-        QueueConnection { inner }
+    /// Whether a task which has failed `attempts` times should be dead-lettered.
+    pub fn is_exhausted(&self, attempts: u32) -> bool {
+        attempts >= self.max_attempts
     }
 }
 
-pub struct AmqpTaskQueue {
-    chan: lapin::Channel,
+#[derive(Clone)]
+pub enum QueueConnection {
+    Amqp(AmqpConnection),
+    Postgres(PgConnection),
 }
 
-impl AmqpTaskQueue {
-    pub async fn new(connection: QueueConnection) -> Self {
-        let chan = connection.inner.create_channel().await.unwrap();
-
-        let s = Self { chan };
-
-        s.initialize_topology().await;
-        s
+impl QueueConnection {
+    pub async fn connect(cfg: &Config, pool: PgPool) -> QueueConnection {
+        match cfg.task_queue_backend {
+            TaskQueueBackend::Amqp => Self::Amqp(AmqpConnection::connect(cfg).await),
+            TaskQueueBackend::Postgres => Self::Postgres(PgConnection::new(cfg, pool)),
+        }
     }
 
-    async fn initialize_topology(&self) {
-        let _tasks = self
-            .chan
-            .queue_declare("tasks", Default::default(), Default::default())
-            .await
-            .unwrap();
+    pub async fn task_queue(&self) -> Arc<dyn TaskQueue> {
+        match self {
+            Self::Amqp(conn) => Arc::new(AmqpTaskQueue::new(conn.clone()).await),
+            Self::Postgres(conn) => Arc::new(PgTaskQueue::new(conn.clone())),
+        }
     }
 
-    pub async fn consume(&self) -> impl Stream<Item = AmqpMessage> {
-        let consumer = self
-            .chan
-            .basic_consume("tasks", "", Default::default(), Default::default())
-            .await
-            .unwrap();
-
-        let stream = consumer.then(|delivery| async move {
-            let delivery = delivery.unwrap();
-            let payload: Box<dyn Task> = serde_json::from_slice(&delivery.data).unwrap();
+    pub async fn dead_letter_queue(&self) -> Arc<dyn DeadLetterQueue> {
+        match self {
+            Self::Amqp(conn) => Arc::new(AmqpTaskQueue::new(conn.clone()).await),
+            Self::Postgres(conn) => Arc::new(PgTaskQueue::new(conn.clone())),
+        }
+    }
 
-            AmqpMessage {
-                content: payload,
-                delivery,
+    async fn consume(&self) -> BoxStream<'static, Box<dyn Message>> {
+        match self {
+            Self::Amqp(conn) => {
+                let task_queue = AmqpTaskQueue::new(conn.clone()).await;
+                task_queue
+                    .consume()
+                    .await
+                    .map(|m| Box::new(m) as Box<dyn Message>)
+                    .boxed()
             }
-        });
-
-        Box::pin(stream)
+            Self::Postgres(conn) => {
+                let task_queue = PgTaskQueue::new(conn.clone());
+                task_queue
+                    .consume()
+                    .await
+                    .map(|m| Box::new(m) as Box<dyn Message>)
+                    .boxed()
+            }
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct BackgroundTaskService {
     services: Services,
-    chan: Channel,
+    conn: QueueConnection,
+    concurrency: usize,
+    drain_timeout: Duration,
 }
 
 impl BackgroundTaskService {
-    pub async fn new(conn: QueueConnection, services: Services) -> Self {
-        let chan = conn.inner.create_channel().await.unwrap();
+    pub async fn new(cfg: &Config, conn: QueueConnection, services: Services) -> Self {
+        Self {
+            services,
+            conn,
+            concurrency: cfg.task_concurrency.max(1) as usize,
+            drain_timeout: Duration::from_secs(cfg.task_drain_timeout as u64),
+        }
+    }
 
-        Self { services, chan }
+    async fn process(&self, message: Box<dyn Message>) {
+        eprintln!("processing a task (attempt {})..", message.attempts());
+        let task = message.content();
+
+        let result = task.execute(&self.services).await;
+
+        message.ack(result.is_some()).await;
     }
 
     pub async fn run(self, kt: CancellationToken) {
-        let task_queue = AmqpTaskQueue { chan: self.chan };
-
-        let mut consumer = task_queue.consume().await;
+        let mut consumer = self.conn.consume().await;
+        let mut in_flight = FuturesUnordered::new();
 
         loop {
             tokio::select! {
                 _ = kt.cancelled() => break,
-                Some(message) = consumer.next() => {
-                    eprintln!("processing a task..");
-                    let task = message.content();
-
-                    let result = task.execute(&self.services)
-                    .await;
-
-                    message.ack(result.is_some()).await;
+                _ = in_flight.next(), if !in_flight.is_empty() => {},
+                message = consumer.next(), if in_flight.len() < self.concurrency => {
+                    match message {
+                        Some(message) => in_flight.push(self.process(message)),
+                        None => break,
+                    }
                 }
             }
         }
+
+        // Stop taking new deliveries, but keep the consumer open so that
+        // in-flight tasks can still be acknowledged.
+        let drain = async { while in_flight.next().await.is_some() {} };
+
+        if tokio::time::timeout(self.drain_timeout, drain)
+            .await
+            .is_err()
+        {
+            eprintln!("abandoning in-flight tasks after drain timeout");
+        }
+
+        drop(consumer);
     }
 }
 
-#[async_trait::async_trait]
-impl TaskQueue for AmqpTaskQueue {
-    async fn enqueue(&self, task: &dyn hanekawa_common::task::Task) -> Option<()> {
-        let payload = serde_json::to_string(task).unwrap();
-
-        self.chan
-            .basic_publish(
-                "",
-                "tasks",
-                Default::default(),
-                payload.as_bytes(),
-                Default::default(),
-            )
-            .await
-            .unwrap();
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = policy();
+
+        assert_eq!(Duration::from_secs(10), policy.delay(1));
+        assert_eq!(Duration::from_secs(20), policy.delay(2));
+        assert_eq!(Duration::from_secs(40), policy.delay(3));
+    }
+
+    #[test]
+    fn caps_backoff_at_max_delay() {
+        let policy = policy();
+
+        assert_eq!(Duration::from_secs(60), policy.delay(4));
+        assert_eq!(Duration::from_secs(60), policy.delay(u32::MAX));
+    }
+
+    #[test]
+    fn exhausts_after_max_attempts() {
+        let policy = policy();
 
-        Some(())
+        assert!(!policy.is_exhausted(4));
+        assert!(policy.is_exhausted(5));
     }
 }
@@ -0,0 +1,321 @@
+use std::time::Duration;
+
+use hanekawa_common::{
+    task::{DeadLetter, DeadLetterQueue, Task, TaskQueue},
+    Config,
+};
+
+use futures::stream::BoxStream;
+use sqlx::postgres::{PgListener, PgPool};
+use time::OffsetDateTime;
+
+use super::{Message, RetryPolicy};
+
+const TASKS_CHANNEL: &str = "tasks";
+
+// Tasks whose visibility timeout lapses do not generate a notification,
+// so idle consumers periodically poll for them.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+async fn dead_letter(pool: &PgPool, id: i64, attempts: i32, reason: &str) {
+    sqlx::query!(
+        "
+WITH dead AS (
+  DELETE FROM tasks
+  WHERE id = $1 AND attempts = $2
+  RETURNING payload, attempts
+)
+INSERT INTO dead_tasks(payload, attempts, reason)
+SELECT payload, attempts, $3
+FROM dead
+",
+        id,
+        attempts,
+        reason
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+pub struct PgMessage {
+    content: Box<dyn Task>,
+    id: i64,
+    attempts: i32,
+    pool: PgPool,
+    policy: RetryPolicy,
+}
+
+impl PgMessage {
+    pub fn content(&self) -> &dyn Task {
+        self.content.as_ref()
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts as u32
+    }
+
+    pub async fn ack(self, success: bool) {
+        // A claim is identified by its attempt number, so a consumer whose
+        // visibility timeout lapsed cannot settle a task another consumer
+        // has since claimed.
+        if success {
+            sqlx::query!(
+                "
+DELETE FROM tasks
+WHERE id = $1 AND attempts = $2
+",
+                self.id,
+                self.attempts
+            )
+            .execute(&self.pool)
+            .await
+            .unwrap();
+        } else if self.policy.is_exhausted(self.attempts()) {
+            dead_letter(
+                &self.pool,
+                self.id,
+                self.attempts,
+                "exceeded maximum attempts",
+            )
+            .await;
+        } else {
+            let delay = self.policy.delay(self.attempts());
+
+            sqlx::query!(
+                "
+UPDATE tasks
+SET visible_after_ts = now() + make_interval(secs => $3)
+WHERE id = $1 AND attempts = $2
+",
+                self.id,
+                self.attempts,
+                delay.as_secs_f64()
+            )
+            .execute(&self.pool)
+            .await
+            .unwrap();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Message for PgMessage {
+    fn content(&self) -> &dyn Task {
+        PgMessage::content(self)
+    }
+
+    fn attempts(&self) -> u32 {
+        PgMessage::attempts(self)
+    }
+
+    async fn ack(self: Box<Self>, success: bool) {
+        PgMessage::ack(*self, success).await
+    }
+}
+
+#[derive(Clone)]
+pub struct PgConnection {
+    pub(crate) pool: PgPool,
+    pub(crate) visibility_timeout: Duration,
+    pub(crate) policy: RetryPolicy,
+}
+
+impl PgConnection {
+    /// Share the storage pool, so the queue doesn't hold connections of its
+    /// own.
+    pub fn new(cfg: &Config, pool: PgPool) -> PgConnection {
+        PgConnection {
+            pool,
+            visibility_timeout: Duration::from_secs(cfg.task_visibility_timeout as u64),
+            policy: RetryPolicy::from_config(cfg),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PgTaskQueue {
+    pool: PgPool,
+    visibility_timeout: Duration,
+    policy: RetryPolicy,
+}
+
+impl PgTaskQueue {
+    pub fn new(connection: PgConnection) -> Self {
+        Self {
+            pool: connection.pool,
+            visibility_timeout: connection.visibility_timeout,
+            policy: connection.policy,
+        }
+    }
+
+    async fn claim(&self) -> Option<PgMessage> {
+        loop {
+            let row = sqlx::query!(
+                "
+UPDATE tasks
+SET
+  attempts = attempts + 1,
+  visible_after_ts = now() + make_interval(secs => $1)
+WHERE id = (
+  SELECT id
+  FROM tasks
+  WHERE visible_after_ts <= now()
+  ORDER BY id
+  FOR UPDATE SKIP LOCKED
+  LIMIT 1
+)
+RETURNING id, payload, attempts
+",
+                self.visibility_timeout.as_secs_f64()
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .unwrap()?;
+
+            // Consumers which repeatedly die mid-task never settle it, so
+            // enforce the attempt limit when claiming as well.
+            if self.policy.is_exhausted(row.attempts as u32 - 1) {
+                dead_letter(
+                    &self.pool,
+                    row.id,
+                    row.attempts,
+                    "exceeded maximum attempts",
+                )
+                .await;
+                continue;
+            }
+
+            match serde_json::from_slice::<Box<dyn Task>>(&row.payload) {
+                Ok(payload) => {
+                    return Some(PgMessage {
+                        content: payload,
+                        id: row.id,
+                        attempts: row.attempts,
+                        pool: self.pool.clone(),
+                        policy: self.policy,
+                    })
+                }
+                Err(e) => {
+                    let reason = format!("undecodable payload: {}", e);
+                    dead_letter(&self.pool, row.id, row.attempts, &reason).await;
+                }
+            }
+        }
+    }
+
+    pub async fn consume(&self) -> BoxStream<'static, PgMessage> {
+        let mut listener = PgListener::connect_with(&self.pool).await.unwrap();
+        listener.listen(TASKS_CHANNEL).await.unwrap();
+
+        let stream = futures::stream::unfold(
+            (self.clone(), listener),
+            |(queue, mut listener)| async move {
+                loop {
+                    if let Some(message) = queue.claim().await {
+                        return Some((message, (queue, listener)));
+                    }
+
+                    let _ = tokio::time::timeout(POLL_INTERVAL, listener.recv()).await;
+                }
+            },
+        );
+
+        Box::pin(stream)
+    }
+}
+
+#[async_trait::async_trait]
+impl TaskQueue for PgTaskQueue {
+    async fn enqueue(&self, task: &dyn Task) -> Option<()> {
+        let payload = serde_json::to_vec(task).unwrap();
+
+        sqlx::query!(
+            "
+INSERT INTO tasks(payload)
+VALUES ($1)
+",
+            &payload
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+
+        Some(())
+    }
+
+    async fn enqueue_at(&self, task: &dyn Task, at: OffsetDateTime) -> Option<()> {
+        let payload = serde_json::to_vec(task).unwrap();
+
+        sqlx::query!(
+            "
+INSERT INTO tasks(payload, visible_after_ts)
+VALUES ($1, $2)
+",
+            &payload,
+            at
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+
+        Some(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DeadLetterQueue for PgTaskQueue {
+    async fn dead_letters(&self, limit: usize) -> Option<Vec<DeadLetter>> {
+        let limit = i64::try_from(limit).ok()?;
+
+        let dead_letters = sqlx::query!(
+            "
+SELECT payload, attempts, reason
+FROM dead_tasks
+ORDER BY id
+LIMIT $1
+",
+            limit
+        )
+        .map(|r| DeadLetter {
+            payload: String::from_utf8_lossy(&r.payload).to_string(),
+            attempts: r.attempts as u32,
+            reason: r.reason,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Some(dead_letters)
+    }
+
+    async fn replay_dead_letters(&self, limit: usize) -> Option<usize> {
+        let limit = i64::try_from(limit).ok()?;
+
+        let result = sqlx::query!(
+            "
+WITH replayed AS (
+  DELETE FROM dead_tasks
+  WHERE id IN (
+    SELECT id
+    FROM dead_tasks
+    ORDER BY id
+    LIMIT $1
+    FOR UPDATE SKIP LOCKED
+  )
+  RETURNING id, payload
+)
+INSERT INTO tasks(payload)
+SELECT payload
+FROM replayed
+ORDER BY id
+",
+            limit
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+
+        Some(result.rows_affected() as usize)
+    }
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use hanekawa_common::{repository::schedule::ClaimScheduledRun, task::ScheduleRegistry, Services};
+
+use tokio_util::sync::CancellationToken;
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Enqueues the jobs of a `ScheduleRegistry` as they become due.
+pub struct Scheduler {
+    registry: ScheduleRegistry,
+    services: Services,
+}
+
+impl Scheduler {
+    pub fn new(registry: ScheduleRegistry, services: Services) -> Self {
+        Self { registry, services }
+    }
+
+    async fn fire_due_jobs(&self) {
+        for job in self.registry.jobs() {
+            let claimed = self
+                .services
+                .schedule_repository
+                .claim_scheduled_run(ClaimScheduledRun {
+                    name: &job.name,
+                    every: job.every,
+                })
+                .await
+                .unwrap();
+
+            if claimed {
+                self.services.task_queue.enqueue(job.task.as_ref()).await;
+            }
+        }
+    }
+
+    pub async fn run(self, kt: CancellationToken) {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = kt.cancelled() => break,
+                _ = interval.tick() => self.fire_due_jobs().await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hanekawa_common::task::Task;
+    use hanekawa_common::testing::Memory;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct NoopTask {
+        id: u32,
+    }
+
+    #[typetag::serde]
+    #[async_trait::async_trait]
+    impl Task for NoopTask {
+        async fn execute(&self, _ctx: &Services) -> Option<()> {
+            Some(())
+        }
+    }
+
+    #[tokio::test]
+    async fn fires_each_job_once_per_interval() {
+        let memory = Memory::new();
+        let registry = ScheduleRegistry::new()
+            .every("hourly", Duration::from_secs(3600), NoopTask { id: 1 })
+            .every("always", Duration::ZERO, NoopTask { id: 2 });
+        let scheduler = Scheduler::new(registry, memory.services());
+
+        scheduler.fire_due_jobs().await;
+        scheduler.fire_due_jobs().await;
+
+        assert_eq!(
+            vec![
+                r#"{"type":"NoopTask","id":1}"#,
+                r#"{"type":"NoopTask","id":2}"#,
+                r#"{"type":"NoopTask","id":2}"#,
+            ],
+            memory.state().tasks
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_runs_claimed_elsewhere() {
+        let memory = Memory::new();
+        let registry =
+            ScheduleRegistry::new().every("hourly", Duration::from_secs(3600), NoopTask { id: 1 });
+
+        // Another node has already claimed the current run.
+        Scheduler::new(registry.clone(), memory.services())
+            .fire_due_jobs()
+            .await;
+        memory.state().tasks.clear();
+
+        Scheduler::new(registry, memory.services())
+            .fire_due_jobs()
+            .await;
+        assert!(memory.state().tasks.is_empty());
+    }
+}
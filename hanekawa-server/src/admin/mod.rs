@@ -1,68 +1,861 @@
-use hanekawa::admin::{AdminService, Error, KnownInfoHashRequest};
-use hanekawa_common::Config;
+use std::net::{Ipv4Addr, SocketAddr};
 
-use crate::http::extractor::Query;
+use hanekawa::admin::{
+    AdminService, AuditLogRequest, BansRequest, Caller, CreateApiKeyRequest, CreateBanRequest,
+    CreateUserRequest, DeadLettersRequest, DeleteBanRequest, Error, ImportInfoHashesRequest,
+    InfoHashesRequest, KnownInfoHashRequest, PeersRequest, RevokeApiKeyRequest,
+    RevokePasskeyRequest, SeedingObligationsRequest, SetTorrentFlagsRequest, TorrentFlagsRequest,
+    TorrentsRequest, UploadTorrentRequest, UserStatsRequest,
+};
+use hanekawa_common::{
+    types::{
+        ApiKeyScope, AuditEntry, BanRule, InfoHashSummary, ObligationStatus, TorrentFlags,
+        TorrentSort,
+    },
+    Config, Services,
+};
+
+use hanekawa::ban::BanList;
+
+use serde::de::value::{Error as DeError, MapDeserializer};
+use time::OffsetDateTime;
 
-use axum::routing::{delete, post};
+use axum::body::Body;
+use axum::extract::{ConnectInfo, FromRequest, FromRequestParts, Multipart};
+use axum::http::{header, request::Parts, Request};
+use axum::routing::{delete, get, post};
 use axum::{
     extract::{Path, State},
     http::StatusCode,
 };
-use axum::{response::IntoResponse, Router};
+use axum::{response::IntoResponse, Json, Router};
 use hanekawa_common::types::InfoHashStatus;
 
+pub struct AdminError(Error);
+
+impl From<Error> for AdminError {
+    fn from(value: Error) -> Self {
+        Self(value)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, error) = match self.0 {
+            // Hide the admin API entirely when it is disabled.
+            Error::NotAllowed => (StatusCode::NOT_FOUND, "not_found"),
+            Error::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
+            Error::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+            Error::NotFound => (StatusCode::NOT_FOUND, "not_found"),
+            Error::InvalidInfoHash { .. } => (StatusCode::BAD_REQUEST, "invalid_info_hash"),
+            Error::InvalidRequest(_) => (StatusCode::BAD_REQUEST, "invalid_request"),
+        };
+
+        let body = ErrorBody {
+            error,
+            message: self.0.to_string(),
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Query string parameters, rejected with a JSON admin error.
+struct AdminQuery<T>(T);
+
+#[async_trait::async_trait]
+impl<T> FromRequestParts<AdminService> for AdminQuery<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    type Rejection = AdminError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _admin: &AdminService,
+    ) -> Result<Self, Self::Rejection> {
+        let value = match parts.uri.query() {
+            // Every parameter is optional for some endpoints, but the query
+            // string parser needs at least one.
+            None | Some("") => T::deserialize(MapDeserializer::<_, DeError>::new(
+                std::iter::empty::<(&str, &str)>(),
+            )),
+            Some(query_string) => hanekawa_percent_encode::from_query_string(query_string),
+        }
+        .map_err(|err| {
+            Error::InvalidRequest(format!("failed to deserialize query string: {}", err))
+        })?;
+
+        Ok(Self(value))
+    }
+}
+
+/// Path parameters, rejected with a JSON admin error.
+struct AdminPath<T>(T);
+
+#[async_trait::async_trait]
+impl<T> FromRequestParts<AdminService> for AdminPath<T>
+where
+    T: serde::de::DeserializeOwned + Send,
+{
+    type Rejection = AdminError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        admin: &AdminService,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(value) = Path::from_request_parts(parts, admin)
+            .await
+            .map_err(|rejection| Error::InvalidRequest(rejection.body_text()))?;
+
+        Ok(Self(value))
+    }
+}
+
+/// A JSON request body, rejected with a JSON admin error.
+struct AdminJson<T>(T);
+
+#[async_trait::async_trait]
+impl<T> FromRequest<AdminService, Body> for AdminJson<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    type Rejection = AdminError;
+
+    async fn from_request(
+        req: Request<Body>,
+        admin: &AdminService,
+    ) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::from_request(req, admin)
+            .await
+            .map_err(|rejection| Error::InvalidRequest(rejection.body_text()))?;
+
+        Ok(Self(value))
+    }
+}
+
+/// A UTF-8 plain text request body, rejected with a JSON admin error.
+struct AdminText(String);
+
+#[async_trait::async_trait]
+impl FromRequest<AdminService, Body> for AdminText {
+    type Rejection = AdminError;
+
+    async fn from_request(
+        req: Request<Body>,
+        admin: &AdminService,
+    ) -> Result<Self, Self::Rejection> {
+        let value = String::from_request(req, admin)
+            .await
+            .map_err(|rejection| Error::InvalidRequest(rejection.body_text()))?;
+
+        Ok(Self(value))
+    }
+}
+
+/// The caller of an admin route, authenticated by a bearer token.
+struct Authenticated(Caller);
+
+#[async_trait::async_trait]
+impl FromRequestParts<AdminService> for Authenticated {
+    type Rejection = AdminError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        admin: &AdminService,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(Ipv4Addr::UNSPECIFIED.into());
+
+        let caller = admin.authenticate(token.trim(), ip).await?;
+
+        Ok(Self(caller))
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct UpdateParams {
     allowed: bool,
 }
 
 async fn delete_info_hash(
-    Path(hex_info_hash): Path<String>,
-    State(admin): State<AdminService>,
-) -> impl IntoResponse {
-    let result = admin
-        .known_info_hash_command(KnownInfoHashRequest {
-            hex_info_hash,
-            action: InfoHashStatus::Unknown,
-        })
-        .await;
-
-    match result {
-        Ok(_) => StatusCode::OK,
-        Err(Error::NotAllowed) => StatusCode::NOT_FOUND,
-    }
+    Authenticated(caller): Authenticated,
+    AdminPath(hex_info_hash): AdminPath<String>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    admin
+        .known_info_hash_command(
+            &caller,
+            KnownInfoHashRequest {
+                hex_info_hash,
+                action: InfoHashStatus::Unknown,
+            },
+        )
+        .await?;
+
+    Ok(StatusCode::OK)
 }
 
 async fn update_info_hash(
-    Path(hex_info_hash): Path<String>,
-    Query(params): Query<UpdateParams>,
-    State(admin): State<AdminService>,
-) -> impl IntoResponse {
-    let result = admin
-        .known_info_hash_command(KnownInfoHashRequest {
-            hex_info_hash,
-            action: if params.allowed {
-                InfoHashStatus::ExplicitAllow
-            } else {
-                InfoHashStatus::ExplicitDeny
-            },
-        })
-        .await;
-
-    match result {
-        Ok(_) => StatusCode::OK,
-        Err(Error::NotAllowed) => StatusCode::NOT_FOUND,
+    Authenticated(caller): Authenticated,
+    AdminPath(hex_info_hash): AdminPath<String>,
+    AdminQuery(params): AdminQuery<UpdateParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    admin
+        .known_info_hash_command(
+            &caller,
+            KnownInfoHashRequest {
+                hex_info_hash,
+                action: if params.allowed {
+                    InfoHashStatus::ExplicitAllow
+                } else {
+                    InfoHashStatus::ExplicitDeny
+                },
+            },
+        )
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct InfoHashesParams {
+    status: Option<InfoHashStatus>,
+    after: Option<String>,
+    limit: Option<i64>,
+}
+
+const DEFAULT_INFO_HASH_LIMIT: i64 = 100;
+const MAX_INFO_HASH_LIMIT: i64 = 1000;
+
+#[derive(serde::Serialize)]
+struct InfoHashPage {
+    info_hashes: Vec<InfoHashSummary>,
+    /// Pass as `after` to fetch the next page, if there may be one.
+    next_after: Option<String>,
+}
+
+async fn get_info_hashes(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<InfoHashesParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_INFO_HASH_LIMIT)
+        .clamp(1, MAX_INFO_HASH_LIMIT);
+
+    let info_hashes = admin
+        .info_hashes(
+            &caller,
+            InfoHashesRequest {
+                status: params.status,
+                after_hex_info_hash: params.after,
+                limit: Some(limit),
+            },
+        )
+        .await?;
+
+    let next_after = if info_hashes.len() as i64 == limit {
+        info_hashes.last().map(|s| s.info_hash.to_hex())
+    } else {
+        None
+    };
+
+    Ok(Json(InfoHashPage {
+        info_hashes,
+        next_after,
+    }))
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    /// The same shape `POST /info_hashes` accepts.
+    #[default]
+    Json,
+    /// One hex info hash per line, as `POST /info_hashes/upload` accepts.
+    Text,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ExportParams {
+    status: Option<InfoHashStatus>,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+async fn export_info_hashes(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<ExportParams>,
+    State(admin): State<AdminService>,
+) -> Result<axum::response::Response, AdminError> {
+    let info_hashes = admin
+        .info_hashes(
+            &caller,
+            InfoHashesRequest {
+                status: params.status,
+                after_hex_info_hash: None,
+                limit: None,
+            },
+        )
+        .await?;
+
+    let response = match params.format {
+        ExportFormat::Json => Json(info_hashes).into_response(),
+        ExportFormat::Text => info_hashes
+            .iter()
+            .map(|s| s.info_hash.to_hex() + "\n")
+            .collect::<String>()
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ImportEntry {
+    info_hash: String,
+    status: InfoHashStatus,
+}
+
+#[derive(serde::Serialize)]
+struct ImportResponse {
+    imported: usize,
+}
+
+async fn import_info_hashes(
+    Authenticated(caller): Authenticated,
+    State(admin): State<AdminService>,
+    AdminJson(entries): AdminJson<Vec<ImportEntry>>,
+) -> Result<impl IntoResponse, AdminError> {
+    let imported = admin
+        .import_info_hashes(
+            &caller,
+            ImportInfoHashesRequest {
+                entries: entries
+                    .into_iter()
+                    .map(|e| (e.info_hash, e.status))
+                    .collect(),
+            },
+        )
+        .await?;
+
+    Ok(Json(ImportResponse { imported }))
+}
+
+async fn upload_info_hashes(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<UpdateParams>,
+    State(admin): State<AdminService>,
+    AdminText(body): AdminText,
+) -> Result<impl IntoResponse, AdminError> {
+    let status = if params.allowed {
+        InfoHashStatus::ExplicitAllow
+    } else {
+        InfoHashStatus::ExplicitDeny
+    };
+
+    let imported = admin
+        .import_info_hashes(
+            &caller,
+            ImportInfoHashesRequest {
+                entries: body
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(|l| (l.to_string(), status.clone()))
+                    .collect(),
+            },
+        )
+        .await?;
+
+    Ok(Json(ImportResponse { imported }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct UploadTorrentParams {
+    #[serde(default = "default_allowed")]
+    allowed: bool,
+}
+
+fn default_allowed() -> bool {
+    true
+}
+
+/// The multipart form field holding the `.torrent` file.
+const TORRENT_FIELD: &str = "torrent";
+
+async fn upload_torrent(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<UploadTorrentParams>,
+    State(admin): State<AdminService>,
+    multipart: Result<Multipart, axum::extract::multipart::MultipartRejection>,
+) -> Result<impl IntoResponse, AdminError> {
+    let invalid = |msg: String| AdminError(Error::InvalidRequest(msg));
+
+    let mut multipart = multipart.map_err(|rejection| invalid(rejection.body_text()))?;
+
+    let mut torrent = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| invalid(err.to_string()))?
+    {
+        if field.name() == Some(TORRENT_FIELD) {
+            torrent = Some(
+                field
+                    .bytes()
+                    .await
+                    .map_err(|err| invalid(err.to_string()))?,
+            );
+            break;
+        }
+    }
+
+    let torrent =
+        torrent.ok_or_else(|| invalid(format!("missing multipart field {:?}", TORRENT_FIELD)))?;
+
+    let uploaded = admin
+        .upload_torrent(
+            &caller,
+            UploadTorrentRequest {
+                torrent: torrent.to_vec(),
+                status: if params.allowed {
+                    InfoHashStatus::ExplicitAllow
+                } else {
+                    InfoHashStatus::ExplicitDeny
+                },
+            },
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(uploaded)))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TorrentsParams {
+    #[serde(default)]
+    sort: TorrentSort,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+const DEFAULT_TORRENT_LIMIT: i64 = 100;
+const MAX_TORRENT_LIMIT: i64 = 1000;
+
+async fn get_torrents(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<TorrentsParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let torrents = admin
+        .torrents(
+            &caller,
+            TorrentsRequest {
+                sort: params.sort,
+                limit: params
+                    .limit
+                    .unwrap_or(DEFAULT_TORRENT_LIMIT)
+                    .clamp(1, MAX_TORRENT_LIMIT),
+                offset: params.offset.unwrap_or(0).max(0),
+            },
+        )
+        .await?;
+
+    Ok(Json(torrents))
+}
+
+async fn get_torrent_peers(
+    Authenticated(caller): Authenticated,
+    AdminPath(hex_info_hash): AdminPath<String>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let peers = admin.peers(&caller, PeersRequest { hex_info_hash }).await?;
+
+    Ok(Json(peers))
+}
+
+async fn get_torrent_flags(
+    Authenticated(caller): Authenticated,
+    AdminPath(hex_info_hash): AdminPath<String>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let flags = admin
+        .torrent_flags(&caller, TorrentFlagsRequest { hex_info_hash })
+        .await?;
+
+    Ok(Json(flags))
+}
+
+async fn set_torrent_flags(
+    Authenticated(caller): Authenticated,
+    AdminPath(hex_info_hash): AdminPath<String>,
+    State(admin): State<AdminService>,
+    AdminJson(flags): AdminJson<TorrentFlags>,
+) -> Result<impl IntoResponse, AdminError> {
+    admin
+        .set_torrent_flags(
+            &caller,
+            SetTorrentFlagsRequest {
+                hex_info_hash,
+                flags,
+            },
+        )
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_clients(
+    Authenticated(caller): Authenticated,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let clients = admin.clients(&caller).await?;
+
+    Ok(Json(clients))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AuditParams {
+    actor: Option<i64>,
+    info_hash: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    since: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    until: Option<OffsetDateTime>,
+    before: Option<i64>,
+    limit: Option<i64>,
+}
+
+const DEFAULT_AUDIT_LIMIT: i64 = 100;
+const MAX_AUDIT_LIMIT: i64 = 1000;
+
+#[derive(serde::Serialize)]
+struct AuditPage {
+    entries: Vec<AuditEntry>,
+    /// Pass as `before` to fetch the next page, if there may be one.
+    next_before: Option<i64>,
+}
+
+async fn get_audit_log(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<AuditParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+
+    let entries = admin
+        .audit_log(
+            &caller,
+            AuditLogRequest {
+                actor: params.actor,
+                hex_info_hash: params.info_hash,
+                since: params.since,
+                until: params.until,
+                before_id: params.before,
+                limit,
+            },
+        )
+        .await?;
+
+    let next_before = if entries.len() as i64 == limit {
+        entries.last().map(|e| e.id)
+    } else {
+        None
+    };
+
+    Ok(Json(AuditPage {
+        entries,
+        next_before,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BansParams {
+    #[serde(default)]
+    include_expired: bool,
+}
+
+async fn get_bans(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<BansParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let bans = admin
+        .bans(
+            &caller,
+            BansRequest {
+                include_expired: params.include_expired,
+            },
+        )
+        .await?;
+
+    Ok(Json(bans))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CreateBanBody {
+    #[serde(flatten)]
+    rule: BanRule,
+    reason: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    expires_ts: Option<OffsetDateTime>,
+}
+
+async fn create_ban(
+    Authenticated(caller): Authenticated,
+    State(admin): State<AdminService>,
+    AdminJson(body): AdminJson<CreateBanBody>,
+) -> Result<impl IntoResponse, AdminError> {
+    let ban = admin
+        .create_ban(
+            &caller,
+            CreateBanRequest {
+                rule: body.rule,
+                reason: body.reason,
+                expires_ts: body.expires_ts,
+            },
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(ban)))
+}
+
+async fn delete_ban(
+    Authenticated(caller): Authenticated,
+    AdminPath(id): AdminPath<i64>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    admin.delete_ban(&caller, DeleteBanRequest { id }).await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DeadLetterParams {
+    limit: Option<usize>,
+}
+
+const DEFAULT_DEAD_LETTER_LIMIT: usize = 100;
+const MAX_DEAD_LETTER_LIMIT: usize = 1000;
+
+impl DeadLetterParams {
+    fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_DEAD_LETTER_LIMIT)
+            .clamp(1, MAX_DEAD_LETTER_LIMIT)
     }
 }
 
-pub async fn admin<S>(cfg: &Config) -> Router<S> {
-    let storage = hanekawa_storage::Services::start(cfg).await;
+async fn get_dead_letters(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<DeadLetterParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let dead_letters = admin
+        .dead_letters(
+            &caller,
+            DeadLettersRequest {
+                limit: params.limit(),
+            },
+        )
+        .await?;
+
+    Ok(Json(dead_letters))
+}
+
+#[derive(serde::Serialize)]
+struct ReplayResponse {
+    replayed: usize,
+}
+
+async fn replay_dead_letters(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<DeadLetterParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let replayed = admin
+        .replay_dead_letters(
+            &caller,
+            DeadLettersRequest {
+                limit: params.limit(),
+            },
+        )
+        .await?;
+
+    Ok(Json(ReplayResponse { replayed }))
+}
+
+async fn get_api_keys(
+    Authenticated(caller): Authenticated,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let api_keys = admin.api_keys(&caller).await?;
+
+    Ok(Json(api_keys))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CreateApiKeyParams {
+    name: String,
+    scope: ApiKeyScope,
+}
+
+async fn create_api_key(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<CreateApiKeyParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let created = admin
+        .create_api_key(
+            &caller,
+            CreateApiKeyRequest {
+                name: params.name,
+                scope: params.scope,
+            },
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn revoke_api_key(
+    Authenticated(caller): Authenticated,
+    AdminPath(id): AdminPath<i64>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    admin
+        .revoke_api_key(&caller, RevokeApiKeyRequest { id })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_users(
+    Authenticated(caller): Authenticated,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let users = admin.users(&caller).await?;
+
+    Ok(Json(users))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CreateUserParams {
+    name: String,
+}
+
+async fn create_user(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<CreateUserParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let user = admin
+        .create_user(&caller, CreateUserRequest { name: params.name })
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+async fn revoke_passkey(
+    Authenticated(caller): Authenticated,
+    AdminPath(user_id): AdminPath<i64>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    admin
+        .revoke_passkey(&caller, RevokePasskeyRequest { user_id })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn get_user_stats(
+    Authenticated(caller): Authenticated,
+    AdminPath(user_id): AdminPath<i64>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let stats = admin
+        .user_stats(&caller, UserStatsRequest { user_id })
+        .await?;
+
+    Ok(Json(stats))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SeedingObligationsParams {
+    user_id: Option<i64>,
+    status: Option<ObligationStatus>,
+}
+
+async fn get_seeding_obligations(
+    Authenticated(caller): Authenticated,
+    AdminQuery(params): AdminQuery<SeedingObligationsParams>,
+    State(admin): State<AdminService>,
+) -> Result<impl IntoResponse, AdminError> {
+    let obligations = admin
+        .seeding_obligations(
+            &caller,
+            SeedingObligationsRequest {
+                user_id: params.user_id,
+                status: params.status,
+            },
+        )
+        .await?;
+
+    Ok(Json(obligations))
+}
 
-    let ir = std::sync::Arc::new(storage.info_hash);
-    let admin = AdminService::new(cfg, ir);
+pub async fn admin<S>(cfg: &Config, services: Services, bans: BanList) -> Router<S> {
+    let admin = AdminService::new(cfg, services, bans);
+    admin.bootstrap().await;
 
     Router::new()
+        .route("/info_hashes", get(get_info_hashes))
+        .route("/info_hashes", post(import_info_hashes))
+        .route("/info_hashes/export", get(export_info_hashes))
+        .route("/info_hashes/upload", post(upload_info_hashes))
         .route("/info_hashes/:info_hash", delete(delete_info_hash))
         .route("/info_hashes/:info_hash", post(update_info_hash))
+        .route("/torrents", get(get_torrents))
+        .route("/torrents", post(upload_torrent))
+        .route("/torrents/:info_hash/peers", get(get_torrent_peers))
+        .route("/torrents/:info_hash/flags", get(get_torrent_flags))
+        .route("/torrents/:info_hash/flags", post(set_torrent_flags))
+        .route("/clients", get(get_clients))
+        .route("/bans", get(get_bans))
+        .route("/bans", post(create_ban))
+        .route("/bans/:id", delete(delete_ban))
+        .route("/audit", get(get_audit_log))
+        .route("/tasks/dead", get(get_dead_letters))
+        .route("/tasks/dead/replay", post(replay_dead_letters))
+        .route("/api_keys", get(get_api_keys))
+        .route("/api_keys", post(create_api_key))
+        .route("/api_keys/:id", delete(revoke_api_key))
+        .route("/users", get(get_users))
+        .route("/users", post(create_user))
+        .route("/users/:id/passkey", delete(revoke_passkey))
+        .route("/users/:id/stats", get(get_user_stats))
+        .route("/obligations", get(get_seeding_obligations))
         .with_state(admin)
 }
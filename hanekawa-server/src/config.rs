@@ -5,15 +5,35 @@ use figment::{
     Figment,
 };
 
+fn role_from_args() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--role" {
+            return args.next();
+        }
+        if let Some(role) = arg.strip_prefix("--role=") {
+            return Some(role.to_string());
+        }
+    }
+
+    None
+}
+
 pub fn load_config() -> Config {
     const CONFIG_FILE: &'static str = "hanekawa.toml";
     const ENV_PREFIX: &'static str = "HKW_";
 
-    let cfg = Figment::new()
+    let mut figment = Figment::new()
         .merge(Serialized::defaults(Config::default_config()))
         .merge(Toml::file(CONFIG_FILE))
-        .merge(Env::prefixed(ENV_PREFIX))
-        .extract();
+        .merge(Env::prefixed(ENV_PREFIX));
+
+    if let Some(role) = role_from_args() {
+        figment = figment.merge(Serialized::default("role", role));
+    }
+
+    let cfg = figment.extract();
 
     let cfg: Config = match cfg {
         Ok(cfg) => cfg,
@@ -1,16 +1,32 @@
+use std::cell::RefCell;
+
 use axum::http::{header, status, HeaderValue};
 use axum::response::IntoResponse;
+use bytes::BytesMut;
 
 pub struct Bencode<T>(pub T);
 
 const APPLICATION_OCTET_STREAM: &'static str = "application/octet-stream";
 
+/// Enough for an announce response with a few dozen peers.
+const INITIAL_BUFFER_CAPACITY: usize = 1024;
+
+thread_local! {
+    /// Responses are encoded into a per-thread buffer and split off it, so
+    /// its allocation is reused once earlier responses have been sent.
+    static BUFFER: RefCell<BytesMut> = RefCell::new(BytesMut::with_capacity(INITIAL_BUFFER_CAPACITY));
+}
+
 impl<T> IntoResponse for Bencode<T>
 where
     T: serde::Serialize,
 {
     fn into_response(self) -> axum::response::Response {
-        let encoded = hanekawa_bencode::to_bytes(&self.0);
+        let encoded = BUFFER.with(|buf| {
+            let mut buf = buf.borrow_mut();
+            buf.reserve(INITIAL_BUFFER_CAPACITY);
+            hanekawa_bencode::to_buf(&self.0, &mut buf).map(|_| buf.split().freeze())
+        });
         match encoded {
             Ok(bs) => (
                 [(
@@ -7,12 +7,13 @@ use super::http::extractor::Query;
 
 use response::Failure;
 
+use hanekawa::ban::BanList;
 use hanekawa::http_tracker::proto::{
     AnnounceRequest, AnnounceResponse, ScrapeRequest, ScrapeResponse,
 };
 use hanekawa::http_tracker::HttpTrackerService;
 
-use axum::extract::{ConnectInfo, State};
+use axum::extract::{ConnectInfo, Path, State};
 use axum::routing::get;
 use axum::Router;
 use hanekawa_common::{Config, Services};
@@ -23,7 +24,20 @@ async fn announce(
     ConnectInfo(info): ConnectInfo<std::net::SocketAddr>,
 ) -> Result<Bencode<AnnounceResponse>, Failure> {
     // TODO: extract true source IP from potential proxies.
-    let response = tracker.announce(announce, info.ip()).await?;
+    let response = tracker.announce(announce, None, info.ip()).await?;
+
+    Ok(Bencode(response))
+}
+
+async fn passkey_announce(
+    OrFailure(Path(passkey)): OrFailure<Path<String>>,
+    OrFailure(Query(announce)): OrFailure<Query<AnnounceRequest>>,
+    State(tracker): State<HttpTrackerService>,
+    ConnectInfo(info): ConnectInfo<std::net::SocketAddr>,
+) -> Result<Bencode<AnnounceResponse>, Failure> {
+    let response = tracker
+        .announce(announce, Some(&passkey), info.ip())
+        .await?;
 
     Ok(Bencode(response))
 }
@@ -33,15 +47,26 @@ async fn scrape(
     OrFailure(Query(scrape)): OrFailure<Query<ScrapeRequest>>,
     State(tracker): State<HttpTrackerService>,
 ) -> Result<Bencode<ScrapeResponse>, Failure> {
-    let response = tracker.scrape(scrape).await?;
+    let response = tracker.scrape(scrape, None).await?;
+    Ok(Bencode(response))
+}
+
+async fn passkey_scrape(
+    OrFailure(Path(passkey)): OrFailure<Path<String>>,
+    OrFailure(Query(scrape)): OrFailure<Query<ScrapeRequest>>,
+    State(tracker): State<HttpTrackerService>,
+) -> Result<Bencode<ScrapeResponse>, Failure> {
+    let response = tracker.scrape(scrape, Some(&passkey)).await?;
     Ok(Bencode(response))
 }
 
-pub async fn tracker<S>(cfg: &Config, services: Services) -> Router<S> {
-    let tracker = HttpTrackerService::new(cfg, services);
+pub async fn tracker<S>(cfg: &Config, services: Services, bans: BanList) -> Router<S> {
+    let tracker = HttpTrackerService::new(cfg, services, bans);
 
     Router::new()
         .route("/announce", get(announce))
         .route("/scrape", get(scrape))
+        .route("/:passkey/announce", get(passkey_announce))
+        .route("/:passkey/scrape", get(passkey_scrape))
         .with_state(tracker)
 }
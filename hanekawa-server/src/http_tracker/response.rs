@@ -29,6 +29,11 @@ impl IntoResponse for Failure {
         let status_code = match self.0 {
             Error::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::InfoHashNotAllowed(_) => StatusCode::FORBIDDEN,
+            Error::Banned(_) => StatusCode::FORBIDDEN,
+            Error::ClientNotAllowed(_) => StatusCode::FORBIDDEN,
+            Error::Unauthorized(_) => StatusCode::FORBIDDEN,
+            Error::InsufficientRatio(_) => StatusCode::FORBIDDEN,
+            Error::InvalidRequest(_) => StatusCode::BAD_REQUEST,
             Error::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -7,15 +7,16 @@ mod udp_tracker;
 
 use std::sync::Arc;
 
+use hanekawa::ban::BanList;
 use hanekawa_common::{Config, Services};
 use http_tracker::tracker;
 
 use axum::Router;
 use tokio_util::sync::CancellationToken;
 
-async fn start_http(cfg: Config, services: Services, kt: CancellationToken) {
-    let tracker = tracker(&cfg, services).await;
-    let admin = admin::admin(&cfg).await;
+async fn start_http(cfg: Config, services: Services, bans: BanList, kt: CancellationToken) {
+    let tracker = tracker(&cfg, services.clone(), bans.clone()).await;
+    let admin = admin::admin(&cfg, services, bans).await;
 
     let app = Router::new().nest("/", tracker).nest("/admin", admin);
 
@@ -26,8 +27,20 @@ async fn start_http(cfg: Config, services: Services, kt: CancellationToken) {
         .unwrap();
 }
 
-async fn start_udp(cfg: Config, _services: Services, kt: CancellationToken) {
-    udp_tracker::start(&cfg, kt).await;
+async fn start_udp(cfg: Config, services: Services, bans: BanList, kt: CancellationToken) {
+    udp_tracker::start(&cfg, services, bans, kt).await;
+}
+
+async fn refresh_bans(cfg: Config, bans: BanList, kt: CancellationToken) {
+    let period = std::time::Duration::from_secs(cfg.ban_refresh_interval as u64);
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        tokio::select! {
+            _ = kt.cancelled() => break,
+            _ = interval.tick() => bans.refresh().await,
+        }
+    }
 }
 
 pub async fn start() {
@@ -35,28 +48,68 @@ pub async fn start() {
     tracing_subscriber::fmt::init();
 
     let cfg = crate::config::load_config();
+    tracing::info!("Starting with role {:?}", cfg.role);
 
     let kt = tokio_util::sync::CancellationToken::new();
 
     let storage = hanekawa_storage::Services::start(&cfg).await;
-    let queue_conn = hanekawa_queue::QueueConnection::connect(&cfg).await;
+    let queue_conn = hanekawa_queue::QueueConnection::connect(&cfg, storage.pool.clone()).await;
 
-    let queue = hanekawa_queue::AmqpTaskQueue::new(queue_conn.clone()).await;
+    let queue = queue_conn.task_queue().await;
+    let dead_letter_queue = queue_conn.dead_letter_queue().await;
 
     let services = hanekawa_common::Services {
+        api_key_repository: Arc::new(storage.api_key),
+        audit_repository: Arc::new(storage.audit),
+        ban_repository: Arc::new(storage.ban),
         peer_repository: Arc::new(storage.peer),
         info_hash_repository: Arc::new(storage.info_hash),
-        task_queue: Arc::new(queue),
+        schedule_repository: Arc::new(storage.schedule),
+        seeding_repository: Arc::new(storage.seeding),
+        user_repository: Arc::new(storage.user),
+        task_queue: queue,
+        dead_letter_queue,
     };
 
-    let hh = tokio::spawn(start_http(cfg.clone(), services.clone(), kt.child_token()));
-    let uh = tokio::spawn(start_udp(cfg.clone(), services.clone(), kt.child_token()));
-
-    let background_tasks =
-        hanekawa_queue::BackgroundTaskService::new(queue_conn.clone(), services.clone()).await;
-
-    let tkt = kt.child_token();
-    let bt = tokio::spawn(async move { background_tasks.run(tkt).await });
+    let mut handles = Vec::new();
+
+    if cfg.role.runs_tracker() {
+        let bans = BanList::new(services.clone());
+        bans.refresh().await;
+
+        handles.push(tokio::spawn(refresh_bans(
+            cfg.clone(),
+            bans.clone(),
+            kt.child_token(),
+        )));
+        handles.push(tokio::spawn(start_http(
+            cfg.clone(),
+            services.clone(),
+            bans.clone(),
+            kt.child_token(),
+        )));
+        handles.push(tokio::spawn(start_udp(
+            cfg.clone(),
+            services.clone(),
+            bans,
+            kt.child_token(),
+        )));
+    }
+
+    if cfg.role.runs_worker() {
+        let background_tasks =
+            hanekawa_queue::BackgroundTaskService::new(&cfg, queue_conn.clone(), services.clone())
+                .await;
+
+        let tkt = kt.child_token();
+        handles.push(tokio::spawn(async move { background_tasks.run(tkt).await }));
+
+        let scheduler =
+            hanekawa_queue::Scheduler::new(hanekawa::schedule::schedule(&cfg), services.clone());
+
+        let skt = kt.child_token();
+        handles.push(tokio::spawn(async move { scheduler.run(skt).await }));
+    }
 
     let cancel = tokio::spawn(async move {
         use tokio::signal::{
@@ -77,5 +130,7 @@ pub async fn start() {
         kt.cancel();
     });
 
-    let _ = tokio::join!(cancel, hh, uh, bt);
+    handles.push(cancel);
+
+    let _ = futures::future::join_all(handles).await;
 }
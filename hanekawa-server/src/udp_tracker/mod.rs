@@ -1,30 +1,49 @@
 mod codec;
 
 use codec::UdpTrackerCodec;
-use hanekawa_common::Config;
+use hanekawa::ban::BanList;
+use hanekawa::udp_tracker::UdpTrackerService;
+use hanekawa_common::{Config, Services};
 
 use tokio_util::sync::CancellationToken;
 
-pub async fn start(cfg: &Config, kt: CancellationToken) {
-    use futures::StreamExt;
+pub async fn start(cfg: &Config, services: Services, bans: BanList, kt: CancellationToken) {
+    use futures::{SinkExt, StreamExt};
     use tokio::net::UdpSocket;
     use tokio_util::udp::UdpFramed;
 
+    let tracker = UdpTrackerService::new(cfg, services, bans);
+
     let socket = UdpSocket::bind((cfg.bind_ip, cfg.udp_bind_port))
         .await
         .unwrap();
-    let mut socket = UdpFramed::new(socket, UdpTrackerCodec {});
+    let (mut sink, mut stream) = UdpFramed::new(socket, UdpTrackerCodec {}).split();
+
+    // Requests are handled concurrently and their responses funnelled
+    // back to the single socket sink.
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1024);
 
     loop {
         tokio::select! {
             _ = kt.cancelled() => {
                 break;
             },
-            request = socket.next() => {
+            Some(response) = rx.recv() => {
+                if let Err(e) = sink.send(response).await {
+                    eprintln!("failed to send response, {:?}", e);
+                }
+            },
+            request = stream.next() => {
                 if let Some(request) = request {
                     match request {
-                        Ok((request, ip)) => {
-                            eprintln!("received request {:?} from ip {:?}", request, ip);
+                        Ok((request, addr)) => {
+                            let tracker = tracker.clone();
+                            let tx = tx.clone();
+
+                            tokio::spawn(async move {
+                                let response = tracker.handle(request, addr).await;
+                                let _ = tx.send((response, addr)).await;
+                            });
                         }
                         Err(e) => {
                             eprintln!("malformed message, {:?}", e);
@@ -0,0 +1,20 @@
+CREATE TABLE tasks(
+       id bigserial NOT NULL PRIMARY KEY,
+       payload bytea NOT NULL,
+       attempts integer NOT NULL DEFAULT 0,
+       enqueued_ts timestamptz NOT NULL DEFAULT now(),
+       visible_after_ts timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX tasks_visible_after_ts_idx ON tasks(visible_after_ts);
+
+CREATE FUNCTION notify_tasks() RETURNS trigger AS $$
+BEGIN
+       PERFORM pg_notify('tasks', '');
+       RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_notify
+       AFTER INSERT ON tasks
+       FOR EACH STATEMENT EXECUTE FUNCTION notify_tasks();
@@ -0,0 +1,7 @@
+CREATE TABLE dead_tasks(
+       id bigserial NOT NULL PRIMARY KEY,
+       payload bytea NOT NULL,
+       attempts integer NOT NULL,
+       reason text NOT NULL,
+       dead_ts timestamptz NOT NULL DEFAULT now()
+);
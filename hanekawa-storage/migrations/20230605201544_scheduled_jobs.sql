@@ -0,0 +1,4 @@
+CREATE TABLE scheduled_jobs(
+       name text NOT NULL PRIMARY KEY,
+       next_run_ts timestamptz NOT NULL
+);
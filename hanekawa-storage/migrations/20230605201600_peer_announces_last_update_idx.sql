@@ -0,0 +1,2 @@
+-- Expired peers are purged by their last announce.
+CREATE INDEX peer_announces_last_update_ts_idx ON peer_announces(last_update_ts);
@@ -0,0 +1,8 @@
+CREATE TABLE api_keys(
+       id bigserial NOT NULL PRIMARY KEY,
+       name text NOT NULL,
+       scope text NOT NULL,
+       key_hash bytea NOT NULL UNIQUE,
+       created_ts timestamptz NOT NULL DEFAULT now(),
+       revoked_ts timestamptz
+);
@@ -0,0 +1,24 @@
+CREATE TABLE audit_log(
+       id bigserial NOT NULL PRIMARY KEY,
+       actor bigint NOT NULL REFERENCES api_keys(id),
+       action text NOT NULL,
+       info_hash bytea NOT NULL,
+       old_status text NOT NULL,
+       new_status text NOT NULL,
+       ip inet NOT NULL,
+       created_ts timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_log_actor_idx ON audit_log(actor);
+CREATE INDEX audit_log_info_hash_idx ON audit_log(info_hash);
+CREATE INDEX audit_log_created_ts_idx ON audit_log(created_ts);
+
+CREATE FUNCTION reject_audit_log_change() RETURNS trigger AS $$
+BEGIN
+       RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+       BEFORE UPDATE OR DELETE ON audit_log
+       FOR EACH ROW EXECUTE FUNCTION reject_audit_log_change();
@@ -0,0 +1,18 @@
+-- One row per completed download, so repeated or replayed completed
+-- announces count once. Snatches are counted per user where peers have
+-- one, and per peer otherwise.
+CREATE TABLE torrent_snatches(
+       info_hash bytea NOT NULL,
+       peer_id bytea NOT NULL,
+       user_id bigint,
+       created_ts timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX torrent_snatches_peer_idx ON torrent_snatches(info_hash, peer_id) WHERE user_id IS NULL;
+CREATE UNIQUE INDEX torrent_snatches_user_idx ON torrent_snatches(info_hash, user_id) WHERE user_id IS NOT NULL;
+
+INSERT INTO torrent_snatches(info_hash, peer_id)
+SELECT info_hash, peer_id
+FROM peer_announces
+WHERE event = 'completed'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,8 @@
+CREATE TABLE bans(
+       id bigserial NOT NULL PRIMARY KEY,
+       kind text NOT NULL,
+       value text NOT NULL,
+       reason text NOT NULL,
+       created_ts timestamptz NOT NULL DEFAULT now(),
+       expires_ts timestamptz
+);
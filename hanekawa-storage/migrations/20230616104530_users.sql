@@ -0,0 +1,9 @@
+CREATE TABLE users(
+       id bigserial NOT NULL PRIMARY KEY,
+       name text NOT NULL UNIQUE,
+       passkey text NOT NULL UNIQUE,
+       created_ts timestamptz NOT NULL DEFAULT now(),
+       revoked_ts timestamptz
+);
+
+ALTER TABLE peer_announces ADD COLUMN user_id bigint REFERENCES users(id);
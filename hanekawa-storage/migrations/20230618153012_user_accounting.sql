@@ -0,0 +1,15 @@
+CREATE TABLE user_torrents(
+       user_id bigint NOT NULL REFERENCES users(id),
+       info_hash bytea NOT NULL,
+       uploaded bigint NOT NULL,
+       downloaded bigint NOT NULL,
+       credited_uploaded bigint NOT NULL,
+       credited_downloaded bigint NOT NULL,
+       PRIMARY KEY(user_id, info_hash)
+);
+
+CREATE TABLE torrent_flags(
+       info_hash bytea NOT NULL PRIMARY KEY,
+       freeleech boolean NOT NULL,
+       double_upload boolean NOT NULL
+);
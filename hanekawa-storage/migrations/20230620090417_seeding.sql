@@ -0,0 +1,25 @@
+CREATE TABLE seeding_sessions(
+       id bigserial NOT NULL PRIMARY KEY,
+       user_id bigint NOT NULL REFERENCES users(id),
+       info_hash bytea NOT NULL,
+       started_ts timestamptz NOT NULL,
+       last_announce_ts timestamptz NOT NULL,
+       ended_ts timestamptz
+);
+
+CREATE INDEX seeding_sessions_user_info_hash_idx ON seeding_sessions(user_id, info_hash);
+
+-- At most one open session per user and torrent.
+CREATE UNIQUE INDEX seeding_sessions_open_idx ON seeding_sessions(user_id, info_hash)
+       WHERE ended_ts IS NULL;
+
+CREATE TABLE seeding_obligations(
+       user_id bigint NOT NULL REFERENCES users(id),
+       info_hash bytea NOT NULL,
+       completed_ts timestamptz NOT NULL,
+       status text NOT NULL DEFAULT 'pending',
+       resolved_ts timestamptz,
+       PRIMARY KEY(user_id, info_hash)
+);
+
+CREATE INDEX seeding_obligations_status_idx ON seeding_obligations(status);
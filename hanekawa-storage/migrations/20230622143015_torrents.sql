@@ -0,0 +1,11 @@
+-- Metadata of uploaded .torrent files, stored once per info hash the
+-- torrent is announced under.
+CREATE TABLE torrents(
+       info_hash bytea NOT NULL PRIMARY KEY,
+       name text NOT NULL,
+       total_length bigint NOT NULL,
+       file_count bigint NOT NULL,
+       is_private boolean NOT NULL,
+       version text NOT NULL,
+       created_ts timestamptz NOT NULL DEFAULT now()
+);
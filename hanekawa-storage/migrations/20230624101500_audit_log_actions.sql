@@ -0,0 +1,7 @@
+-- Audit every admin change, not just info hash status changes.
+ALTER TABLE audit_log
+      ALTER COLUMN info_hash DROP NOT NULL,
+      ALTER COLUMN old_status DROP NOT NULL,
+      ALTER COLUMN new_status DROP NOT NULL,
+      ADD COLUMN target_id bigint,
+      ADD COLUMN details text;
@@ -0,0 +1,154 @@
+use hanekawa_common::repository::{
+    api_key::{
+        ApiKeyRepository as Repository, CreateApiKey, EnsureApiKey, GetApiKey, RevokeApiKey,
+    },
+    Error,
+};
+use hanekawa_common::types::{ApiKey, AuditAction};
+
+use sqlx::postgres::PgPool;
+
+#[derive(Clone)]
+pub struct ApiKeyRepository {
+    pool: PgPool,
+}
+
+impl ApiKeyRepository {
+    pub(super) fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for ApiKeyRepository {
+    async fn create_api_key(&self, cmd: CreateApiKey<'_>) -> Result<ApiKey, Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let api_key = sqlx::query!(
+            "
+INSERT INTO api_keys(name, scope, key_hash)
+VALUES ($1, $2, $3)
+RETURNING id, name, scope, created_ts, revoked_ts
+",
+            cmd.name,
+            cmd.scope.as_str(),
+            cmd.key_hash
+        )
+        .map(|r| ApiKey {
+            id: r.id,
+            name: r.name,
+            scope: r.scope.parse().unwrap(),
+            created_ts: r.created_ts,
+            revoked_ts: r.revoked_ts,
+        })
+        .fetch_one(&mut tx)
+        .await
+        .unwrap();
+
+        crate::audit::append_entry(
+            &mut tx,
+            cmd.actor,
+            AuditAction::CreateApiKey,
+            Some(api_key.id),
+            Some(&api_key.name),
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        Ok(api_key)
+    }
+
+    async fn ensure_api_key(&self, cmd: EnsureApiKey<'_>) -> Result<(), Error> {
+        sqlx::query!(
+            "
+INSERT INTO api_keys(name, scope, key_hash)
+VALUES ($1, $2, $3)
+ON CONFLICT (key_hash) DO NOTHING
+",
+            cmd.name,
+            cmd.scope.as_str(),
+            cmd.key_hash
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(())
+    }
+
+    async fn get_api_key(&self, cmd: GetApiKey<'_>) -> Result<Option<ApiKey>, Error> {
+        let api_key = sqlx::query!(
+            "
+SELECT id, name, scope, created_ts, revoked_ts
+FROM api_keys
+WHERE key_hash = $1
+",
+            cmd.key_hash
+        )
+        .map(|r| ApiKey {
+            id: r.id,
+            name: r.name,
+            scope: r.scope.parse().unwrap(),
+            created_ts: r.created_ts,
+            revoked_ts: r.revoked_ts,
+        })
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(api_key)
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, Error> {
+        let api_keys = sqlx::query!(
+            "
+SELECT id, name, scope, created_ts, revoked_ts
+FROM api_keys
+ORDER BY id
+"
+        )
+        .map(|r| ApiKey {
+            id: r.id,
+            name: r.name,
+            scope: r.scope.parse().unwrap(),
+            created_ts: r.created_ts,
+            revoked_ts: r.revoked_ts,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(api_keys)
+    }
+
+    async fn revoke_api_key(&self, cmd: RevokeApiKey) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let result = sqlx::query!(
+            "
+UPDATE api_keys
+SET revoked_ts = now()
+WHERE id = $1 AND revoked_ts IS NULL
+",
+            cmd.id
+        )
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+        let revoked = result.rows_affected() > 0;
+        if revoked {
+            crate::audit::append_entry(
+                &mut tx,
+                cmd.actor,
+                AuditAction::RevokeApiKey,
+                Some(cmd.id),
+                None,
+            )
+            .await;
+        }
+        tx.commit().await.unwrap();
+
+        Ok(revoked)
+    }
+}
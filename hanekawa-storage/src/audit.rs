@@ -0,0 +1,160 @@
+use hanekawa_common::repository::{
+    audit::{Actor, AppendAuditEntry, AuditRepository as Repository, ListAuditEntries},
+    Error,
+};
+use hanekawa_common::types::{AuditAction, AuditEntry, InfoHash, InfoHashStatus, InfoHashSummary};
+
+use sqlx::postgres::{PgPool, Postgres};
+use sqlx::types::ipnetwork::IpNetwork;
+use sqlx::Transaction;
+
+#[derive(Clone)]
+pub struct AuditRepository {
+    pool: PgPool,
+}
+
+impl AuditRepository {
+    pub(super) fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Record a change to an API key, ban or user, in the transaction that
+/// makes it.
+pub(crate) async fn append_entry(
+    tx: &mut Transaction<'_, Postgres>,
+    actor: Actor,
+    action: AuditAction,
+    target_id: Option<i64>,
+    details: Option<&str>,
+) {
+    let inet: IpNetwork = actor.ip.into();
+
+    sqlx::query!(
+        "
+INSERT INTO audit_log(actor, action, target_id, details, ip)
+VALUES ($1, $2, $3, $4, $5)
+",
+        actor.api_key_id,
+        action.as_str(),
+        target_id,
+        details,
+        inet
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+}
+
+/// Record a change to the settings of one torrent, in the transaction that
+/// makes it.
+pub(crate) async fn append_torrent_entry(
+    tx: &mut Transaction<'_, Postgres>,
+    actor: Actor,
+    action: AuditAction,
+    info_hash: &InfoHash,
+    details: Option<&str>,
+) {
+    let inet: IpNetwork = actor.ip.into();
+
+    sqlx::query!(
+        "
+INSERT INTO audit_log(actor, action, info_hash, details, ip)
+VALUES ($1, $2, $3, $4, $5)
+",
+        actor.api_key_id,
+        action.as_str(),
+        &info_hash.0,
+        details,
+        inet
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+}
+
+/// Record a change to many info hashes, in the transaction that makes it.
+/// `previous` holds the status each info hash had before the change.
+pub(crate) async fn append_info_hash_entries(
+    tx: &mut Transaction<'_, Postgres>,
+    actor: Actor,
+    action: AuditAction,
+    previous: &[InfoHashSummary],
+    new_status: &InfoHashStatus,
+) {
+    let inet: IpNetwork = actor.ip.into();
+    let (info_hashes, old_statuses): (Vec<_>, Vec<_>) = previous
+        .iter()
+        .map(|s| (s.info_hash.0.clone(), s.status.as_str()))
+        .unzip();
+
+    sqlx::query!(
+        "
+INSERT INTO audit_log(actor, action, info_hash, old_status, new_status, ip)
+SELECT $1, $2, info_hash, old_status, $5, $6
+FROM unnest($3::bytea[], $4::text[]) AS t(info_hash, old_status)
+",
+        actor.api_key_id,
+        action.as_str(),
+        &info_hashes,
+        &old_statuses as &[&str],
+        new_status.as_str(),
+        inet
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+}
+
+#[async_trait::async_trait]
+impl Repository for AuditRepository {
+    async fn append_audit_entry(&self, cmd: AppendAuditEntry<'_>) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+        append_entry(&mut tx, cmd.actor, cmd.action, cmd.target_id, cmd.details).await;
+        tx.commit().await.unwrap();
+
+        Ok(())
+    }
+
+    async fn list_audit_entries(
+        &self,
+        cmd: ListAuditEntries<'_>,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        let entries = sqlx::query!(
+            "
+SELECT id, actor, action, info_hash, old_status, new_status, target_id, details, ip, created_ts
+FROM audit_log
+WHERE ($1::bigint IS NULL OR actor = $1)
+  AND ($2::bytea IS NULL OR info_hash = $2)
+  AND ($3::timestamptz IS NULL OR created_ts >= $3)
+  AND ($4::timestamptz IS NULL OR created_ts < $4)
+  AND ($5::bigint IS NULL OR id < $5)
+ORDER BY id DESC
+LIMIT $6
+",
+            cmd.actor,
+            cmd.info_hash.map(|i| &i.0),
+            cmd.since,
+            cmd.until,
+            cmd.before_id,
+            cmd.limit
+        )
+        .map(|r| AuditEntry {
+            id: r.id,
+            actor: r.actor,
+            action: r.action.parse().unwrap(),
+            info_hash: r.info_hash.map(InfoHash),
+            old_status: r.old_status.map(|s| s.parse().unwrap()),
+            new_status: r.new_status.map(|s| s.parse().unwrap()),
+            target_id: r.target_id,
+            details: r.details,
+            created_ts: r.created_ts,
+            ip: r.ip.ip(),
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,113 @@
+use hanekawa_common::repository::{
+    ban::{BanRepository as Repository, CreateBan, DeleteBan, ListBans},
+    Error,
+};
+use hanekawa_common::types::{AuditAction, Ban, BanRule};
+
+use sqlx::postgres::PgPool;
+
+#[derive(Clone)]
+pub struct BanRepository {
+    pool: PgPool,
+}
+
+impl BanRepository {
+    pub(super) fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for BanRepository {
+    async fn create_ban(&self, cmd: CreateBan<'_>) -> Result<Ban, Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let ban = sqlx::query!(
+            "
+INSERT INTO bans(kind, value, reason, expires_ts)
+VALUES ($1, $2, $3, $4)
+RETURNING id, kind, value, reason, created_ts, expires_ts
+",
+            cmd.rule.kind(),
+            cmd.rule.value(),
+            cmd.reason,
+            cmd.expires_ts
+        )
+        .map(|r| Ban {
+            id: r.id,
+            rule: BanRule::from_parts(&r.kind, &r.value).unwrap(),
+            reason: r.reason,
+            created_ts: r.created_ts,
+            expires_ts: r.expires_ts,
+        })
+        .fetch_one(&mut tx)
+        .await
+        .unwrap();
+
+        crate::audit::append_entry(
+            &mut tx,
+            cmd.actor,
+            AuditAction::CreateBan,
+            Some(ban.id),
+            Some(&format!("{} {}", ban.rule.kind(), ban.rule.value())),
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        Ok(ban)
+    }
+
+    async fn list_bans(&self, cmd: ListBans) -> Result<Vec<Ban>, Error> {
+        let bans = sqlx::query!(
+            "
+SELECT id, kind, value, reason, created_ts, expires_ts
+FROM bans
+WHERE $1::timestamptz IS NULL OR expires_ts IS NULL OR expires_ts > $1
+ORDER BY id
+",
+            cmd.active_at
+        )
+        .map(|r| Ban {
+            id: r.id,
+            rule: BanRule::from_parts(&r.kind, &r.value).unwrap(),
+            reason: r.reason,
+            created_ts: r.created_ts,
+            expires_ts: r.expires_ts,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(bans)
+    }
+
+    async fn delete_ban(&self, cmd: DeleteBan) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let result = sqlx::query!(
+            "
+DELETE FROM bans
+WHERE id = $1
+",
+            cmd.id
+        )
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            crate::audit::append_entry(
+                &mut tx,
+                cmd.actor,
+                AuditAction::DeleteBan,
+                Some(cmd.id),
+                None,
+            )
+            .await;
+        }
+        tx.commit().await.unwrap();
+
+        Ok(deleted)
+    }
+}
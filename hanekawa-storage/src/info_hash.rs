@@ -1,10 +1,17 @@
+use std::collections::HashMap;
+
 use hanekawa_common::repository::{
-    info_hash::{GetInfoHashSummary, InfoHashRepository as Repository, UpdateInfoHash},
+    info_hash::{
+        GetInfoHashSummary, GetTorrentFlags, GetTorrentNames, InfoHashRepository as Repository,
+        ListInfoHashes, RegisterTorrent, SetTorrentFlags, UpdateInfoHash, UpdateInfoHashes,
+    },
     Error,
 };
-use hanekawa_common::types::{InfoHashStatus, InfoHashSummary};
+use hanekawa_common::types::{
+    AuditAction, InfoHash, InfoHashStatus, InfoHashSummary, TorrentFlags,
+};
 
-use sqlx::postgres::PgPool;
+use sqlx::{postgres::PgPool, Postgres, Transaction};
 
 #[derive(Clone)]
 pub struct InfoHashRepository {
@@ -17,6 +24,99 @@ impl InfoHashRepository {
     }
 }
 
+fn status(is_allowed: Option<bool>) -> InfoHashStatus {
+    match is_allowed {
+        Some(true) => InfoHashStatus::ExplicitAllow,
+        Some(false) => InfoHashStatus::ExplicitDeny,
+        None => InfoHashStatus::Unknown,
+    }
+}
+
+/// Set the status of each distinct info hash, returning the status each
+/// had before.
+async fn update_statuses(
+    tx: &mut Transaction<'_, Postgres>,
+    info_hashes: &[Vec<u8>],
+    new_status: &InfoHashStatus,
+) -> Vec<InfoHashSummary> {
+    // Lock the stored rows so the previous statuses in the audit log are
+    // the ones this update actually replaced.
+    sqlx::query!(
+        "
+SELECT info_hash
+FROM info_hashes
+WHERE info_hash = ANY($1)
+FOR UPDATE
+",
+        info_hashes
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
+
+    // Each query reads the previous statuses from the snapshot taken
+    // before its own modification.
+    if let InfoHashStatus::Unknown = new_status {
+        sqlx::query!(
+            "
+WITH input AS (
+    SELECT DISTINCT unnest($1::bytea[]) AS info_hash
+), previous AS (
+    SELECT input.info_hash, info_hashes.is_allowed
+    FROM input
+    LEFT JOIN info_hashes USING (info_hash)
+), deleted AS (
+    DELETE FROM info_hashes
+    WHERE info_hash IN (SELECT info_hash FROM input)
+)
+SELECT info_hash AS \"info_hash!\", is_allowed
+FROM previous
+",
+            info_hashes
+        )
+        .map(|r| InfoHashSummary {
+            info_hash: InfoHash(r.info_hash),
+            status: status(r.is_allowed),
+            name: None,
+        })
+        .fetch_all(&mut *tx)
+        .await
+        .unwrap()
+    } else {
+        let is_allowed = *new_status == InfoHashStatus::ExplicitAllow;
+
+        sqlx::query!(
+            "
+WITH input AS (
+    SELECT DISTINCT unnest($1::bytea[]) AS info_hash
+), previous AS (
+    SELECT input.info_hash, info_hashes.is_allowed
+    FROM input
+    LEFT JOIN info_hashes USING (info_hash)
+), upserted AS (
+    INSERT INTO info_hashes(info_hash, is_allowed)
+    SELECT info_hash, $2
+    FROM input
+    ON CONFLICT (info_hash) DO UPDATE
+    SET is_allowed = $2
+)
+SELECT info_hash AS \"info_hash!\", is_allowed
+FROM previous
+",
+            info_hashes,
+            is_allowed
+        )
+        .map(|r| InfoHashSummary {
+            info_hash: InfoHash(r.info_hash),
+            status: status(r.is_allowed),
+            name: None,
+        })
+        .fetch_all(&mut *tx)
+        .await
+        .unwrap()
+    }
+}
+
 #[async_trait::async_trait]
 impl Repository for InfoHashRepository {
     async fn get_info_hash_summary(
@@ -38,6 +138,7 @@ WHERE info_hash = $1
             } else {
                 InfoHashStatus::ExplicitDeny
             },
+            name: None,
         })
         .fetch_optional(&self.pool)
         .await
@@ -46,6 +147,7 @@ WHERE info_hash = $1
         Ok(result.unwrap_or(InfoHashSummary {
             info_hash: cmd.info_hash.clone(),
             status: InfoHashStatus::Unknown,
+            name: None,
         }))
     }
 
@@ -81,4 +183,177 @@ SET is_allowed = $2
 
         Ok(())
     }
+
+    async fn update_info_hashes(
+        &self,
+        cmd: UpdateInfoHashes<'_>,
+    ) -> Result<Vec<InfoHashSummary>, Error> {
+        let info_hashes: Vec<_> = cmd.info_hashes.iter().map(|i| i.0.clone()).collect();
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let previous = update_statuses(&mut tx, &info_hashes, &cmd.status).await;
+
+        crate::audit::append_info_hash_entries(
+            &mut tx,
+            cmd.actor,
+            cmd.action,
+            &previous,
+            &cmd.status,
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        Ok(previous)
+    }
+
+    async fn list_info_hashes(
+        &self,
+        cmd: ListInfoHashes<'_>,
+    ) -> Result<Vec<InfoHashSummary>, Error> {
+        let is_allowed = match cmd.status {
+            None => None,
+            Some(InfoHashStatus::ExplicitAllow) => Some(true),
+            Some(InfoHashStatus::ExplicitDeny) => Some(false),
+            // Unknown info hashes are exactly those not stored.
+            Some(InfoHashStatus::Unknown) => return Ok(vec![]),
+        };
+
+        let info_hashes = sqlx::query!(
+            "
+SELECT info_hash, is_allowed, name AS \"name?\"
+FROM info_hashes
+LEFT JOIN torrents USING (info_hash)
+WHERE ($1::boolean IS NULL OR is_allowed = $1)
+  AND ($2::bytea IS NULL OR info_hash > $2)
+ORDER BY info_hash
+LIMIT $3
+",
+            is_allowed,
+            cmd.after.map(|i| &i.0),
+            cmd.limit
+        )
+        .map(|r| InfoHashSummary {
+            info_hash: InfoHash(r.info_hash),
+            status: status(Some(r.is_allowed)),
+            name: r.name,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(info_hashes)
+    }
+
+    async fn get_torrent_flags(&self, cmd: GetTorrentFlags<'_>) -> Result<TorrentFlags, Error> {
+        let flags = sqlx::query!(
+            "
+SELECT freeleech, double_upload
+FROM torrent_flags
+WHERE info_hash = $1
+",
+            &cmd.info_hash.0
+        )
+        .map(|r| TorrentFlags {
+            freeleech: r.freeleech,
+            double_upload: r.double_upload,
+        })
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(flags.unwrap_or_default())
+    }
+
+    async fn set_torrent_flags(&self, cmd: SetTorrentFlags<'_>) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        sqlx::query!(
+            "
+INSERT INTO torrent_flags(info_hash, freeleech, double_upload)
+VALUES ($1, $2, $3)
+ON CONFLICT (info_hash) DO UPDATE
+  SET freeleech = $2, double_upload = $3
+",
+            &cmd.info_hash.0,
+            cmd.flags.freeleech,
+            cmd.flags.double_upload
+        )
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+        crate::audit::append_torrent_entry(
+            &mut tx,
+            cmd.actor,
+            AuditAction::SetTorrentFlags,
+            cmd.info_hash,
+            Some(&format!(
+                "freeleech={} double_upload={}",
+                cmd.flags.freeleech, cmd.flags.double_upload
+            )),
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        Ok(())
+    }
+
+    async fn register_torrent(&self, cmd: RegisterTorrent<'_>) -> Result<(), Error> {
+        let info_hashes: Vec<_> = cmd.info_hashes.iter().map(|i| i.0.clone()).collect();
+        let metadata = cmd.metadata;
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let previous = update_statuses(&mut tx, &info_hashes, &cmd.status).await;
+        crate::audit::append_info_hash_entries(
+            &mut tx,
+            cmd.actor,
+            AuditAction::UploadTorrent,
+            &previous,
+            &cmd.status,
+        )
+        .await;
+
+        sqlx::query!(
+            "
+INSERT INTO torrents(info_hash, name, total_length, file_count, is_private, version)
+SELECT DISTINCT unnest($1::bytea[]), $2, $3, $4, $5, $6
+ON CONFLICT (info_hash) DO UPDATE
+  SET name = $2, total_length = $3, file_count = $4, is_private = $5, version = $6
+",
+            &info_hashes,
+            metadata.name,
+            metadata.total_length as i64,
+            metadata.file_count as i64,
+            metadata.private,
+            metadata.version.as_str()
+        )
+        .execute(&mut tx)
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        Ok(())
+    }
+
+    async fn get_torrent_names(
+        &self,
+        cmd: GetTorrentNames<'_>,
+    ) -> Result<HashMap<InfoHash, String>, Error> {
+        let info_hashes: Vec<_> = cmd.info_hashes.iter().map(|i| i.0.clone()).collect();
+
+        let names = sqlx::query!(
+            "
+SELECT info_hash, name
+FROM torrents
+WHERE info_hash = ANY($1)
+",
+            &info_hashes
+        )
+        .map(|r| (InfoHash(r.info_hash), r.name))
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(names.into_iter().collect())
+    }
 }
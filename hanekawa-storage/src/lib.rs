@@ -1,14 +1,27 @@
 use hanekawa_common::Config;
 
-use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
 use sqlx::ConnectOptions;
 
+pub mod api_key;
+pub mod audit;
+pub mod ban;
 pub mod info_hash;
 pub mod peer;
+pub mod schedule;
+pub mod seeding;
+pub mod user;
 
 pub struct Services {
+    pub pool: PgPool,
+    pub api_key: api_key::ApiKeyRepository,
+    pub audit: audit::AuditRepository,
+    pub ban: ban::BanRepository,
     pub peer: peer::PeerRepository,
     pub info_hash: info_hash::InfoHashRepository,
+    pub schedule: schedule::ScheduleRepository,
+    pub seeding: seeding::SeedingRepository,
+    pub user: user::UserRepository,
 }
 
 impl Services {
@@ -25,9 +38,25 @@ impl Services {
 
         sqlx::migrate!().run(&pool).await.unwrap();
 
+        let api_key = api_key::ApiKeyRepository::new(pool.clone());
+        let audit = audit::AuditRepository::new(pool.clone());
+        let ban = ban::BanRepository::new(pool.clone());
         let peer = peer::PeerRepository::new(pool.clone(), cfg);
-        let info_hash = info_hash::InfoHashRepository::new(pool);
-
-        Self { peer, info_hash }
+        let info_hash = info_hash::InfoHashRepository::new(pool.clone());
+        let schedule = schedule::ScheduleRepository::new(pool.clone());
+        let seeding = seeding::SeedingRepository::new(pool.clone());
+        let user = user::UserRepository::new(pool.clone());
+
+        Self {
+            pool,
+            api_key,
+            audit,
+            ban,
+            peer,
+            info_hash,
+            schedule,
+            seeding,
+            user,
+        }
     }
 }
@@ -1,15 +1,22 @@
 use hanekawa_common::{
+    accounting::transfer_delta,
     repository::{
-        peer::{GetPeerStatistics, GetPeers, PeerRepository as Repository, UpdatePeerAnnounce},
+        peer::{
+            CountPeerIdPrefixes, GetPeerDetails, GetPeerStatistics, GetPeers, ListTorrents,
+            PeerRepository as Repository, PurgePeers, UpdatePeerAnnounce,
+        },
         Error,
     },
-    types::{InfoHash, Peer, PeerId, PeerStatistics},
+    types::{
+        Event, InfoHash, Peer, PeerDetails, PeerId, PeerStatistics, TorrentFlags, TorrentSummary,
+    },
     Config,
 };
 
-use sqlx::postgres::PgPool;
+use sqlx::postgres::{PgPool, Postgres};
 use sqlx::types::ipnetwork::IpNetwork;
 use sqlx::types::time::OffsetDateTime;
+use sqlx::Transaction;
 use std::collections::HashMap;
 
 #[derive(Clone)]
@@ -23,6 +30,48 @@ impl Repository for PeerRepository {
     async fn update_peer_announce(&self, cmd: &UpdatePeerAnnounce) -> Result<(), Error> {
         let inet: IpNetwork = cmd.ip.clone().into();
 
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let previous = sqlx::query!(
+            "
+SELECT uploaded, downloaded, last_update_ts
+FROM peer_announces
+WHERE info_hash = $1 AND peer_id = $2
+FOR UPDATE
+",
+            &cmd.info_hash.0,
+            &cmd.peer_id.0
+        )
+        .fetch_optional(&mut tx)
+        .await
+        .unwrap();
+
+        // Tasks can be redelivered or run out of order, and applying an
+        // older announce would be mistaken for a counter reset.
+        if let Some(ts) = previous.as_ref().and_then(|p| p.last_update_ts) {
+            if ts >= cmd.update_timestamp {
+                return Ok(());
+            }
+        }
+
+        // Count a snatch once per user or peer, not on repeated completed
+        // announces.
+        if cmd.event == Event::Completed {
+            sqlx::query!(
+                "
+INSERT INTO torrent_snatches(info_hash, peer_id, user_id)
+VALUES ($1, $2, $3)
+ON CONFLICT DO NOTHING
+",
+                &cmd.info_hash.0,
+                &cmd.peer_id.0,
+                cmd.user_id
+            )
+            .execute(&mut tx)
+            .await
+            .unwrap();
+        }
+
         sqlx::query!(
             "
 INSERT INTO peer_announces(
@@ -34,9 +83,10 @@ INSERT INTO peer_announces(
   downloaded,
   remaining,
   event,
-  last_update_ts
+  last_update_ts,
+  user_id
 )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
 ON CONFLICT (info_hash, peer_id) DO UPDATE
   SET
     ip = $3,
@@ -45,22 +95,96 @@ ON CONFLICT (info_hash, peer_id) DO UPDATE
     downloaded = $6,
     remaining = $7,
     event = $8,
-    last_update_ts = $9;
+    last_update_ts = $9,
+    user_id = $10;
 ",
             &cmd.info_hash.0,
             &cmd.peer_id.0,
             &inet,
             cmd.port as i32,
-            cmd.uploaded as i64,
-            cmd.downloaded as i64,
-            cmd.left as i64,
+            bigint(cmd.uploaded),
+            bigint(cmd.downloaded),
+            bigint(cmd.left),
             cmd.event.to_string(),
-            OffsetDateTime::now_utc()
+            cmd.update_timestamp,
+            cmd.user_id
         )
-        .execute(&self.pool)
+        .execute(&mut tx)
         .await
         .unwrap();
 
+        if let Some(user_id) = cmd.user_id {
+            // Counters stored before they were range checked may be
+            // negative, and are then treated as missing.
+            let uploaded = transfer_delta(
+                previous
+                    .as_ref()
+                    .and_then(|p| u64::try_from(p.uploaded).ok()),
+                cmd.uploaded,
+                &cmd.event,
+            );
+            let downloaded = transfer_delta(
+                previous
+                    .as_ref()
+                    .and_then(|p| u64::try_from(p.downloaded).ok()),
+                cmd.downloaded,
+                &cmd.event,
+            );
+
+            let flags = sqlx::query!(
+                "
+SELECT freeleech, double_upload
+FROM torrent_flags
+WHERE info_hash = $1
+",
+                &cmd.info_hash.0
+            )
+            .map(|r| TorrentFlags {
+                freeleech: r.freeleech,
+                double_upload: r.double_upload,
+            })
+            .fetch_optional(&mut tx)
+            .await
+            .unwrap()
+            .unwrap_or_default();
+
+            let (credited_uploaded, credited_downloaded) = flags.credit(uploaded, downloaded);
+
+            // Totals saturate rather than overflow.
+            sqlx::query!(
+                "
+INSERT INTO user_torrents(
+  user_id,
+  info_hash,
+  uploaded,
+  downloaded,
+  credited_uploaded,
+  credited_downloaded
+)
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (user_id, info_hash) DO UPDATE
+  SET
+    uploaded = LEAST(user_torrents.uploaded::numeric + $3, 9223372036854775807)::bigint,
+    downloaded = LEAST(user_torrents.downloaded::numeric + $4, 9223372036854775807)::bigint,
+    credited_uploaded = LEAST(user_torrents.credited_uploaded::numeric + $5, 9223372036854775807)::bigint,
+    credited_downloaded = LEAST(user_torrents.credited_downloaded::numeric + $6, 9223372036854775807)::bigint
+",
+                user_id,
+                &cmd.info_hash.0,
+                bigint(uploaded),
+                bigint(downloaded),
+                bigint(credited_uploaded),
+                bigint(credited_downloaded)
+            )
+            .execute(&mut tx)
+            .await
+            .unwrap();
+
+            self.record_seeding(&mut tx, user_id, cmd).await;
+        }
+
+        tx.commit().await.unwrap();
+
         Ok(())
     }
 
@@ -127,6 +251,147 @@ GROUP BY info_hash
 
         Ok(result.into_iter().collect())
     }
+
+    async fn purge_peers(&self, cmd: PurgePeers) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            "
+DELETE FROM peer_announces
+WHERE last_update_ts < $1
+",
+            cmd.inactive_before
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(result.rows_affected())
+    }
+
+    async fn list_torrents(&self, cmd: ListTorrents) -> Result<Vec<TorrentSummary>, Error> {
+        let torrents = sqlx::query!(
+            "
+WITH swarms AS (
+  SELECT
+    info_hash,
+    COUNT(*) FILTER (WHERE remaining =  0) AS seeders,
+    COUNT(*) FILTER (WHERE remaining <> 0) AS leechers,
+    MAX(last_update_ts) AS last_announce_ts
+  FROM peer_announces
+  WHERE last_update_ts > $1
+  GROUP BY info_hash
+), snatch_counts AS (
+  SELECT info_hash, COUNT(*) AS snatches
+  FROM torrent_snatches
+  WHERE info_hash IN (SELECT info_hash FROM swarms)
+  GROUP BY info_hash
+)
+SELECT
+  info_hash,
+  seeders AS \"seeders!\",
+  leechers AS \"leechers!\",
+  COALESCE(snatches, 0) AS \"snatches!\",
+  last_announce_ts AS \"last_announce_ts!\",
+  name AS \"name?\"
+FROM swarms
+LEFT JOIN snatch_counts USING (info_hash)
+LEFT JOIN torrents USING (info_hash)
+ORDER BY
+  CASE $2
+    WHEN 'seeders' THEN seeders
+    WHEN 'leechers' THEN leechers
+    WHEN 'snatches' THEN COALESCE(snatches, 0)
+    WHEN 'last_announce' THEN EXTRACT(EPOCH FROM last_announce_ts)::bigint
+    ELSE seeders + leechers
+  END DESC,
+  info_hash
+LIMIT $3
+OFFSET $4
+",
+            cmd.active_after,
+            cmd.sort.as_str(),
+            cmd.limit,
+            cmd.offset
+        )
+        .map(|r| TorrentSummary {
+            info_hash: InfoHash(r.info_hash),
+            seeders: r.seeders as u64,
+            leechers: r.leechers as u64,
+            snatches: r.snatches as u64,
+            last_announce_ts: r.last_announce_ts,
+            name: r.name,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(torrents)
+    }
+
+    async fn get_peer_details(&self, cmd: GetPeerDetails<'_>) -> Result<Vec<PeerDetails>, Error> {
+        let peers = sqlx::query!(
+            "
+SELECT
+  peer_id,
+  ip,
+  port,
+  uploaded,
+  downloaded,
+  remaining,
+  event,
+  last_update_ts AS \"last_update_ts!\"
+FROM peer_announces
+WHERE
+  info_hash = $1
+  AND last_update_ts > $2
+ORDER BY last_update_ts DESC
+",
+            &cmd.info_hash.0,
+            cmd.active_after
+        )
+        .map(|r| {
+            let peer_id = PeerId(r.peer_id);
+
+            PeerDetails {
+                client: peer_id.client(),
+                peer_id,
+                ip: r.ip.ip(),
+                port: r.port as u16,
+                uploaded: r.uploaded as u64,
+                downloaded: r.downloaded as u64,
+                left: r.remaining as u64,
+                event: r.event.and_then(|e| e.parse().ok()),
+                last_announce_ts: r.last_update_ts,
+            }
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(peers)
+    }
+
+    async fn count_peer_id_prefixes(
+        &self,
+        cmd: CountPeerIdPrefixes,
+    ) -> Result<Vec<(PeerId, u64)>, Error> {
+        let prefixes = sqlx::query!(
+            "
+SELECT
+  substring(peer_id FROM 1 FOR 9) AS \"prefix!\",
+  COUNT(DISTINCT peer_id) AS \"peers!\"
+FROM peer_announces
+WHERE last_update_ts > $1
+GROUP BY 1
+",
+            cmd.active_after
+        )
+        .map(|r| (PeerId(r.prefix), r.peers as u64))
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(prefixes)
+    }
 }
 
 impl PeerRepository {
@@ -134,4 +399,97 @@ impl PeerRepository {
         let cfg = cfg.clone();
         Self { pool, cfg }
     }
+
+    /// Track a user's seeding sessions and seeding obligations.
+    async fn record_seeding(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: i64,
+        cmd: &UpdatePeerAnnounce,
+    ) {
+        // The first completion obliges the user to seed.
+        if cmd.event == Event::Completed {
+            sqlx::query!(
+                "
+INSERT INTO seeding_obligations(user_id, info_hash, completed_ts)
+VALUES ($1, $2, $3)
+ON CONFLICT (user_id, info_hash) DO NOTHING
+",
+                user_id,
+                &cmd.info_hash.0,
+                cmd.update_timestamp
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        }
+
+        // A session the user stopped announcing to ended at its last
+        // announce, rather than stretching across the gap.
+        let inactive_before = cmd.update_timestamp
+            - std::time::Duration::from_secs(self.cfg.peer_activity_timeout as u64);
+
+        sqlx::query!(
+            "
+UPDATE seeding_sessions
+SET ended_ts = last_announce_ts
+WHERE user_id = $1
+  AND info_hash = $2
+  AND ended_ts IS NULL
+  AND last_announce_ts <= $3
+",
+            user_id,
+            &cmd.info_hash.0,
+            inactive_before
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        let is_seeding = cmd.left == 0;
+
+        if is_seeding && cmd.event != Event::Stopped {
+            sqlx::query!(
+                "
+INSERT INTO seeding_sessions(user_id, info_hash, started_ts, last_announce_ts)
+VALUES ($1, $2, $3, $3)
+ON CONFLICT (user_id, info_hash) WHERE ended_ts IS NULL DO UPDATE
+  SET last_announce_ts = $3
+",
+                user_id,
+                &cmd.info_hash.0,
+                cmd.update_timestamp
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        } else {
+            // Stopping while seeding counts the time up to now; switching
+            // to leeching does not.
+            sqlx::query!(
+                "
+UPDATE seeding_sessions
+SET
+  last_announce_ts = CASE WHEN $4 THEN $3 ELSE last_announce_ts END,
+  ended_ts = CASE WHEN $4 THEN $3 ELSE last_announce_ts END
+WHERE user_id = $1
+  AND info_hash = $2
+  AND ended_ts IS NULL
+",
+                user_id,
+                &cmd.info_hash.0,
+                cmd.update_timestamp,
+                is_seeding
+            )
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        }
+    }
+}
+
+/// A byte count as a `bigint`, saturating at `i64::MAX`. Announced counters
+/// are range checked, but credits can exceed them.
+fn bigint(bytes: u64) -> i64 {
+    i64::try_from(bytes).unwrap_or(i64::MAX)
 }
@@ -0,0 +1,42 @@
+use hanekawa_common::repository::{
+    schedule::{ClaimScheduledRun, ScheduleRepository as Repository},
+    Error,
+};
+
+use sqlx::postgres::PgPool;
+
+#[derive(Clone)]
+pub struct ScheduleRepository {
+    pool: PgPool,
+}
+
+impl ScheduleRepository {
+    pub(super) fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for ScheduleRepository {
+    async fn claim_scheduled_run(&self, cmd: ClaimScheduledRun<'_>) -> Result<bool, Error> {
+        // The row lock taken by the upsert serializes competing nodes, and
+        // only the first sees a run which is due.
+        let claimed = sqlx::query!(
+            "
+INSERT INTO scheduled_jobs(name, next_run_ts)
+VALUES ($1, now() + make_interval(secs => $2))
+ON CONFLICT (name) DO UPDATE
+  SET next_run_ts = now() + make_interval(secs => $2)
+  WHERE scheduled_jobs.next_run_ts <= now()
+RETURNING name
+",
+            cmd.name,
+            cmd.every.as_secs_f64()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(claimed.is_some())
+    }
+}
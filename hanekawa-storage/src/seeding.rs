@@ -0,0 +1,111 @@
+use hanekawa_common::repository::{
+    seeding::{ListSeedingObligations, ResolveSeedingObligations, SeedingRepository as Repository},
+    Error,
+};
+use hanekawa_common::types::{InfoHash, SeedingObligation};
+
+use sqlx::postgres::PgPool;
+
+#[derive(Clone)]
+pub struct SeedingRepository {
+    pool: PgPool,
+}
+
+impl SeedingRepository {
+    pub(super) fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for SeedingRepository {
+    async fn list_seeding_obligations(
+        &self,
+        cmd: ListSeedingObligations,
+    ) -> Result<Vec<SeedingObligation>, Error> {
+        // Only seeding after completion counts towards an obligation.
+        let obligations = sqlx::query!(
+            "
+SELECT
+  o.user_id,
+  o.info_hash,
+  o.completed_ts,
+  o.status,
+  o.resolved_ts,
+  COALESCE(s.seeded_seconds, 0)::bigint AS \"seeded_seconds!\",
+  COALESCE(s.is_seeding, false) AS \"is_seeding!\",
+  COALESCE(t.uploaded, 0) AS \"uploaded!\",
+  COALESCE(t.downloaded, 0) AS \"downloaded!\"
+FROM seeding_obligations o
+LEFT JOIN LATERAL (
+  SELECT
+    SUM(EXTRACT(EPOCH FROM last_announce_ts - GREATEST(started_ts, o.completed_ts))) AS seeded_seconds,
+    bool_or(ended_ts IS NULL AND last_announce_ts > $3) AS is_seeding
+  FROM seeding_sessions
+  WHERE user_id = o.user_id
+    AND info_hash = o.info_hash
+    AND last_announce_ts > o.completed_ts
+) s ON true
+LEFT JOIN user_torrents t
+  ON t.user_id = o.user_id AND t.info_hash = o.info_hash
+WHERE ($1::bigint IS NULL OR o.user_id = $1)
+  AND ($2::text IS NULL OR o.status = $2)
+ORDER BY o.completed_ts, o.user_id
+",
+            cmd.user_id,
+            cmd.status.map(|s| s.as_str()),
+            cmd.active_after
+        )
+        .map(|r| SeedingObligation {
+            user_id: r.user_id,
+            info_hash: InfoHash(r.info_hash),
+            completed_ts: r.completed_ts,
+            status: r.status.parse().unwrap(),
+            resolved_ts: r.resolved_ts,
+            seeded_seconds: r.seeded_seconds as u64,
+            is_seeding: r.is_seeding,
+            uploaded: r.uploaded as u64,
+            downloaded: r.downloaded as u64,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(obligations)
+    }
+
+    async fn resolve_seeding_obligations(
+        &self,
+        cmd: ResolveSeedingObligations<'_>,
+    ) -> Result<u64, Error> {
+        let mut user_ids = Vec::with_capacity(cmd.resolutions.len());
+        let mut info_hashes = Vec::with_capacity(cmd.resolutions.len());
+        let mut statuses = Vec::with_capacity(cmd.resolutions.len());
+
+        for (user_id, info_hash, status) in cmd.resolutions {
+            user_ids.push(*user_id);
+            info_hashes.push(info_hash.0.clone());
+            statuses.push(status.as_str());
+        }
+
+        let result = sqlx::query!(
+            "
+UPDATE seeding_obligations o
+SET status = r.status, resolved_ts = $4
+FROM unnest($1::bigint[], $2::bytea[], $3::text[]) AS r(user_id, info_hash, status)
+WHERE o.user_id = r.user_id
+  AND o.info_hash = r.info_hash
+  AND o.status = 'pending'
+",
+            &user_ids,
+            &info_hashes,
+            &statuses as &[&str],
+            cmd.resolved_ts
+        )
+        .execute(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(result.rows_affected())
+    }
+}
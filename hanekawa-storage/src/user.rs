@@ -0,0 +1,166 @@
+use hanekawa_common::repository::{
+    user::{
+        CreateUser, GetUserByPasskey, GetUserStats, RevokePasskey, UserRepository as Repository,
+    },
+    Error,
+};
+use hanekawa_common::types::{AuditAction, User, UserStats};
+
+use sqlx::postgres::PgPool;
+
+#[derive(Clone)]
+pub struct UserRepository {
+    pool: PgPool,
+}
+
+impl UserRepository {
+    pub(super) fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl Repository for UserRepository {
+    async fn create_user(&self, cmd: CreateUser<'_>) -> Result<Option<User>, Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let user = sqlx::query!(
+            "
+INSERT INTO users(name, passkey)
+VALUES ($1, $2)
+ON CONFLICT (name) DO NOTHING
+RETURNING id, name, passkey, created_ts, revoked_ts
+",
+            cmd.name,
+            cmd.passkey
+        )
+        .map(|r| User {
+            id: r.id,
+            name: r.name,
+            passkey: r.passkey,
+            created_ts: r.created_ts,
+            revoked_ts: r.revoked_ts,
+        })
+        .fetch_optional(&mut tx)
+        .await
+        .unwrap();
+
+        if let Some(user) = &user {
+            crate::audit::append_entry(
+                &mut tx,
+                cmd.actor,
+                AuditAction::CreateUser,
+                Some(user.id),
+                Some(&user.name),
+            )
+            .await;
+        }
+        tx.commit().await.unwrap();
+
+        Ok(user)
+    }
+
+    async fn get_user_by_passkey(&self, cmd: GetUserByPasskey<'_>) -> Result<Option<User>, Error> {
+        let user = sqlx::query!(
+            "
+SELECT id, name, passkey, created_ts, revoked_ts
+FROM users
+WHERE passkey = $1
+",
+            cmd.passkey
+        )
+        .map(|r| User {
+            id: r.id,
+            name: r.name,
+            passkey: r.passkey,
+            created_ts: r.created_ts,
+            revoked_ts: r.revoked_ts,
+        })
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(user)
+    }
+
+    async fn list_users(&self) -> Result<Vec<User>, Error> {
+        let users = sqlx::query!(
+            "
+SELECT id, name, passkey, created_ts, revoked_ts
+FROM users
+ORDER BY id
+"
+        )
+        .map(|r| User {
+            id: r.id,
+            name: r.name,
+            passkey: r.passkey,
+            created_ts: r.created_ts,
+            revoked_ts: r.revoked_ts,
+        })
+        .fetch_all(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(users)
+    }
+
+    async fn revoke_passkey(&self, cmd: RevokePasskey) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await.unwrap();
+
+        let result = sqlx::query!(
+            "
+UPDATE users
+SET revoked_ts = now()
+WHERE id = $1 AND revoked_ts IS NULL
+",
+            cmd.user_id
+        )
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+        let revoked = result.rows_affected() > 0;
+        if revoked {
+            crate::audit::append_entry(
+                &mut tx,
+                cmd.actor,
+                AuditAction::RevokePasskey,
+                Some(cmd.user_id),
+                None,
+            )
+            .await;
+        }
+        tx.commit().await.unwrap();
+
+        Ok(revoked)
+    }
+
+    async fn get_user_stats(&self, cmd: GetUserStats) -> Result<Option<UserStats>, Error> {
+        let stats = sqlx::query!(
+            "
+SELECT
+  COALESCE(SUM(t.uploaded), 0)::bigint AS \"uploaded!\",
+  COALESCE(SUM(t.downloaded), 0)::bigint AS \"downloaded!\",
+  COALESCE(SUM(t.credited_uploaded), 0)::bigint AS \"credited_uploaded!\",
+  COALESCE(SUM(t.credited_downloaded), 0)::bigint AS \"credited_downloaded!\"
+FROM users u
+LEFT JOIN user_torrents t ON t.user_id = u.id
+WHERE u.id = $1
+GROUP BY u.id
+",
+            cmd.user_id
+        )
+        .map(|r| UserStats {
+            uploaded: r.uploaded as u64,
+            downloaded: r.downloaded as u64,
+            credited_uploaded: r.credited_uploaded as u64,
+            credited_downloaded: r.credited_downloaded as u64,
+        })
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap();
+
+        Ok(stats)
+    }
+}
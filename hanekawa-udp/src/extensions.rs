@@ -12,13 +12,12 @@ use nom::{
     IResult,
 };
 
-fn parse_three_part_option(input: &[u8]) -> IResult<&[u8], (u8, String)> {
+fn parse_three_part_option(input: &[u8]) -> IResult<&[u8], (u8, Vec<u8>)> {
     let (input, id) = be_u8(input)?;
     let (input, len) = be_u8(input)?;
     let (input, bs) = take(len)(input)?;
-    let s = std::str::from_utf8(bs).unwrap().to_string();
 
-    Ok((input, (id, s)))
+    Ok((input, (id, bs.to_vec())))
 }
 
 pub(super) fn parse_extensions(input: &[u8]) -> IResult<&[u8], Vec<Extension>> {
@@ -85,11 +84,11 @@ mod test {
     fn parses_urldata() {
         let mut buf = BytesMut::new();
 
-        let opts = "/announce?peer_id=1".to_string();
+        let opts = b"/announce?peer_id=1".to_vec();
 
         buf.put_u8(2);
         buf.put_u8(opts.len() as u8);
-        buf.put_slice(opts.as_bytes());
+        buf.put_slice(&opts);
 
         assert_eq!(
             Ok((&[] as &[u8], vec![Extension::UrlData(opts)])),
@@ -101,11 +100,11 @@ mod test {
     fn parses_unknown_data() {
         let mut buf = BytesMut::new();
 
-        let opts = "mystery".to_string();
+        let opts = b"mystery".to_vec();
 
         buf.put_u8(127);
         buf.put_u8(opts.len() as u8);
-        buf.put_slice(opts.as_bytes());
+        buf.put_slice(&opts);
 
         assert_eq!(
             Ok((&[] as &[u8], vec![Extension::Unknown(127, opts)])),
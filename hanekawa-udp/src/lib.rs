@@ -1,7 +1,7 @@
 mod extensions;
 
 use hanekawa::udp_tracker::proto::*;
-use hanekawa_common::types::Event;
+use hanekawa_common::types::{Event, InfoHash, PeerId};
 
 use extensions::parse_extensions;
 
@@ -39,11 +39,8 @@ fn encode_connect_response(resp: &ConnectResponse, buf: &mut BytesMut) {
     buf.put_i64(resp.connection_id);
 }
 
-fn parse_20_bit_string(input: &[u8]) -> IResult<&[u8], String> {
-    let (input, bts) = take(20_usize)(input)?;
-    let s = std::str::from_utf8(bts).unwrap();
-
-    Ok((input, s.to_string()))
+fn parse_20_bytes(input: &[u8]) -> IResult<&[u8], Vec<u8>> {
+    map(take(20_usize), <[u8]>::to_vec)(input)
 }
 
 fn parse_event(input: &[u8]) -> IResult<&[u8], Option<Event>> {
@@ -92,8 +89,8 @@ fn parse_announce_request(input: &[u8]) -> IResult<&[u8], AnnounceRequest> {
         be_i64,
         tag(1_u32.to_be_bytes()),
         be_i32,
-        parse_20_bit_string,
-        parse_20_bit_string,
+        map(parse_20_bytes, InfoHash),
+        map(parse_20_bytes, PeerId),
         be_i64,
         be_i64,
         be_i64,
@@ -143,7 +140,7 @@ fn parse_scrape_request(input: &[u8]) -> IResult<&[u8], ScrapeRequest> {
         be_i64,
         tag(2_u32.to_be_bytes()),
         be_i32,
-        many1(parse_20_bit_string),
+        many1(map(parse_20_bytes, InfoHash)),
     ))(input)?;
 
     Ok((
@@ -241,8 +238,8 @@ mod test {
                 AnnounceRequest {
                     connection_id: 42,
                     transaction_id: 32,
-                    info_hash: info_hash.to_string(),
-                    peer_id: peer_id.to_string(),
+                    info_hash: InfoHash(info_hash.as_bytes().to_vec()),
+                    peer_id: PeerId(peer_id.as_bytes().to_vec()),
                     downloaded: 3,
                     left: 4,
                     uploaded: 5,
@@ -258,6 +255,22 @@ mod test {
         )
     }
 
+    #[test]
+    fn parses_binary_info_hashes() {
+        let mut buf = BytesMut::new();
+
+        let info_hash = [0xff_u8; 20];
+
+        buf.put_i64(42);
+        buf.put_i32(2);
+        buf.put_i32(32);
+        buf.put_slice(&info_hash);
+
+        let (_, request) = parse_scrape_request(&buf).unwrap();
+
+        assert_eq!(vec![InfoHash(info_hash.to_vec())], request.info_hashes);
+    }
+
     #[test]
     fn parses_scrape_request() {
         let mut buf = BytesMut::new();
@@ -267,7 +280,7 @@ mod test {
 
         let mut hashes = Vec::new();
         for _ in 0..num_hashes {
-            hashes.push(info_hash.clone());
+            hashes.push(InfoHash(info_hash.as_bytes().to_vec()));
         }
 
         buf.put_i64(42);
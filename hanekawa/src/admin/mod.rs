@@ -1,20 +1,95 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::net::IpAddr;
 
 use hanekawa_common::{
-    repository::info_hash::{InfoHashRepository, UpdateInfoHash},
-    types::{InfoHash, InfoHashStatus},
-    Config,
+    client::Client,
+    metainfo::Metainfo,
+    repository::{
+        api_key::{CreateApiKey, EnsureApiKey, GetApiKey, RevokeApiKey},
+        audit::{Actor, AppendAuditEntry, ListAuditEntries},
+        ban::{CreateBan, DeleteBan, ListBans},
+        info_hash::{
+            GetTorrentFlags, ListInfoHashes, RegisterTorrent, SetTorrentFlags, UpdateInfoHashes,
+        },
+        peer::{CountPeerIdPrefixes, GetPeerDetails, ListTorrents},
+        seeding::ListSeedingObligations,
+        user::{CreateUser, GetUserStats, RevokePasskey},
+    },
+    task::DeadLetter,
+    types::{
+        ApiKey, ApiKeyScope, AuditAction, AuditEntry, Ban, BanRule, InfoHash, InfoHashError,
+        InfoHashStatus, InfoHashSummary, ObligationStatus, PeerDetails, SeedingObligation,
+        TorrentFlags, TorrentMetadata, TorrentSort, TorrentSummary, User, UserStats,
+    },
+    Config, Services,
 };
 
+use time::OffsetDateTime;
+
+use crate::ban::BanList;
+
+use sha2::{Digest, Sha256};
+
 #[derive(Debug)]
 pub enum Error {
     NotAllowed,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    InvalidInfoHash {
+        value: String,
+        reason: InfoHashError,
+    },
+    InvalidRequest(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAllowed | Self::NotFound => f.write_str("not found"),
+            Self::Unauthorized => f.write_str("missing or invalid api key"),
+            Self::Forbidden => f.write_str("api key lacks the required scope"),
+            Self::InvalidInfoHash { value, reason } => {
+                f.write_fmt(format_args!("invalid info hash {:?}: {}", value, reason))
+            }
+            Self::InvalidRequest(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn dead_letter_limit_out_of_range() -> Error {
+    Error::InvalidRequest("dead letter limit out of range".to_string())
+}
+
+fn parse_info_hash(hex: String) -> Result<InfoHash, Error> {
+    InfoHash::from_hex(&hex).map_err(|reason| Error::InvalidInfoHash { value: hex, reason })
 }
 
 #[derive(Clone)]
 pub struct AdminService {
     config: Config,
-    info_hash_repository: Arc<dyn InfoHashRepository>,
+    services: Services,
+    bans: BanList,
+}
+
+/// An authenticated admin API caller.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub api_key_id: i64,
+    pub scope: ApiKeyScope,
+    /// The address the request came from, recorded in the audit log.
+    pub ip: IpAddr,
+}
+
+impl Caller {
+    fn actor(&self) -> Actor {
+        Actor {
+            api_key_id: self.api_key_id,
+            ip: self.ip,
+        }
+    }
 }
 
 pub struct KnownInfoHashRequest {
@@ -22,34 +97,1074 @@ pub struct KnownInfoHashRequest {
     pub action: InfoHashStatus,
 }
 
+pub struct InfoHashesRequest {
+    pub status: Option<InfoHashStatus>,
+    pub after_hex_info_hash: Option<String>,
+    /// `None` lists every info hash.
+    pub limit: Option<i64>,
+}
+
+pub struct ImportInfoHashesRequest {
+    pub entries: Vec<(String, InfoHashStatus)>,
+}
+
+pub struct UploadTorrentRequest {
+    /// The contents of a `.torrent` file.
+    pub torrent: Vec<u8>,
+    pub status: InfoHashStatus,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct UploadedTorrent {
+    /// The hex SHA-1 info hash, for v1 and hybrid torrents.
+    pub info_hash_v1: Option<String>,
+    /// The hex SHA-256 info hash, for v2 and hybrid torrents. Clients
+    /// announce it truncated to its first 20 bytes.
+    pub info_hash_v2: Option<String>,
+    #[serde(flatten)]
+    pub metadata: TorrentMetadata,
+}
+
+pub struct TorrentsRequest {
+    pub sort: TorrentSort,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+pub struct PeersRequest {
+    pub hex_info_hash: String,
+}
+
+pub struct TorrentFlagsRequest {
+    pub hex_info_hash: String,
+}
+
+pub struct SetTorrentFlagsRequest {
+    pub hex_info_hash: String,
+    pub flags: TorrentFlags,
+}
+
+pub struct AuditLogRequest {
+    pub actor: Option<i64>,
+    pub hex_info_hash: Option<String>,
+    pub since: Option<OffsetDateTime>,
+    pub until: Option<OffsetDateTime>,
+    pub before_id: Option<i64>,
+    pub limit: i64,
+}
+
+pub struct BansRequest {
+    pub include_expired: bool,
+}
+
+pub struct CreateBanRequest {
+    pub rule: BanRule,
+    pub reason: String,
+    pub expires_ts: Option<OffsetDateTime>,
+}
+
+pub struct DeleteBanRequest {
+    pub id: i64,
+}
+
+pub struct DeadLettersRequest {
+    pub limit: usize,
+}
+
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scope: ApiKeyScope,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// The bearer token for the key. Only its hash is stored, so it
+    /// cannot be retrieved again.
+    pub token: String,
+}
+
+/// How many active peers use a client version.
+#[derive(Debug, serde::Serialize)]
+pub struct ClientUsage {
+    /// `None` for peers whose peer IDs follow no known convention.
+    pub client: Option<Client>,
+    pub peers: u64,
+}
+
+pub struct RevokeApiKeyRequest {
+    pub id: i64,
+}
+
+pub struct CreateUserRequest {
+    pub name: String,
+}
+
+pub struct RevokePasskeyRequest {
+    pub user_id: i64,
+}
+
+pub struct SeedingObligationsRequest {
+    pub user_id: Option<i64>,
+    pub status: Option<ObligationStatus>,
+}
+
+pub struct UserStatsRequest {
+    pub user_id: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct UserStatsResponse {
+    #[serde(flatten)]
+    pub stats: UserStats,
+    pub ratio: Option<f64>,
+}
+
+const TOKEN_PREFIX: &str = "hkw_";
+
+fn generate_token() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0_u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("{}{}", TOKEN_PREFIX, hex::encode(bytes))
+}
+
+/// Passkeys appear in announce URLs, so are stored as-is rather than hashed.
+fn generate_passkey() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0_u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    hex::encode(bytes)
+}
+
+// Tokens are long and random, so a fast unsalted hash is sufficient.
+fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
 impl AdminService {
-    pub fn new(config: &Config, info_hash_repository: Arc<dyn InfoHashRepository>) -> Self {
+    pub fn new(config: &Config, services: Services, bans: BanList) -> Self {
         let config = config.clone();
 
         Self {
             config,
-            info_hash_repository,
+            services,
+            bans,
+        }
+    }
+
+    /// Register the configured bootstrap token as a write-scoped key, so
+    /// that further keys can be created through the API. Does nothing while
+    /// the admin API is disabled.
+    pub async fn bootstrap(&self) {
+        if !self.config.enable_admin_api {
+            return;
+        }
+
+        if let Some(token) = &self.config.admin_bootstrap_token {
+            self.services
+                .api_key_repository
+                .ensure_api_key(EnsureApiKey {
+                    name: "bootstrap",
+                    scope: ApiKeyScope::Write,
+                    key_hash: &hash_token(token),
+                })
+                .await
+                .unwrap();
         }
     }
 
+    pub async fn authenticate(&self, token: &str, ip: IpAddr) -> Result<Caller, Error> {
+        if !self.config.enable_admin_api {
+            return Err(Error::NotAllowed);
+        }
+
+        if token.is_empty() {
+            return Err(Error::Unauthorized);
+        }
+
+        let api_key = self
+            .services
+            .api_key_repository
+            .get_api_key(GetApiKey {
+                key_hash: &hash_token(token),
+            })
+            .await
+            .unwrap();
+
+        match api_key {
+            Some(api_key) if api_key.revoked_ts.is_none() => Ok(Caller {
+                api_key_id: api_key.id,
+                scope: api_key.scope,
+                ip,
+            }),
+            _ => Err(Error::Unauthorized),
+        }
+    }
+
+    fn authorize(&self, caller: &Caller, scope: ApiKeyScope) -> Result<(), Error> {
+        if !self.config.enable_admin_api {
+            return Err(Error::NotAllowed);
+        }
+
+        if caller.scope < scope {
+            return Err(Error::Forbidden);
+        }
+
+        Ok(())
+    }
+
     pub async fn known_info_hash_command(
         &self,
+        caller: &Caller,
         command: KnownInfoHashRequest,
     ) -> Result<(), Error> {
-        if !self.config.enable_admin_api {
-            return Err(Error::NotAllowed);
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let info_hash = parse_info_hash(command.hex_info_hash)?;
+
+        self.set_info_hash_statuses(
+            caller,
+            AuditAction::UpdateInfoHash,
+            &[info_hash],
+            command.action,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    pub async fn info_hashes(
+        &self,
+        caller: &Caller,
+        request: InfoHashesRequest,
+    ) -> Result<Vec<InfoHashSummary>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let after = request
+            .after_hex_info_hash
+            .map(parse_info_hash)
+            .transpose()?;
+
+        Ok(self
+            .services
+            .info_hash_repository
+            .list_info_hashes(ListInfoHashes {
+                status: request.status,
+                after: after.as_ref(),
+                limit: request.limit,
+            })
+            .await
+            .unwrap())
+    }
+
+    /// Apply many status changes, with one query per distinct status.
+    /// Returns the number of distinct info hashes changed.
+    pub async fn import_info_hashes(
+        &self,
+        caller: &Caller,
+        request: ImportInfoHashesRequest,
+    ) -> Result<usize, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        // Validate everything up front so a bad entry changes nothing.
+        let entries = request
+            .entries
+            .into_iter()
+            .map(|(hex, status)| Ok((parse_info_hash(hex)?, status)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Entries apply in order, so a later entry for the same info hash
+        // wins.
+        let mut statuses = HashMap::new();
+        for (info_hash, status) in entries {
+            statuses.insert(info_hash, status);
+        }
+
+        for status in [
+            InfoHashStatus::ExplicitAllow,
+            InfoHashStatus::ExplicitDeny,
+            InfoHashStatus::Unknown,
+        ] {
+            let info_hashes: Vec<_> = statuses
+                .iter()
+                .filter(|(_, s)| **s == status)
+                .map(|(info_hash, _)| info_hash.clone())
+                .collect();
+
+            if !info_hashes.is_empty() {
+                self.set_info_hash_statuses(
+                    caller,
+                    AuditAction::ImportInfoHashes,
+                    &info_hashes,
+                    status,
+                )
+                .await;
+            }
         }
 
-        let info_hash = InfoHash::from_hex(command.hex_info_hash);
+        Ok(statuses.len())
+    }
+
+    /// Validate a `.torrent` file, then set the status of and record the
+    /// metadata under every info hash clients may announce it with.
+    pub async fn upload_torrent(
+        &self,
+        caller: &Caller,
+        request: UploadTorrentRequest,
+    ) -> Result<UploadedTorrent, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let metainfo = Metainfo::from_bytes(&request.torrent)
+            .map_err(|err| Error::InvalidRequest(format!("invalid torrent: {}", err)))?;
+
+        let metadata = TorrentMetadata {
+            name: metainfo.info.name.clone(),
+            total_length: metainfo.total_length(),
+            file_count: metainfo.file_count() as u64,
+            private: metainfo.info.private,
+            version: metainfo.version(),
+        };
+
+        let info_hashes: Vec<_> = metainfo
+            .info_hash_v1
+            .iter()
+            .cloned()
+            .chain(
+                metainfo
+                    .info_hash_v2
+                    .iter()
+                    .map(|v2| InfoHash(v2.0[..InfoHash::V1_LEN].to_vec())),
+            )
+            .collect();
+
+        self.services
+            .info_hash_repository
+            .register_torrent(RegisterTorrent {
+                info_hashes: &info_hashes,
+                metadata: &metadata,
+                status: request.status,
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
 
-        self.info_hash_repository
-            .update_info_hash(UpdateInfoHash {
+        Ok(UploadedTorrent {
+            info_hash_v1: metainfo.info_hash_v1.map(|i| i.to_hex()),
+            info_hash_v2: metainfo.info_hash_v2.map(|i| i.to_hex()),
+            metadata,
+        })
+    }
+
+    async fn set_info_hash_statuses(
+        &self,
+        caller: &Caller,
+        action: AuditAction,
+        info_hashes: &[InfoHash],
+        status: InfoHashStatus,
+    ) {
+        self.services
+            .info_hash_repository
+            .update_info_hashes(UpdateInfoHashes {
+                info_hashes,
+                status,
+                action,
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
+    }
+
+    /// The start of the window in which peers count as active.
+    fn active_after(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+            - std::time::Duration::from_secs(self.config.peer_activity_timeout as u64)
+    }
+
+    pub async fn torrents(
+        &self,
+        caller: &Caller,
+        request: TorrentsRequest,
+    ) -> Result<Vec<TorrentSummary>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        Ok(self
+            .services
+            .peer_repository
+            .list_torrents(ListTorrents {
+                active_after: self.active_after(),
+                sort: request.sort,
+                limit: request.limit,
+                offset: request.offset,
+            })
+            .await
+            .unwrap())
+    }
+
+    pub async fn peers(
+        &self,
+        caller: &Caller,
+        request: PeersRequest,
+    ) -> Result<Vec<PeerDetails>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let info_hash = parse_info_hash(request.hex_info_hash)?;
+
+        Ok(self
+            .services
+            .peer_repository
+            .get_peer_details(GetPeerDetails {
+                info_hash: &info_hash,
+                active_after: self.active_after(),
+            })
+            .await
+            .unwrap())
+    }
+
+    pub async fn torrent_flags(
+        &self,
+        caller: &Caller,
+        request: TorrentFlagsRequest,
+    ) -> Result<TorrentFlags, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let info_hash = parse_info_hash(request.hex_info_hash)?;
+
+        Ok(self
+            .services
+            .info_hash_repository
+            .get_torrent_flags(GetTorrentFlags {
+                info_hash: &info_hash,
+            })
+            .await
+            .unwrap())
+    }
+
+    pub async fn set_torrent_flags(
+        &self,
+        caller: &Caller,
+        request: SetTorrentFlagsRequest,
+    ) -> Result<(), Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let info_hash = parse_info_hash(request.hex_info_hash)?;
+
+        self.services
+            .info_hash_repository
+            .set_torrent_flags(SetTorrentFlags {
                 info_hash: &info_hash,
-                status: command.action,
+                flags: request.flags,
+                actor: caller.actor(),
             })
             .await
             .unwrap();
 
         Ok(())
     }
+
+    pub async fn clients(&self, caller: &Caller) -> Result<Vec<ClientUsage>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let prefixes = self
+            .services
+            .peer_repository
+            .count_peer_id_prefixes(CountPeerIdPrefixes {
+                active_after: self.active_after(),
+            })
+            .await
+            .unwrap();
+
+        let mut usage: Vec<ClientUsage> = Vec::new();
+
+        for (prefix, peers) in prefixes {
+            let client = prefix.client();
+
+            match usage.iter_mut().find(|u| u.client == client) {
+                Some(u) => u.peers += peers,
+                None => usage.push(ClientUsage { client, peers }),
+            }
+        }
+
+        usage.sort_by_key(|u| std::cmp::Reverse(u.peers));
+
+        Ok(usage)
+    }
+
+    pub async fn audit_log(
+        &self,
+        caller: &Caller,
+        request: AuditLogRequest,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let info_hash = request.hex_info_hash.map(parse_info_hash).transpose()?;
+
+        Ok(self
+            .services
+            .audit_repository
+            .list_audit_entries(ListAuditEntries {
+                actor: request.actor,
+                info_hash: info_hash.as_ref(),
+                since: request.since,
+                until: request.until,
+                before_id: request.before_id,
+                limit: request.limit,
+            })
+            .await
+            .unwrap())
+    }
+
+    pub async fn bans(&self, caller: &Caller, request: BansRequest) -> Result<Vec<Ban>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let active_at = if request.include_expired {
+            None
+        } else {
+            Some(OffsetDateTime::now_utc())
+        };
+
+        Ok(self
+            .services
+            .ban_repository
+            .list_bans(ListBans { active_at })
+            .await
+            .unwrap())
+    }
+
+    pub async fn create_ban(
+        &self,
+        caller: &Caller,
+        request: CreateBanRequest,
+    ) -> Result<Ban, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        if let BanRule::PeerId(prefix) = &request.rule {
+            if prefix.is_empty() {
+                return Err(Error::InvalidRequest(
+                    "peer id prefix must not be empty".to_string(),
+                ));
+            }
+        }
+
+        let ban = self
+            .services
+            .ban_repository
+            .create_ban(CreateBan {
+                rule: &request.rule,
+                reason: &request.reason,
+                expires_ts: request.expires_ts,
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
+
+        // Other tracker processes pick the ban up on their next refresh.
+        self.bans.refresh().await;
+
+        Ok(ban)
+    }
+
+    pub async fn delete_ban(
+        &self,
+        caller: &Caller,
+        request: DeleteBanRequest,
+    ) -> Result<(), Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let deleted = self
+            .services
+            .ban_repository
+            .delete_ban(DeleteBan {
+                id: request.id,
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
+
+        if !deleted {
+            return Err(Error::NotFound);
+        }
+
+        self.bans.refresh().await;
+
+        Ok(())
+    }
+
+    pub async fn dead_letters(
+        &self,
+        caller: &Caller,
+        request: DeadLettersRequest,
+    ) -> Result<Vec<DeadLetter>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        self.services
+            .dead_letter_queue
+            .dead_letters(request.limit)
+            .await
+            .ok_or_else(dead_letter_limit_out_of_range)
+    }
+
+    pub async fn replay_dead_letters(
+        &self,
+        caller: &Caller,
+        request: DeadLettersRequest,
+    ) -> Result<usize, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let replayed = self
+            .services
+            .dead_letter_queue
+            .replay_dead_letters(request.limit)
+            .await
+            .ok_or_else(dead_letter_limit_out_of_range)?;
+
+        // The queue is not in the database, so this cannot share a
+        // transaction with the replay.
+        if replayed > 0 {
+            self.services
+                .audit_repository
+                .append_audit_entry(AppendAuditEntry {
+                    actor: caller.actor(),
+                    action: AuditAction::ReplayDeadLetters,
+                    target_id: None,
+                    details: Some(&format!("replayed {} dead letters", replayed)),
+                })
+                .await
+                .unwrap();
+        }
+
+        Ok(replayed)
+    }
+
+    pub async fn api_keys(&self, caller: &Caller) -> Result<Vec<ApiKey>, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        Ok(self
+            .services
+            .api_key_repository
+            .list_api_keys()
+            .await
+            .unwrap())
+    }
+
+    pub async fn create_api_key(
+        &self,
+        caller: &Caller,
+        request: CreateApiKeyRequest,
+    ) -> Result<CreatedApiKey, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let token = generate_token();
+
+        let api_key = self
+            .services
+            .api_key_repository
+            .create_api_key(CreateApiKey {
+                name: &request.name,
+                scope: request.scope,
+                key_hash: &hash_token(&token),
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
+
+        Ok(CreatedApiKey { api_key, token })
+    }
+
+    pub async fn revoke_api_key(
+        &self,
+        caller: &Caller,
+        request: RevokeApiKeyRequest,
+    ) -> Result<(), Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let revoked = self
+            .services
+            .api_key_repository
+            .revoke_api_key(RevokeApiKey {
+                id: request.id,
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
+
+        if revoked {
+            Ok(())
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    pub async fn users(&self, caller: &Caller) -> Result<Vec<User>, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        Ok(self.services.user_repository.list_users().await.unwrap())
+    }
+
+    pub async fn create_user(
+        &self,
+        caller: &Caller,
+        request: CreateUserRequest,
+    ) -> Result<User, Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        if request.name.trim().is_empty() {
+            return Err(Error::InvalidRequest(
+                "user name cannot be empty".to_string(),
+            ));
+        }
+
+        self.services
+            .user_repository
+            .create_user(CreateUser {
+                name: &request.name,
+                passkey: &generate_passkey(),
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap()
+            .ok_or_else(|| Error::InvalidRequest(format!("user already exists: {}", request.name)))
+    }
+
+    pub async fn revoke_passkey(
+        &self,
+        caller: &Caller,
+        request: RevokePasskeyRequest,
+    ) -> Result<(), Error> {
+        self.authorize(caller, ApiKeyScope::Write)?;
+
+        let revoked = self
+            .services
+            .user_repository
+            .revoke_passkey(RevokePasskey {
+                user_id: request.user_id,
+                actor: caller.actor(),
+            })
+            .await
+            .unwrap();
+
+        if revoked {
+            Ok(())
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    pub async fn user_stats(
+        &self,
+        caller: &Caller,
+        request: UserStatsRequest,
+    ) -> Result<UserStatsResponse, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        let stats = self
+            .services
+            .user_repository
+            .get_user_stats(GetUserStats {
+                user_id: request.user_id,
+            })
+            .await
+            .unwrap()
+            .ok_or(Error::NotFound)?;
+
+        Ok(UserStatsResponse {
+            ratio: stats.ratio(),
+            stats,
+        })
+    }
+
+    pub async fn seeding_obligations(
+        &self,
+        caller: &Caller,
+        request: SeedingObligationsRequest,
+    ) -> Result<Vec<SeedingObligation>, Error> {
+        self.authorize(caller, ApiKeyScope::Read)?;
+
+        Ok(self
+            .services
+            .seeding_repository
+            .list_seeding_obligations(ListSeedingObligations {
+                user_id: request.user_id,
+                status: request.status,
+                active_after: self.active_after(),
+            })
+            .await
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use hanekawa_common::testing::{self, Memory};
+
+    const BOOTSTRAP_TOKEN: &str = "hkw_bootstrap";
+
+    fn admin_service(config: &Config) -> (Arc<Memory>, AdminService) {
+        let memory = Memory::new();
+        let services = memory.services();
+        let bans = BanList::new(services.clone());
+
+        (memory, AdminService::new(config, services, bans))
+    }
+
+    fn enabled_config() -> Config {
+        Config {
+            enable_admin_api: true,
+            admin_bootstrap_token: Some(BOOTSTRAP_TOKEN.to_string()),
+            ..testing::config()
+        }
+    }
+
+    async fn write_caller(admin: &AdminService) -> Caller {
+        admin.bootstrap().await;
+        admin
+            .authenticate(BOOTSTRAP_TOKEN, IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn bootstraps_only_when_enabled() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let (memory, admin) = admin_service(&Config {
+            enable_admin_api: false,
+            ..enabled_config()
+        });
+        admin.bootstrap().await;
+        assert!(memory.state().api_keys.is_empty());
+
+        let (memory, admin) = admin_service(&enabled_config());
+        admin.bootstrap().await;
+        admin.bootstrap().await;
+        assert_eq!(1, memory.state().api_keys.len());
+
+        let caller = admin.authenticate(BOOTSTRAP_TOKEN, ip).await.unwrap();
+        assert_eq!(ApiKeyScope::Write, caller.scope);
+    }
+
+    #[tokio::test]
+    async fn audits_api_key_changes() {
+        let (memory, admin) = admin_service(&enabled_config());
+        let caller = write_caller(&admin).await;
+
+        let created = admin
+            .create_api_key(
+                &caller,
+                CreateApiKeyRequest {
+                    name: "reader".to_string(),
+                    scope: ApiKeyScope::Read,
+                },
+            )
+            .await
+            .unwrap();
+        let id = created.api_key.id;
+        admin
+            .revoke_api_key(&caller, RevokeApiKeyRequest { id })
+            .await
+            .unwrap();
+        assert!(matches!(
+            admin
+                .revoke_api_key(&caller, RevokeApiKeyRequest { id })
+                .await,
+            Err(Error::NotFound)
+        ));
+
+        assert_eq!(
+            vec![
+                (AuditAction::CreateApiKey, Some(id)),
+                (AuditAction::RevokeApiKey, Some(id)),
+            ],
+            memory.state().audit_log
+        );
+    }
+
+    #[tokio::test]
+    async fn imports_later_entries_over_earlier_ones() {
+        let (memory, admin) = admin_service(&enabled_config());
+        let caller = write_caller(&admin).await;
+        let a = "aa".repeat(20);
+        let b = "bb".repeat(20);
+
+        let imported = admin
+            .import_info_hashes(
+                &caller,
+                ImportInfoHashesRequest {
+                    entries: vec![
+                        (a.clone(), InfoHashStatus::ExplicitDeny),
+                        (b.clone(), InfoHashStatus::ExplicitDeny),
+                        (a.clone(), InfoHashStatus::ExplicitAllow),
+                        (b.clone(), InfoHashStatus::Unknown),
+                    ],
+                },
+            )
+            .await
+            .unwrap();
+
+        let state = memory.state();
+        assert_eq!(2, imported);
+        assert_eq!(
+            HashMap::from([(InfoHash::from_hex(&a).unwrap(), true)]),
+            state.info_hashes
+        );
+        assert_eq!(2, state.audit_log.len());
+    }
+
+    #[tokio::test]
+    async fn counts_duplicate_imports_once() {
+        let (memory, admin) = admin_service(&enabled_config());
+        let caller = write_caller(&admin).await;
+        let a = "aa".repeat(20);
+
+        let imported = admin
+            .import_info_hashes(
+                &caller,
+                ImportInfoHashesRequest {
+                    entries: vec![(a.clone(), InfoHashStatus::ExplicitAllow); 3],
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(1, imported);
+        assert_eq!(1, memory.state().info_hashes.len());
+    }
+
+    #[tokio::test]
+    async fn registers_uploaded_torrents_with_their_status() {
+        let (memory, admin) = admin_service(&enabled_config());
+        let caller = write_caller(&admin).await;
+
+        let mut torrent =
+            b"d4:infod6:lengthi10e4:name4:test12:piece lengthi16384e6:pieces20:".to_vec();
+        torrent.extend_from_slice(&[0; 20]);
+        torrent.extend_from_slice(b"ee");
+
+        let uploaded = admin
+            .upload_torrent(
+                &caller,
+                UploadTorrentRequest {
+                    torrent,
+                    status: InfoHashStatus::ExplicitAllow,
+                },
+            )
+            .await
+            .unwrap();
+
+        let info_hash = parse_info_hash(uploaded.info_hash_v1.unwrap()).unwrap();
+        let state = memory.state();
+        assert_eq!(Some(&true), state.info_hashes.get(&info_hash));
+        assert_eq!("test", state.torrents[&info_hash].name);
+        assert_eq!(10, state.torrents[&info_hash].total_length);
+        assert_eq!(
+            Some(&(AuditAction::UploadTorrent, None)),
+            state.audit_log.last()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_non_canonical_torrents() {
+        let (memory, admin) = admin_service(&enabled_config());
+        let caller = write_caller(&admin).await;
+
+        // The `length` key has a `+`-prefixed length.
+        let mut torrent =
+            b"d4:infod+6:lengthi10e4:name4:test12:piece lengthi16384e6:pieces20:".to_vec();
+        torrent.extend_from_slice(&[0; 20]);
+        torrent.extend_from_slice(b"ee");
+
+        let result = admin
+            .upload_torrent(
+                &caller,
+                UploadTorrentRequest {
+                    torrent,
+                    status: InfoHashStatus::ExplicitAllow,
+                },
+            )
+            .await;
+
+        assert!(matches!(result, Err(Error::InvalidRequest(_))));
+        assert!(memory.state().torrents.is_empty());
+        assert!(memory.state().info_hashes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn audits_user_changes() {
+        let (memory, admin) = admin_service(&enabled_config());
+        let caller = write_caller(&admin).await;
+
+        let user = admin
+            .create_user(
+                &caller,
+                CreateUserRequest {
+                    name: "alice".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(admin
+            .create_user(
+                &caller,
+                CreateUserRequest {
+                    name: "alice".to_string(),
+                },
+            )
+            .await
+            .is_err());
+        admin
+            .revoke_passkey(&caller, RevokePasskeyRequest { user_id: user.id })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (AuditAction::CreateUser, Some(user.id)),
+                (AuditAction::RevokePasskey, Some(user.id)),
+            ],
+            memory.state().audit_log
+        );
+    }
+
+    #[test]
+    fn generates_distinct_prefixed_tokens() {
+        let a = generate_token();
+        let b = generate_token();
+
+        assert!(a.starts_with(TOKEN_PREFIX));
+        assert_eq!(TOKEN_PREFIX.len() + 64, a.len());
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn reports_invalid_info_hashes() {
+        let err = parse_info_hash("nothex".to_string()).unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::InvalidInfoHash {
+                reason: InfoHashError::InvalidHex,
+                ..
+            }
+        ));
+        assert_eq!(
+            "invalid info hash \"nothex\": info hash is not valid hex",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn write_scope_implies_read_scope() {
+        assert!(ApiKeyScope::Write >= ApiKeyScope::Read);
+        assert!(ApiKeyScope::Read < ApiKeyScope::Write);
+    }
 }
@@ -0,0 +1,218 @@
+//! Announce handling shared by the HTTP and UDP trackers.
+
+use hanekawa_common::{
+    accounting::RatioAction,
+    repository::{
+        info_hash::GetInfoHashSummary,
+        peer::UpdatePeerAnnounce,
+        user::{GetUserByPasskey, GetUserStats},
+    },
+    task::Task,
+    types::{InfoHash, InfoHashStatus},
+    Config, Services,
+};
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct UpdatePeerAnnounceTask {
+    pub(crate) cmd: UpdatePeerAnnounce,
+}
+
+#[typetag::serde]
+#[async_trait::async_trait]
+impl Task for UpdatePeerAnnounceTask {
+    async fn execute(&self, ctx: &Services) -> Option<()> {
+        ctx.peer_repository
+            .update_peer_announce(&self.cmd)
+            .await
+            .unwrap();
+
+        Some(())
+    }
+}
+
+pub(crate) async fn is_info_hash_allowed(
+    config: &Config,
+    services: &Services,
+    info_hash: &InfoHash,
+) -> bool {
+    let info_hash_summary = services
+        .info_hash_repository
+        .get_info_hash_summary(GetInfoHashSummary { info_hash })
+        .await
+        .unwrap();
+
+    match info_hash_summary.status {
+        InfoHashStatus::ExplicitDeny => false,
+        InfoHashStatus::ExplicitAllow => true,
+        InfoHashStatus::Unknown => !config.only_allowed_info_hashes,
+    }
+}
+
+/// A transfer counter reported by a client. Counters are stored as
+/// `bigint`, so anything negative or above `i64::MAX` is rejected rather
+/// than wrapped into a value that would skew the user's ratio.
+pub(crate) fn transfer_counter<T>(name: &str, value: T) -> Result<u64, String>
+where
+    T: Copy + TryInto<u64> + TryInto<i64>,
+{
+    match (
+        TryInto::<u64>::try_into(value),
+        TryInto::<i64>::try_into(value),
+    ) {
+        (Ok(value), Ok(_)) => Ok(value),
+        _ => Err(format!("{name} out of range")),
+    }
+}
+
+/// The user a request is attributed to. Private mode requires a passkey;
+/// otherwise one is optional, but must still be valid if given.
+pub(crate) async fn authenticate_passkey(
+    config: &Config,
+    services: &Services,
+    passkey: Option<&str>,
+) -> Result<Option<i64>, String> {
+    let passkey = match passkey {
+        Some(passkey) => passkey,
+        None if config.private_mode => return Err("passkey required".to_string()),
+        None => return Ok(None),
+    };
+
+    let user = services
+        .user_repository
+        .get_user_by_passkey(GetUserByPasskey { passkey })
+        .await
+        .unwrap();
+
+    match user {
+        Some(user) if user.revoked_ts.is_none() => Ok(Some(user.id)),
+        Some(_) => Err("passkey revoked".to_string()),
+        None => Err("unknown passkey".to_string()),
+    }
+}
+
+/// Apply the ratio policy to a user who is leeching, returning a warning
+/// to pass on or why they are refused.
+pub(crate) async fn check_ratio(
+    config: &Config,
+    services: &Services,
+    user_id: Option<i64>,
+    left: u64,
+) -> Result<Option<String>, String> {
+    let user_id = match user_id {
+        Some(user_id) if left > 0 && config.ratio_policy.action != RatioAction::Off => user_id,
+        _ => return Ok(None),
+    };
+
+    let stats = services
+        .user_repository
+        .get_user_stats(GetUserStats { user_id })
+        .await
+        .unwrap()
+        .unwrap_or_default();
+
+    config.ratio_policy.check(&stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use hanekawa_common::repository::{
+        audit::Actor,
+        user::{CreateUser, RevokePasskey},
+    };
+    use hanekawa_common::testing::{self, Memory};
+
+    const ACTOR: Actor = Actor {
+        api_key_id: 1,
+        ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+    };
+
+    async fn create_user(services: &Services, name: &str, passkey: &str) -> i64 {
+        services
+            .user_repository
+            .create_user(CreateUser {
+                name,
+                passkey,
+                actor: ACTOR,
+            })
+            .await
+            .unwrap()
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn rejects_transfer_counters_out_of_range() {
+        assert_eq!(Ok(10), transfer_counter("uploaded", 10_i64));
+        assert_eq!(Ok(10), transfer_counter("uploaded", 10_u64));
+        assert_eq!(
+            Ok(i64::MAX as u64),
+            transfer_counter("uploaded", i64::MAX as u64)
+        );
+        assert_eq!(
+            Err("uploaded out of range".to_string()),
+            transfer_counter("uploaded", -1_i64)
+        );
+        assert_eq!(
+            Err("left out of range".to_string()),
+            transfer_counter("left", u64::MAX)
+        );
+    }
+
+    #[tokio::test]
+    async fn requires_passkeys_only_in_private_mode() {
+        let services = Memory::new().services();
+        let private = Config {
+            private_mode: true,
+            ..testing::config()
+        };
+
+        assert_eq!(
+            Ok(None),
+            authenticate_passkey(&testing::config(), &services, None).await
+        );
+        assert_eq!(
+            Err("passkey required".to_string()),
+            authenticate_passkey(&private, &services, None).await
+        );
+    }
+
+    #[tokio::test]
+    async fn authenticates_active_passkeys() {
+        let services = Memory::new().services();
+        let alice = create_user(&services, "alice", "alicekey").await;
+        let bob = create_user(&services, "bob", "bobkey").await;
+        services
+            .user_repository
+            .revoke_passkey(RevokePasskey {
+                user_id: bob,
+                actor: ACTOR,
+            })
+            .await
+            .unwrap();
+
+        for config in [
+            testing::config(),
+            Config {
+                private_mode: true,
+                ..testing::config()
+            },
+        ] {
+            assert_eq!(
+                Ok(Some(alice)),
+                authenticate_passkey(&config, &services, Some("alicekey")).await
+            );
+            assert_eq!(
+                Err("passkey revoked".to_string()),
+                authenticate_passkey(&config, &services, Some("bobkey")).await
+            );
+            assert_eq!(
+                Err("unknown passkey".to_string()),
+                authenticate_passkey(&config, &services, Some("nokey")).await
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+
+use hanekawa_common::{
+    repository::ban::ListBans,
+    types::{Ban, BanRule, Cidr, PeerId},
+    Services,
+};
+
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone)]
+struct BanEntry {
+    reason: String,
+    expires_ts: Option<OffsetDateTime>,
+}
+
+impl BanEntry {
+    fn is_active(&self, now: OffsetDateTime) -> bool {
+        match self.expires_ts {
+            Some(ts) => ts > now,
+            None => true,
+        }
+    }
+
+    /// Keep whichever of two overlapping bans lasts longer.
+    fn merge(self, other: Self) -> Self {
+        match (self.expires_ts, other.expires_ts) {
+            (None, _) => self,
+            (_, None) => other,
+            (Some(a), Some(b)) if a >= b => self,
+            _ => other,
+        }
+    }
+}
+
+/// Bans indexed for cheap lookups on every announce.
+#[derive(Debug, Default)]
+struct BanIndex {
+    /// Banned networks by prefix length, keyed by network address. A
+    /// lookup costs one hash probe per distinct prefix length.
+    networks: HashMap<u8, HashMap<IpAddr, BanEntry>>,
+    peer_id_prefixes: Vec<(Vec<u8>, BanEntry)>,
+}
+
+impl BanIndex {
+    fn new(bans: Vec<Ban>) -> Self {
+        let mut index = Self::default();
+
+        for ban in bans {
+            let entry = BanEntry {
+                reason: ban.reason,
+                expires_ts: ban.expires_ts,
+            };
+
+            match ban.rule {
+                BanRule::Network(cidr) => {
+                    let networks = index.networks.entry(cidr.prefix_len()).or_default();
+                    let entry = match networks.remove(&cidr.addr()) {
+                        Some(existing) => existing.merge(entry),
+                        None => entry,
+                    };
+                    networks.insert(cidr.addr(), entry);
+                }
+                BanRule::PeerId(prefix) => {
+                    index.peer_id_prefixes.push((prefix.into_bytes(), entry));
+                }
+            }
+        }
+
+        index
+    }
+
+    fn check(&self, ip: IpAddr, peer_id: &PeerId, now: OffsetDateTime) -> Option<&str> {
+        let network_ban = self.networks.iter().find_map(|(prefix_len, networks)| {
+            // Prefix lengths longer than the address family allows cannot match.
+            let network = Cidr::new(ip, *prefix_len).ok()?;
+            networks
+                .get(&network.addr())
+                .filter(|entry| entry.is_active(now))
+        });
+
+        let peer_id_ban = || {
+            self.peer_id_prefixes
+                .iter()
+                .find(|(prefix, entry)| peer_id.0.starts_with(prefix) && entry.is_active(now))
+                .map(|(_, entry)| entry)
+        };
+
+        network_ban
+            .or_else(peer_id_ban)
+            .map(|entry| entry.reason.as_str())
+    }
+}
+
+/// An in-memory copy of the active bans, shared by the tracker frontends
+/// and refreshed from the ban repository.
+#[derive(Clone)]
+pub struct BanList {
+    services: Services,
+    index: Arc<RwLock<BanIndex>>,
+}
+
+impl BanList {
+    pub fn new(services: Services) -> Self {
+        Self {
+            services,
+            index: Default::default(),
+        }
+    }
+
+    pub async fn refresh(&self) {
+        let bans = self
+            .services
+            .ban_repository
+            .list_bans(ListBans {
+                active_at: Some(OffsetDateTime::now_utc()),
+            })
+            .await
+            .unwrap();
+
+        *self.index.write().unwrap() = BanIndex::new(bans);
+    }
+
+    /// The reason a peer is banned, if it is.
+    pub fn check(&self, ip: IpAddr, peer_id: &PeerId) -> Option<String> {
+        self.index
+            .read()
+            .unwrap()
+            .check(ip, peer_id, OffsetDateTime::now_utc())
+            .map(ToString::to_string)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ban(rule: BanRule, reason: &str, expires_ts: Option<OffsetDateTime>) -> Ban {
+        Ban {
+            id: 0,
+            rule,
+            reason: reason.to_string(),
+            created_ts: OffsetDateTime::UNIX_EPOCH,
+            expires_ts,
+        }
+    }
+
+    #[test]
+    fn matches_networks_and_peer_id_prefixes() {
+        let index = BanIndex::new(vec![
+            ban(
+                BanRule::Network("10.0.0.0/8".parse().unwrap()),
+                "range",
+                None,
+            ),
+            ban(BanRule::Network("192.0.2.7".parse().unwrap()), "host", None),
+            ban(BanRule::PeerId("-XL".to_string()), "client", None),
+        ]);
+
+        let now = OffsetDateTime::now_utc();
+        let peer_id = PeerId(b"-qB4500-abcdefghijkl".to_vec());
+
+        assert_eq!(
+            Some("range"),
+            index.check("10.20.30.40".parse().unwrap(), &peer_id, now)
+        );
+        assert_eq!(
+            Some("host"),
+            index.check("192.0.2.7".parse().unwrap(), &peer_id, now)
+        );
+        assert_eq!(
+            None,
+            index.check("192.0.2.8".parse().unwrap(), &peer_id, now)
+        );
+        assert_eq!(
+            Some("client"),
+            index.check(
+                "192.0.2.8".parse().unwrap(),
+                &PeerId(b"-XL0012-abcdefghijkl".to_vec()),
+                now
+            )
+        );
+    }
+
+    #[test]
+    fn ignores_expired_bans() {
+        let now = OffsetDateTime::now_utc();
+        let earlier = now - std::time::Duration::from_secs(60);
+
+        let index = BanIndex::new(vec![
+            ban(
+                BanRule::Network("10.0.0.1".parse().unwrap()),
+                "old",
+                Some(earlier),
+            ),
+            ban(BanRule::PeerId("-XL".to_string()), "old", Some(earlier)),
+        ]);
+
+        let peer_id = PeerId(b"-XL0012-abcdefghijkl".to_vec());
+
+        assert_eq!(
+            None,
+            index.check("10.0.0.1".parse().unwrap(), &peer_id, now)
+        );
+    }
+}
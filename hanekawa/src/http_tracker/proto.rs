@@ -6,6 +6,11 @@ use std::{collections::HashMap, fmt::Display};
 pub enum Error {
     ServerError(String),
     InfoHashNotAllowed(String),
+    Banned(String),
+    ClientNotAllowed(String),
+    Unauthorized(String),
+    InsufficientRatio(String),
+    InvalidRequest(String),
     Other(String),
 }
 
@@ -14,6 +19,11 @@ impl Display for Error {
         match self {
             Self::ServerError(s) => f.write_fmt(format_args!("server error: {s}")),
             Self::InfoHashNotAllowed(s) => f.write_fmt(format_args!("info hash not allowed: {s}")),
+            Self::Banned(s) => f.write_fmt(format_args!("banned: {s}")),
+            Self::ClientNotAllowed(s) => f.write_fmt(format_args!("client not allowed: {s}")),
+            Self::Unauthorized(s) => f.write_fmt(format_args!("unauthorized: {s}")),
+            Self::InsufficientRatio(s) => f.write_fmt(format_args!("insufficient ratio: {s}")),
+            Self::InvalidRequest(s) => f.write_fmt(format_args!("invalid request: {s}")),
             Self::Other(s) => f.write_fmt(format_args!("error: {s}")),
         }
     }
@@ -42,6 +52,9 @@ pub enum PeerData {
 #[derive(serde::Serialize)]
 pub struct AnnounceResponse {
     pub interval: u32,
+    #[serde(rename = "warning message")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning_message: Option<String>,
     pub peers: PeerData,
     pub peers6: PeerData,
     #[serde(flatten)]
@@ -54,7 +67,16 @@ pub struct ScrapeRequest {
     pub info_hash: Vec<InfoHash>,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct ScrapeFile {
+    #[serde(flatten)]
+    pub stats: PeerStatistics,
+    /// The name of the torrent, if it was uploaded (BEP 48).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct ScrapeResponse {
-    pub files: HashMap<InfoHash, PeerStatistics>,
+    pub files: HashMap<InfoHash, ScrapeFile>,
 }
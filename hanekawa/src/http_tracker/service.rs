@@ -1,70 +1,69 @@
 use super::proto::{
-    AnnounceRequest, AnnounceResponse, Error, PeerData, ScrapeRequest, ScrapeResponse,
+    AnnounceRequest, AnnounceResponse, Error, PeerData, ScrapeFile, ScrapeRequest, ScrapeResponse,
 };
 
+use crate::announce::{
+    authenticate_passkey, check_ratio, is_info_hash_allowed, transfer_counter,
+    UpdatePeerAnnounceTask,
+};
+use crate::ban::BanList;
+
 use hanekawa_common::{
-    repository::{
-        info_hash::GetInfoHashSummary,
-        peer::{GetPeerStatistics, GetPeers, UpdatePeerAnnounce},
-    },
-    task::Task,
-    types::{InfoHashStatus, Peer},
+    repository::info_hash::GetTorrentNames,
+    repository::peer::{GetPeerStatistics, GetPeers, UpdatePeerAnnounce},
+    types::Peer,
     Config, Services,
 };
 
 use std::net::IpAddr;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct UpdatePeerAnnounceTask {
-    cmd: UpdatePeerAnnounce,
-}
-
-#[typetag::serde]
-#[async_trait::async_trait]
-impl Task for UpdatePeerAnnounceTask {
-    async fn execute(&self, ctx: &Services) -> Option<()> {
-        ctx.peer_repository
-            .update_peer_announce(&self.cmd)
-            .await
-            .unwrap();
-
-        Some(())
-    }
-}
-
 #[derive(Clone)]
 pub struct HttpTrackerService {
     config: Config,
     services: Services,
+    bans: BanList,
 }
 
 impl HttpTrackerService {
-    pub fn new(config: &Config, services: Services) -> Self {
+    pub fn new(config: &Config, services: Services, bans: BanList) -> Self {
         Self {
             config: config.clone(),
             services,
+            bans,
         }
     }
 
     pub async fn announce(
         &self,
         announce: AnnounceRequest,
+        passkey: Option<&str>,
         sender_ip: IpAddr,
     ) -> Result<AnnounceResponse, Error> {
-        let info_hash_summary = self
-            .services
-            .info_hash_repository
-            .get_info_hash_summary(GetInfoHashSummary {
-                info_hash: &announce.info_hash,
-            })
+        if let Some(reason) = self.bans.check(sender_ip, &announce.peer_id) {
+            return Err(Error::Banned(reason));
+        }
+
+        self.config
+            .client_policy
+            .check(&announce.peer_id)
+            .map_err(Error::ClientNotAllowed)?;
+
+        let uploaded =
+            transfer_counter("uploaded", announce.uploaded).map_err(Error::InvalidRequest)?;
+        let downloaded =
+            transfer_counter("downloaded", announce.downloaded).map_err(Error::InvalidRequest)?;
+        let left = transfer_counter("left", announce.left).map_err(Error::InvalidRequest)?;
+
+        let user_id = authenticate_passkey(&self.config, &self.services, passkey)
             .await
-            .unwrap();
+            .map_err(Error::Unauthorized)?;
+
+        let warning_message = check_ratio(&self.config, &self.services, user_id, left)
+            .await
+            .map_err(Error::InsufficientRatio)?;
 
-        if info_hash_summary.status == InfoHashStatus::ExplicitDeny
-            || (self.config.only_allowed_info_hashes
-                && info_hash_summary.status != InfoHashStatus::ExplicitAllow)
-        {
-            let st = info_hash_summary.info_hash.to_hex();
+        if !is_info_hash_allowed(&self.config, &self.services, &announce.info_hash).await {
+            let st = announce.info_hash.to_hex();
             return Err(Error::InfoHashNotAllowed(st));
         }
 
@@ -73,11 +72,12 @@ impl HttpTrackerService {
             peer_id: announce.peer_id.clone(),
             ip: sender_ip,
             port: announce.port,
-            uploaded: announce.uploaded,
-            downloaded: announce.downloaded,
-            left: announce.left,
+            uploaded,
+            downloaded,
+            left,
             event: announce.event,
             update_timestamp: time::OffsetDateTime::now_utc(),
+            user_id,
         };
 
         self.services
@@ -117,13 +117,22 @@ impl HttpTrackerService {
 
         Ok(AnnounceResponse {
             interval: self.config.peer_announce_interval,
+            warning_message,
             peers,
             peers6,
             stats,
         })
     }
 
-    pub async fn scrape(&self, request: ScrapeRequest) -> Result<ScrapeResponse, Error> {
+    pub async fn scrape(
+        &self,
+        request: ScrapeRequest,
+        passkey: Option<&str>,
+    ) -> Result<ScrapeResponse, Error> {
+        authenticate_passkey(&self.config, &self.services, passkey)
+            .await
+            .map_err(Error::Unauthorized)?;
+
         let active_after = time::OffsetDateTime::now_utc()
             - std::time::Duration::from_secs(self.config.peer_activity_timeout as u64);
 
@@ -132,13 +141,30 @@ impl HttpTrackerService {
             active_after,
         };
 
-        let files = self
+        let stats = self
             .services
             .peer_repository
             .get_peer_statistics(cmd)
             .await
             .unwrap();
 
+        let mut names = self
+            .services
+            .info_hash_repository
+            .get_torrent_names(GetTorrentNames {
+                info_hashes: &request.info_hash,
+            })
+            .await
+            .unwrap();
+
+        let files = stats
+            .into_iter()
+            .map(|(info_hash, stats)| {
+                let name = names.remove(&info_hash);
+                (info_hash, ScrapeFile { stats, name })
+            })
+            .collect();
+
         Ok(ScrapeResponse { files })
     }
 }
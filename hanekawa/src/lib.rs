@@ -1,3 +1,6 @@
 pub mod admin;
+mod announce;
+pub mod ban;
 pub mod http_tracker;
+pub mod schedule;
 pub mod udp_tracker;
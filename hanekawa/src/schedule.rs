@@ -0,0 +1,101 @@
+use hanekawa_common::{
+    hit_and_run::HitAndRunRules,
+    repository::{
+        peer::PurgePeers,
+        seeding::{ListSeedingObligations, ResolveSeedingObligations},
+    },
+    task::{ScheduleRegistry, Task},
+    types::ObligationStatus,
+    Config, Services,
+};
+
+use std::time::Duration;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct PurgeExpiredPeersTask {
+    peer_activity_timeout: u32,
+}
+
+#[typetag::serde]
+#[async_trait::async_trait]
+impl Task for PurgeExpiredPeersTask {
+    async fn execute(&self, ctx: &Services) -> Option<()> {
+        let inactive_before = time::OffsetDateTime::now_utc()
+            - Duration::from_secs(self.peer_activity_timeout as u64);
+
+        ctx.peer_repository
+            .purge_peers(PurgePeers { inactive_before })
+            .await
+            .unwrap();
+
+        Some(())
+    }
+}
+
+/// Resolve pending seeding obligations against the hit-and-run rules.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct DetectHitAndRunsTask {
+    rules: HitAndRunRules,
+    peer_activity_timeout: u32,
+}
+
+#[typetag::serde]
+#[async_trait::async_trait]
+impl Task for DetectHitAndRunsTask {
+    async fn execute(&self, ctx: &Services) -> Option<()> {
+        let now = time::OffsetDateTime::now_utc();
+
+        let obligations = ctx
+            .seeding_repository
+            .list_seeding_obligations(ListSeedingObligations {
+                user_id: None,
+                status: Some(ObligationStatus::Pending),
+                active_after: now - Duration::from_secs(self.peer_activity_timeout as u64),
+            })
+            .await
+            .unwrap();
+
+        let resolutions: Vec<_> = obligations
+            .into_iter()
+            .filter_map(|o| match self.rules.evaluate(&o, now) {
+                ObligationStatus::Pending => None,
+                status => Some((o.user_id, o.info_hash, status)),
+            })
+            .collect();
+
+        if !resolutions.is_empty() {
+            ctx.seeding_repository
+                .resolve_seeding_obligations(ResolveSeedingObligations {
+                    resolutions: &resolutions,
+                    resolved_ts: now,
+                })
+                .await
+                .unwrap();
+        }
+
+        Some(())
+    }
+}
+
+pub fn schedule(config: &Config) -> ScheduleRegistry {
+    let registry = ScheduleRegistry::new().every(
+        "purge_expired_peers",
+        Duration::from_secs(config.peer_purge_interval as u64),
+        PurgeExpiredPeersTask {
+            peer_activity_timeout: config.peer_activity_timeout,
+        },
+    );
+
+    if config.hit_and_run.enabled {
+        registry.every(
+            "detect_hit_and_runs",
+            Duration::from_secs(config.hit_and_run.check_interval as u64),
+            DetectHitAndRunsTask {
+                rules: config.hit_and_run.clone(),
+                peer_activity_timeout: config.peer_activity_timeout,
+            },
+        )
+    } else {
+        registry
+    }
+}
@@ -3,6 +3,9 @@
 #[derive(Debug, Eq, PartialEq)]
 pub enum Extension {
     Nop,
-    UrlData(String),
-    Unknown(u8, String),
+    /// Part of the announce URL's path and query. Clients may split it
+    /// across options anywhere, even inside a multi-byte character, so it
+    /// is only decoded once reassembled.
+    UrlData(Vec<u8>),
+    Unknown(u8, Vec<u8>),
 }
@@ -2,3 +2,6 @@
 
 mod extensions;
 pub mod proto;
+mod service;
+
+pub use service::UdpTrackerService;
@@ -1,5 +1,5 @@
 pub use super::extensions::Extension;
-use hanekawa_common::types::Event;
+use hanekawa_common::types::{Event, InfoHash, PeerId};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct ConnectRequest {
@@ -15,8 +15,8 @@ pub struct ConnectResponse {
 pub struct AnnounceRequest {
     pub connection_id: i64,
     pub transaction_id: i32,
-    pub info_hash: String,
-    pub peer_id: String,
+    pub info_hash: InfoHash,
+    pub peer_id: PeerId,
     pub downloaded: i64,
     pub left: i64,
     pub uploaded: i64,
@@ -40,7 +40,7 @@ pub struct AnnounceResponse {
 pub struct ScrapeRequest {
     pub connection_id: i64,
     pub transaction_id: i32,
-    pub info_hashes: Vec<String>,
+    pub info_hashes: Vec<InfoHash>,
 }
 
 pub struct InfoHashScrapeData {
@@ -0,0 +1,356 @@
+use super::proto::{
+    AnnounceRequest, AnnounceResponse, ConnectResponse, ErrorResponse, Extension,
+    InfoHashScrapeData, Request, Response, ScrapeRequest, ScrapeResponse,
+};
+
+use crate::announce::{
+    authenticate_passkey, check_ratio, is_info_hash_allowed, transfer_counter,
+    UpdatePeerAnnounceTask,
+};
+use crate::ban::BanList;
+
+use hanekawa_common::{
+    repository::peer::{GetPeerStatistics, GetPeers, UpdatePeerAnnounce},
+    Config, Services,
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use std::net::{IpAddr, SocketAddr};
+
+/// How long a connection ID stays valid for, in seconds. BEP 15 lets
+/// clients use one for a minute, so each is accepted for up to two.
+const CONNECTION_ID_WINDOW: u64 = 60;
+
+const DEFAULT_NUM_WANT: usize = 50;
+const MAX_NUM_WANT: usize = 200;
+
+#[derive(Clone)]
+pub struct UdpTrackerService {
+    config: Config,
+    services: Services,
+    bans: BanList,
+    /// Keys connection IDs, so clients cannot forge them.
+    connection_key: Hmac<Sha256>,
+}
+
+impl UdpTrackerService {
+    pub fn new(config: &Config, services: Services, bans: BanList) -> Self {
+        let secret = match &config.udp_connection_secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => {
+                use rand::RngCore;
+
+                let mut secret = vec![0_u8; 32];
+                rand::thread_rng().fill_bytes(&mut secret);
+                secret
+            }
+        };
+
+        Self {
+            config: config.clone(),
+            services,
+            bans,
+            connection_key: Hmac::new_from_slice(&secret).unwrap(),
+        }
+    }
+
+    fn connection_id(&self, ip: IpAddr, window: u64) -> i64 {
+        let mut mac = self.connection_key.clone();
+        match ip {
+            IpAddr::V4(ip) => mac.update(&ip.octets()),
+            IpAddr::V6(ip) => mac.update(&ip.octets()),
+        }
+        mac.update(&window.to_be_bytes());
+
+        let tag = mac.finalize().into_bytes();
+        i64::from_be_bytes(tag[..8].try_into().unwrap())
+    }
+
+    fn current_window() -> u64 {
+        time::OffsetDateTime::now_utc().unix_timestamp() as u64 / CONNECTION_ID_WINDOW
+    }
+
+    fn check_connection_id(&self, ip: IpAddr, connection_id: i64) -> Result<(), String> {
+        let window = Self::current_window();
+
+        if connection_id == self.connection_id(ip, window)
+            || connection_id == self.connection_id(ip, window.saturating_sub(1))
+        {
+            Ok(())
+        } else {
+            Err("invalid connection id".to_string())
+        }
+    }
+
+    pub async fn handle(&self, request: Request, sender: SocketAddr) -> Response {
+        let ip = sender.ip();
+
+        match request {
+            Request::Connect(r) => Response::Connect(ConnectResponse {
+                transaction_id: r.transaction_id,
+                connection_id: self.connection_id(ip, Self::current_window()),
+            }),
+            Request::Announce(r) => {
+                let transaction_id = r.transaction_id;
+                self.announce(r, ip)
+                    .await
+                    .map_or_else(|m| error(transaction_id, m), Response::Announce)
+            }
+            Request::Scrape(r) => {
+                let transaction_id = r.transaction_id;
+                self.scrape(r, ip)
+                    .await
+                    .map_or_else(|m| error(transaction_id, m), Response::Scrape)
+            }
+        }
+    }
+
+    async fn announce(
+        &self,
+        announce: AnnounceRequest,
+        sender_ip: IpAddr,
+    ) -> Result<AnnounceResponse, String> {
+        self.check_connection_id(sender_ip, announce.connection_id)?;
+
+        if let Some(reason) = self.bans.check(sender_ip, &announce.peer_id) {
+            return Err(format!("banned: {reason}"));
+        }
+
+        self.config
+            .client_policy
+            .check(&announce.peer_id)
+            .map_err(|reason| format!("client not allowed: {reason}"))?;
+
+        let uploaded = transfer_counter("uploaded", announce.uploaded)?;
+        let downloaded = transfer_counter("downloaded", announce.downloaded)?;
+        let left = transfer_counter("left", announce.left)?;
+
+        let passkey = passkey_from_url_data(&announce.extensions);
+        let user_id = authenticate_passkey(&self.config, &self.services, passkey.as_deref())
+            .await
+            .map_err(|reason| format!("unauthorized: {reason}"))?;
+
+        // The UDP protocol has no way to deliver warnings.
+        check_ratio(&self.config, &self.services, user_id, left)
+            .await
+            .map_err(|reason| format!("insufficient ratio: {reason}"))?;
+
+        if !is_info_hash_allowed(&self.config, &self.services, &announce.info_hash).await {
+            return Err(format!(
+                "info hash not allowed: {}",
+                announce.info_hash.to_hex()
+            ));
+        }
+
+        let cmd = UpdatePeerAnnounce {
+            info_hash: announce.info_hash.clone(),
+            peer_id: announce.peer_id.clone(),
+            ip: sender_ip,
+            port: announce.port as u16,
+            uploaded,
+            downloaded,
+            left,
+            event: announce.event.unwrap_or_default(),
+            update_timestamp: time::OffsetDateTime::now_utc(),
+            user_id,
+        };
+
+        self.services
+            .task_queue
+            .enqueue(&UpdatePeerAnnounceTask { cmd })
+            .await;
+
+        let active_after = time::OffsetDateTime::now_utc()
+            - std::time::Duration::from_secs(self.config.peer_activity_timeout as u64);
+
+        let num_want = announce
+            .num_want
+            .map_or(DEFAULT_NUM_WANT, |n| n.max(0) as usize)
+            .min(MAX_NUM_WANT);
+
+        // The UDP response format only carries IPv4 peers.
+        let peers = self
+            .services
+            .peer_repository
+            .get_peers(GetPeers {
+                info_hash: &announce.info_hash,
+                active_after: Some(active_after),
+            })
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|p| p.ip != sender_ip)
+            .filter_map(|p| match p.ip {
+                IpAddr::V4(ip) => Some((u32::from(ip) as i32, p.port as i16)),
+                IpAddr::V6(_) => None,
+            })
+            .take(num_want)
+            .collect();
+
+        let stats = self
+            .services
+            .peer_repository
+            .get_peer_statistics(GetPeerStatistics {
+                info_hashes: std::slice::from_ref(&announce.info_hash),
+                active_after,
+            })
+            .await
+            .unwrap()
+            .remove(&announce.info_hash);
+
+        Ok(AnnounceResponse {
+            transaction_id: announce.transaction_id,
+            interval: self.config.peer_announce_interval as i32,
+            leechers: stats.as_ref().map_or(0, |s| s.incomplete as i32),
+            seeders: stats.as_ref().map_or(0, |s| s.complete as i32),
+            peers,
+        })
+    }
+
+    async fn scrape(
+        &self,
+        request: ScrapeRequest,
+        sender_ip: IpAddr,
+    ) -> Result<ScrapeResponse, String> {
+        self.check_connection_id(sender_ip, request.connection_id)?;
+
+        // Scrape requests carry no URLData, so cannot be authenticated.
+        if self.config.private_mode {
+            return Err("unauthorized: passkey required".to_string());
+        }
+
+        let active_after = time::OffsetDateTime::now_utc()
+            - std::time::Duration::from_secs(self.config.peer_activity_timeout as u64);
+
+        let stats = self
+            .services
+            .peer_repository
+            .get_peer_statistics(GetPeerStatistics {
+                info_hashes: &request.info_hashes,
+                active_after,
+            })
+            .await
+            .unwrap();
+
+        // Results must be in the same order as the requested info hashes.
+        let data = request
+            .info_hashes
+            .iter()
+            .map(|info_hash| match stats.get(info_hash) {
+                Some(s) => InfoHashScrapeData {
+                    seeders: s.complete as i32,
+                    completed: s.downloaded as i32,
+                    leechers: s.incomplete as i32,
+                },
+                None => InfoHashScrapeData {
+                    seeders: 0,
+                    completed: 0,
+                    leechers: 0,
+                },
+            })
+            .collect();
+
+        Ok(ScrapeResponse {
+            transaction_id: request.transaction_id,
+            data,
+        })
+    }
+}
+
+fn error(transaction_id: i32, message: String) -> Response {
+    Response::Error(ErrorResponse {
+        transaction_id,
+        message,
+    })
+}
+
+/// The passkey in an announce's URLData, which BEP 41 lets clients split
+/// across several options. Announce URLs take the form
+/// `/<passkey>/announce`.
+fn passkey_from_url_data(extensions: &[Extension]) -> Option<String> {
+    let url_data: Vec<u8> = extensions
+        .iter()
+        .filter_map(|e| match e {
+            Extension::UrlData(bs) => Some(bs.as_slice()),
+            _ => None,
+        })
+        .flatten()
+        .copied()
+        .collect();
+    let url_data = String::from_utf8(url_data).ok()?;
+
+    let path = url_data.split('?').next()?;
+
+    match path.split('/').collect::<Vec<_>>()[..] {
+        ["", passkey, "announce"] if !passkey.is_empty() => Some(passkey.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hanekawa_common::testing::{self, Memory};
+
+    fn udp_service(secret: &str) -> UdpTrackerService {
+        let services = Memory::new().services();
+        let bans = BanList::new(services.clone());
+        let config = Config {
+            udp_connection_secret: Some(secret.to_string()),
+            ..testing::config()
+        };
+
+        UdpTrackerService::new(&config, services, bans)
+    }
+
+    #[test]
+    fn shares_connection_ids_between_processes() {
+        let ip = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        let window = UdpTrackerService::current_window();
+        let connection_id = udp_service("secret").connection_id(ip, window);
+
+        assert!(udp_service("secret")
+            .check_connection_id(ip, connection_id)
+            .is_ok());
+        assert!(udp_service("other")
+            .check_connection_id(ip, connection_id)
+            .is_err());
+        assert!(udp_service("secret")
+            .check_connection_id(IpAddr::V4(std::net::Ipv4Addr::BROADCAST), connection_id)
+            .is_err());
+    }
+
+    #[test]
+    fn extracts_passkeys_from_url_data() {
+        let url_data = |parts: &[&[u8]]| -> Vec<Extension> {
+            parts
+                .iter()
+                .map(|bs| Extension::UrlData(bs.to_vec()))
+                .collect()
+        };
+
+        assert_eq!(
+            Some("abc123".to_string()),
+            passkey_from_url_data(&url_data(&[b"/abc", b"123/announce?a=1"]))
+        );
+        assert_eq!(
+            Some("abc123".to_string()),
+            passkey_from_url_data(&[
+                Extension::Nop,
+                Extension::UrlData(b"/abc123/announce".to_vec())
+            ])
+        );
+        // "é" is split between two options.
+        assert_eq!(
+            Some("caf\u{e9}".to_string()),
+            passkey_from_url_data(&url_data(&[b"/caf\xc3", b"\xa9/announce"]))
+        );
+        assert_eq!(None, passkey_from_url_data(&url_data(&[b"/\xff/announce"])));
+        assert_eq!(None, passkey_from_url_data(&url_data(&[b"/announce"])));
+        assert_eq!(None, passkey_from_url_data(&url_data(&[b"//announce"])));
+        assert_eq!(None, passkey_from_url_data(&[]));
+    }
+}